@@ -0,0 +1,50 @@
+// With the `embedded` feature, compress the SQLite file named by
+// WILAYAH_EMBED_DATABASE (default data/wilayah_indonesia.db) into OUT_DIR,
+// where src/embedded.rs includes it.
+fn main() {
+    #[cfg(feature = "embedded")]
+    embed_database();
+}
+
+#[cfg(feature = "embedded")]
+fn embed_database() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    println!("cargo:rerun-if-env-changed=WILAYAH_EMBED_DATABASE");
+    let path = std::env::var("WILAYAH_EMBED_DATABASE")
+        .unwrap_or_else(|_| "data/wilayah_indonesia.db".to_string());
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(path);
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    let mut image = std::fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "the embedded feature needs the database at {} (set WILAYAH_EMBED_DATABASE): {}",
+            path.display(),
+            e
+        )
+    });
+    assert!(
+        image.starts_with(b"SQLite format 3\0"),
+        "{} is not an SQLite database",
+        path.display()
+    );
+    let wal = PathBuf::from(format!("{}-wal", path.display()));
+    if std::fs::metadata(&wal).is_ok_and(|wal| wal.len() > 0) {
+        println!(
+            "cargo:warning={} is not checkpointed, its changes are not embedded",
+            wal.display()
+        );
+    }
+    // In-memory databases have no WAL; mark the image as a rollback journal
+    // database (file format versions at offsets 18 and 19)
+    image[18] = 1;
+    image[19] = 1;
+
+    let out = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("wilayah.db.gz");
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&image).unwrap();
+    std::fs::write(out, encoder.finish().unwrap()).unwrap();
+}
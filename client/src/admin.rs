@@ -0,0 +1,68 @@
+use reqwest::Method;
+
+use wilayah::models::{
+    CacheStats, CreateWebhook, DeliveryStatus, QualityReport, Webhook, WebhookDelivery,
+};
+
+use crate::client::{Call, Client};
+use crate::error::ClientError;
+
+// Every /admin route needs the server's ADMIN_TOKEN, given to
+// ClientBuilder::bearer_token
+impl Client {
+    pub async fn quality_report(&self) -> Result<QualityReport, ClientError> {
+        self.send(Call::get("/admin/quality")).await?.json()
+    }
+
+    pub async fn cache_stats(&self) -> Result<CacheStats, ClientError> {
+        self.send(Call::get("/admin/cache")).await?.json()
+    }
+
+    // The SQLite database file, compacted with VACUUM INTO when `vacuum`
+    pub async fn backup(&self, vacuum: bool) -> Result<Vec<u8>, ClientError> {
+        let call = Call::get("/admin/backup").query("vacuum", vacuum);
+        Ok(self.send(call).await?.body)
+    }
+
+    pub async fn get_webhooks(&self) -> Result<Vec<Webhook>, ClientError> {
+        self.send(Call::get("/admin/webhooks")).await?.json()
+    }
+
+    pub async fn get_one_webhook(&self, id: i64) -> Result<Option<Webhook>, ClientError> {
+        match self
+            .send_optional(Call::get(format!("/admin/webhooks/{}", id)))
+            .await?
+        {
+            Some(reply) => Ok(Some(reply.json()?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn create_webhook(&self, webhook: &CreateWebhook) -> Result<Webhook, ClientError> {
+        let call = Call::new(Method::POST, "/admin/webhooks").json(webhook)?;
+        self.send(call).await?.json()
+    }
+
+    pub async fn delete_webhook(&self, id: i64) -> Result<(), ClientError> {
+        let call = Call::new(Method::DELETE, format!("/admin/webhooks/{}", id));
+        self.send(call).await?;
+        Ok(())
+    }
+
+    // Newest first; the server defaults to 100 and caps `limit` at 1000
+    pub async fn get_webhook_deliveries(
+        &self,
+        id: i64,
+        status: Option<DeliveryStatus>,
+        limit: Option<usize>,
+    ) -> Result<Vec<WebhookDelivery>, ClientError> {
+        let mut call = Call::get(format!("/admin/webhooks/{}/deliveries", id));
+        if let Some(status) = status {
+            call = call.query("status", status.as_str());
+        }
+        if let Some(limit) = limit {
+            call = call.query("limit", limit);
+        }
+        self.send(call).await?.json()
+    }
+}
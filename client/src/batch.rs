@@ -0,0 +1,49 @@
+use reqwest::Method;
+use std::collections::BTreeMap;
+
+use wilayah::models::{
+    BatchItem, BatchResolution, BatchResolveRequest, BatchResolveResponse, BulkUpsertLine,
+    BulkUpsertResponse,
+};
+
+use crate::client::{Call, Client};
+use crate::error::ClientError;
+
+impl Client {
+    // Resolve up to 50,000 codes and ids in one request. Results are keyed by
+    // the code, or by "<level>:<id>" for id items.
+    pub async fn batch_resolve(
+        &self,
+        items: &[BatchItem],
+    ) -> Result<BTreeMap<String, BatchResolution>, ClientError> {
+        let body = BatchResolveRequest {
+            items: items.to_vec(),
+        };
+        let call = Call::new(Method::POST, "/batch/resolve")
+            .json(&body)?
+            .idempotent();
+        Ok(self
+            .send(call)
+            .await?
+            .json::<BatchResolveResponse>()?
+            .results)
+    }
+
+    // Upsert rows by code, sent as NDJSON. Invalid rows are reported in the
+    // results instead of failing the whole request.
+    pub async fn bulk_upsert(
+        &self,
+        lines: &[BulkUpsertLine],
+    ) -> Result<BulkUpsertResponse, ClientError> {
+        let mut body = Vec::new();
+        for line in lines {
+            serde_json::to_writer(&mut body, line)?;
+            body.push(b'\n');
+        }
+        // Upserts by code converge, so repeating the request is harmless
+        let call = Call::new(Method::POST, "/batch/upsert")
+            .body("application/x-ndjson", body)
+            .idempotent();
+        self.send(call).await?.json()
+    }
+}
@@ -0,0 +1,171 @@
+use log::warn;
+
+use wilayah::models::{Change, ChangeFeed, ChangeFilter};
+
+use crate::client::{Call, Client};
+use crate::error::ClientError;
+
+impl Client {
+    // One page of the change feed after the `since` token
+    pub async fn get_changes(
+        &self,
+        since: &str,
+        limit: Option<usize>,
+    ) -> Result<ChangeFeed, ClientError> {
+        let mut call = Call::get("/changes").query("since", since);
+        if let Some(limit) = limit {
+            call = call.query("limit", limit);
+        }
+        self.send(call).await?.json()
+    }
+
+    // Page through the change feed from `since` ("0" for the beginning):
+    //
+    //     let mut pages = client.changes("0");
+    //     while let Some(changes) = pages.next_page().await? { ... }
+    //     save(pages.since());
+    pub fn changes(&self, since: &str) -> ChangePages {
+        ChangePages {
+            client: self.clone(),
+            since: since.to_string(),
+            limit: None,
+            done: false,
+        }
+    }
+
+    // Live changes from the /events stream. With `since` the stream first
+    // replays the changes after that seq. Dropped connections are reopened
+    // after the last received change.
+    pub async fn events(
+        &self,
+        filter: &ChangeFilter,
+        since: Option<i64>,
+    ) -> Result<EventStream, ClientError> {
+        let mut events = EventStream {
+            client: self.clone(),
+            filter: filter.clone(),
+            last_seq: since,
+            response: None,
+            buffer: Vec::new(),
+        };
+        events.connect().await?;
+        Ok(events)
+    }
+}
+
+pub struct ChangePages {
+    client: Client,
+    since: String,
+    limit: Option<usize>,
+    done: bool,
+}
+
+impl ChangePages {
+    // Changes per page, the server caps it at 5000
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    // Token to resume from after the pages read so far
+    pub fn since(&self) -> &str {
+        &self.since
+    }
+
+    // None once the feed is caught up
+    pub async fn next_page(&mut self) -> Result<Option<Vec<Change>>, ClientError> {
+        if self.done {
+            return Ok(None);
+        }
+        let feed = self.client.get_changes(&self.since, self.limit).await?;
+        self.since = feed.next;
+        self.done = !feed.has_more;
+        if feed.changes.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(feed.changes))
+    }
+}
+
+pub struct EventStream {
+    client: Client,
+    filter: ChangeFilter,
+    last_seq: Option<i64>,
+    response: Option<reqwest::Response>,
+    // Received bytes not yet split into events
+    buffer: Vec<u8>,
+}
+
+impl EventStream {
+    // Seq of the last change returned, to resume from later
+    pub fn last_seq(&self) -> Option<i64> {
+        self.last_seq
+    }
+
+    // Wait for the next change
+    pub async fn next(&mut self) -> Result<Change, ClientError> {
+        loop {
+            if let Some(change) = self.next_buffered()? {
+                self.last_seq = Some(change.seq);
+                return Ok(change);
+            }
+
+            let chunk = match self.response.as_mut() {
+                Some(response) => response.chunk().await,
+                None => Ok(None),
+            };
+            match chunk {
+                Ok(Some(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Ok(None) => self.connect().await?,
+                Err(e) => {
+                    warn!("Event stream interrupted, reconnecting: {}", e);
+                    self.connect().await?;
+                }
+            }
+        }
+    }
+
+    async fn connect(&mut self) -> Result<(), ClientError> {
+        self.buffer.clear();
+        let levels: Vec<&str> = self.filter.levels.iter().map(|l| l.as_str()).collect();
+        let mut call = Call::get("/events");
+        if !levels.is_empty() {
+            call = call.query("level", levels.join(","));
+        }
+        if let Some(prefix) = &self.filter.code_prefix {
+            call = call.query("code_prefix", prefix);
+        }
+        if let Some(seq) = self.last_seq {
+            call = call.query("since", seq);
+        }
+
+        let response = self.client.open(&call).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(ClientError::Api {
+                status,
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+        self.response = Some(response);
+        Ok(())
+    }
+
+    // Parse the first complete event out of the buffer. Comments (keepalives)
+    // and `retry:` lines are skipped.
+    fn next_buffered(&mut self) -> Result<Option<Change>, ClientError> {
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let frame: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let frame = String::from_utf8_lossy(&frame);
+            let data: Vec<&str> = frame
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.trim_start())
+                .collect();
+            if !data.is_empty() {
+                return Ok(Some(serde_json::from_str(&data.join("\n"))?));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,457 @@
+use log::warn;
+use lru::LruCache;
+use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use wilayah::models::{ErrorResponse, HasChildrenResponse, ValidationErrorResponse};
+
+use crate::error::ClientError;
+
+// How often and how long to wait before retrying a failed request. Only
+// requests that are safe to repeat are retried: reads, PUTs and DELETEs, and
+// anything that never reached the server.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    // Delay before the first retry, doubled for every further one
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    // Exponential backoff with up to 25% jitter, or the server's Retry-After
+    fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let delay = retry_after.unwrap_or_else(|| {
+            let backoff = self.base_delay.saturating_mul(1 << attempt.min(16));
+            let jitter = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .subsec_nanos() as u64
+                % (backoff.as_millis() as u64 / 4 + 1);
+            backoff + Duration::from_millis(jitter)
+        });
+        delay.min(self.max_delay)
+    }
+}
+
+pub struct ClientBuilder {
+    base_url: String,
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    cache_capacity: Option<usize>,
+}
+
+impl ClientBuilder {
+    // Sent as `Authorization: Bearer <token>`, e.g. for an API gateway in
+    // front of the service
+    pub fn bearer_token(self, token: &str) -> Self {
+        self.header("Authorization", &format!("Bearer {}", token))
+    }
+
+    // Extra header sent with every request, e.g. an API key
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // Timeout of a single attempt, retries not included
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    // Keep up to `capacity` GET responses. They are revalidated with
+    // If-None-Match and served as they are when the server is unreachable.
+    pub fn cache(mut self, capacity: usize) -> Self {
+        self.cache_capacity = Some(capacity);
+        self
+    }
+
+    pub fn build(self) -> Result<Client, ClientError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        for (name, value) in &self.headers {
+            let name = HeaderName::try_from(name.as_str())
+                .map_err(|_| ClientError::Config(format!("invalid header name {}", name)))?;
+            let value = HeaderValue::try_from(value.as_str())
+                .map_err(|_| ClientError::Config(format!("invalid value of header {}", name)))?;
+            headers.insert(name, value);
+        }
+
+        let mut http = reqwest::Client::builder().default_headers(headers);
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+
+        let base_url = self.base_url.trim_end_matches('/').to_string();
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            return Err(ClientError::Config(format!(
+                "base URL {} must be an http:// or https:// URL",
+                base_url
+            )));
+        }
+
+        Ok(Client {
+            http: http.build()?,
+            base_url: base_url.into(),
+            retry: self.retry,
+            cache: self.cache_capacity.map(|capacity| {
+                let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+                Arc::new(Mutex::new(LruCache::new(capacity)))
+            }),
+        })
+    }
+}
+
+// Last successful response to a GET, keyed by path and query
+#[derive(Clone)]
+struct CachedReply {
+    etag: String,
+    reply: Reply,
+}
+
+// Typed async client of the wilayah-service HTTP API. Cheap to clone; clones
+// share the connection pool and the cache.
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: Arc<str>,
+    retry: RetryPolicy,
+    cache: Option<Arc<Mutex<LruCache<String, CachedReply>>>>,
+}
+
+// A request, kept around so it can be sent again on retry
+pub(crate) struct Call {
+    method: Method,
+    path: String,
+    query: Vec<(String, String)>,
+    body: Option<(&'static str, Vec<u8>)>,
+    if_match: Option<i64>,
+    // Safe to send twice, e.g. a POST that only reads
+    idempotent: bool,
+}
+
+impl Call {
+    pub(crate) fn new(method: Method, path: impl Into<String>) -> Call {
+        let idempotent = method != Method::POST && method != Method::PATCH;
+        Call {
+            method,
+            path: path.into(),
+            query: Vec::new(),
+            body: None,
+            if_match: None,
+            idempotent,
+        }
+    }
+
+    pub(crate) fn get(path: impl Into<String>) -> Call {
+        Call::new(Method::GET, path)
+    }
+
+    pub(crate) fn query(mut self, name: &str, value: impl ToString) -> Call {
+        self.query.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub(crate) fn json<T: Serialize>(mut self, body: &T) -> Result<Call, ClientError> {
+        self.body = Some(("application/json", serde_json::to_vec(body)?));
+        Ok(self)
+    }
+
+    pub(crate) fn body(mut self, content_type: &'static str, body: Vec<u8>) -> Call {
+        self.body = Some((content_type, body));
+        self
+    }
+
+    pub(crate) fn if_match(mut self, version: Option<i64>) -> Call {
+        self.if_match = version;
+        self
+    }
+
+    pub(crate) fn idempotent(mut self) -> Call {
+        self.idempotent = true;
+        self
+    }
+
+    fn cache_key(&self) -> String {
+        let query: Vec<String> = self
+            .query
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        format!("{}?{}", self.path, query.join("&"))
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Reply {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    pub(crate) fn json<T: DeserializeOwned>(&self) -> Result<T, ClientError> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    // Row version from the ETag of single-row responses
+    pub(crate) fn version(&self) -> Option<i64> {
+        self.headers
+            .get(header::ETAG)?
+            .to_str()
+            .ok()?
+            .trim_matches('"')
+            .parse()
+            .ok()
+    }
+}
+
+impl Client {
+    // `base_url` is where the service is mounted, e.g. http://localhost:8080
+    pub fn builder(base_url: &str) -> ClientBuilder {
+        ClientBuilder {
+            base_url: base_url.to_string(),
+            headers: Vec::new(),
+            timeout: None,
+            retry: RetryPolicy::default(),
+            cache_capacity: None,
+        }
+    }
+
+    pub fn new(base_url: &str) -> Result<Client, ClientError> {
+        Client::builder(base_url).build()
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    // Send `call`, retrying per the policy. Responses with a status >= 400 are
+    // turned into errors.
+    pub(crate) async fn send(&self, call: Call) -> Result<Reply, ClientError> {
+        let reply = self.send_raw(&call).await?;
+        if reply.status.is_client_error() || reply.status.is_server_error() {
+            return Err(error_of(&reply));
+        }
+        Ok(reply)
+    }
+
+    // Like `send`, but a 404 is None
+    pub(crate) async fn send_optional(&self, call: Call) -> Result<Option<Reply>, ClientError> {
+        match self.send(call).await {
+            Ok(reply) => Ok(Some(reply)),
+            Err(ClientError::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(crate) async fn open(&self, call: &Call) -> Result<reqwest::Response, reqwest::Error> {
+        let mut attempt = 0;
+        loop {
+            let result = self.request(call, None).send().await;
+            match self.retry_delay(call, &result, attempt) {
+                Some(delay) => {
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                }
+                None => return result,
+            }
+        }
+    }
+
+    async fn send_raw(&self, call: &Call) -> Result<Reply, ClientError> {
+        let cache = self.cache.as_ref().filter(|_| call.method == Method::GET);
+        let key = call.cache_key();
+        let cached = cache.and_then(|cache| cache.lock().unwrap().get(&key).cloned());
+
+        let mut attempt = 0;
+        let result = loop {
+            let etag = cached.as_ref().map(|cached| cached.etag.as_str());
+            let result = self.request(call, etag).send().await;
+            match self.retry_delay(call, &result, attempt) {
+                Some(delay) => {
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                }
+                None => break result,
+            }
+        };
+
+        let res = match result {
+            Ok(res) => res,
+            Err(e) => {
+                return match cached {
+                    Some(cached) => {
+                        warn!("Serving cached {} after error: {}", call.path, e);
+                        Ok(cached.reply)
+                    }
+                    None => Err(e.into()),
+                }
+            }
+        };
+        let reply = Reply {
+            status: res.status(),
+            headers: res.headers().clone(),
+            body: res.bytes().await?.to_vec(),
+        };
+
+        match (cache, cached) {
+            (Some(_), Some(cached)) if reply.status == StatusCode::NOT_MODIFIED => Ok(cached.reply),
+            (Some(_), Some(cached)) if reply.status.is_server_error() => {
+                warn!("Serving cached {} after {}", call.path, reply.status);
+                Ok(cached.reply)
+            }
+            (Some(cache), _) if reply.status == StatusCode::OK => {
+                let etag = reply
+                    .headers
+                    .get(header::ETAG)
+                    .and_then(|etag| etag.to_str().ok());
+                if let Some(etag) = etag {
+                    let cached = CachedReply {
+                        etag: etag.to_string(),
+                        reply: reply.clone(),
+                    };
+                    cache.lock().unwrap().put(key, cached);
+                }
+                Ok(reply)
+            }
+            _ => Ok(reply),
+        }
+    }
+
+    fn request(&self, call: &Call, etag: Option<&str>) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", self.base_url, call.path);
+        let mut req = self
+            .http
+            .request(call.method.clone(), url)
+            .query(&call.query);
+        if let Some(etag) = etag {
+            req = req.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(version) = call.if_match {
+            req = req.header(header::IF_MATCH, format!("\"{}\"", version));
+        }
+        if let Some((content_type, body)) = &call.body {
+            req = req
+                .header(header::CONTENT_TYPE, *content_type)
+                .body(body.clone());
+        }
+        req
+    }
+
+    // Delay before the next attempt, None when `result` is final
+    fn retry_delay(
+        &self,
+        call: &Call,
+        result: &Result<reqwest::Response, reqwest::Error>,
+        attempt: u32,
+    ) -> Option<Duration> {
+        if attempt >= self.retry.max_retries {
+            return None;
+        }
+        let retry_after = match result {
+            Ok(res) => match res.status() {
+                StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+                    if call.idempotent =>
+                {
+                    res.headers()
+                        .get(header::RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.parse().ok())
+                        .map(Duration::from_secs)
+                }
+                _ => return None,
+            },
+            // A refused connection never reached the server
+            Err(e) if e.is_connect() => None,
+            Err(e) if e.is_timeout() && call.idempotent => None,
+            Err(_) => return None,
+        };
+        Some(self.retry.delay(attempt, retry_after))
+    }
+}
+
+fn error_of(reply: &Reply) -> ClientError {
+    match reply.status {
+        StatusCode::NOT_FOUND => return ClientError::NotFound,
+        StatusCode::CONFLICT => {
+            if let Ok(body) = reply.json::<HasChildrenResponse>() {
+                return ClientError::HasChildren(body.children);
+            }
+        }
+        StatusCode::PRECONDITION_FAILED => {
+            if let Some(version) = reply.version() {
+                return ClientError::PreconditionFailed(version);
+            }
+        }
+        StatusCode::UNPROCESSABLE_ENTITY => {
+            if let Ok(body) = reply.json::<ValidationErrorResponse>() {
+                return ClientError::Validation(body.fields);
+            }
+        }
+        _ => {}
+    }
+    let message = match reply.json::<ErrorResponse>() {
+        Ok(body) => body.error,
+        Err(_) => String::from_utf8_lossy(&reply.body).into_owned(),
+    };
+    ClientError::Api {
+        status: reply.status,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially_up_to_max_delay() {
+        let retry = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+        let first = retry.delay(0, None);
+        assert!(first >= Duration::from_millis(100) && first <= Duration::from_millis(125));
+        let third = retry.delay(2, None);
+        assert!(third >= Duration::from_millis(400) && third <= Duration::from_millis(500));
+        assert_eq!(retry.delay(9, None), Duration::from_secs(1));
+        assert_eq!(
+            retry.delay(0, Some(Duration::from_secs(30))),
+            Duration::from_secs(1)
+        );
+    }
+}
@@ -0,0 +1,19 @@
+use reqwest::Method;
+use serde_json::{json, Value};
+
+use crate::client::{Call, Client};
+use crate::error::ClientError;
+
+impl Client {
+    // The server's OpenAPI 3.1 document
+    pub async fn openapi(&self) -> Result<Value, ClientError> {
+        self.send(Call::get("/openapi.json")).await?.json()
+    }
+
+    // Run a GraphQL query and return the whole response, `data` and `errors`
+    pub async fn graphql(&self, query: &str, variables: Value) -> Result<Value, ClientError> {
+        let body = json!({ "query": query, "variables": variables });
+        let call = Call::new(Method::POST, "/graphql").json(&body)?;
+        self.send(call).await?.json()
+    }
+}
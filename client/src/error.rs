@@ -0,0 +1,30 @@
+use reqwest::StatusCode;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+use wilayah::models::FieldError;
+
+// Mirrors wilayah::DatabaseError so code can switch between the library and
+// the HTTP API without rewriting its error handling
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Invalid response body: {0}")]
+    Decode(#[from] serde_json::Error),
+    #[error("Invalid client configuration: {0}")]
+    Config(String),
+    #[error("No data found")]
+    NotFound,
+    #[error("Validation failed")]
+    Validation(Vec<FieldError>),
+    // Number of live rows per child table blocking a non-cascading delete
+    #[error("Row still has children")]
+    HasChildren(BTreeMap<String, usize>),
+    // If-Match did not match; carries the current version of the row
+    #[error("Row was modified, current version is {0}")]
+    PreconditionFailed(i64),
+    // Any other error response, with the `error` field of its body
+    #[error("{status}: {message}")]
+    Api { status: StatusCode, message: String },
+}
@@ -0,0 +1,42 @@
+//! Async client of the wilayah-service HTTP API, with typed methods for every
+//! route, retries with backoff, paging through the change feed and an
+//! optional local cache. Requests and responses use the `wilayah` models.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), wilayah_client::ClientError> {
+//! use std::time::Duration;
+//! use wilayah_client::Client;
+//!
+//! let client = Client::builder("http://localhost:8080")
+//!     .bearer_token("rahasia")
+//!     .timeout(Duration::from_secs(10))
+//!     .cache(1_000)
+//!     .build()?;
+//!
+//! for province in client.get_all_provinces().await? {
+//!     println!("{} {}", province.code, province.name);
+//! }
+//! let village = client.get_village_by_code("32.04.08.2002").await?;
+//!
+//! let mut pages = client.changes("0").limit(1_000);
+//! while let Some(changes) = pages.next_page().await? {
+//!     println!("{} changes", changes.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod admin;
+mod batch;
+mod changes;
+mod client;
+mod docs;
+mod error;
+mod regions;
+mod search;
+
+pub use changes::{ChangePages, EventStream};
+pub use client::{Client, ClientBuilder, RetryPolicy};
+pub use error::ClientError;
+pub use wilayah::models;
+pub use wilayah::Level;
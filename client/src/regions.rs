@@ -0,0 +1,299 @@
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use wilayah::models::{
+    CreatedId, DeleteOptions, DeletePlan, District, FullAdminData, Province, Regency,
+    UpsertRequest, UpsertResult, Village,
+};
+use wilayah::Level;
+
+use crate::client::{Call, Client};
+use crate::error::ClientError;
+
+// The per-level routes, named after the WilayahRepository methods. Writes
+// take the version the change is based on and send it as If-Match; they
+// return the new version of the row.
+impl Client {
+    pub async fn get_all_provinces(&self) -> Result<Vec<Province>, ClientError> {
+        self.get_list("/provinces".to_string()).await
+    }
+
+    pub async fn get_one_province(&self, id: i64) -> Result<Option<Province>, ClientError> {
+        self.get_row(format!("/provinces/{}", id)).await
+    }
+
+    pub async fn get_province_by_code(&self, code: &str) -> Result<Option<Province>, ClientError> {
+        self.get_row(format!("/provinces/code/{}", code)).await
+    }
+
+    pub async fn create_province(&self, province: &Province) -> Result<i64, ClientError> {
+        self.create("/provinces", province).await
+    }
+
+    pub async fn update_province(
+        &self,
+        id: i64,
+        province: &Province,
+        if_match: Option<i64>,
+    ) -> Result<i64, ClientError> {
+        self.update(
+            Method::PUT,
+            format!("/provinces/{}", id),
+            province,
+            if_match,
+        )
+        .await
+    }
+
+    // Only the fields present in `patch` change, e.g. {"name": "JAWA BARAT"}
+    pub async fn patch_province(
+        &self,
+        id: i64,
+        patch: &Value,
+        if_match: Option<i64>,
+    ) -> Result<i64, ClientError> {
+        self.update(Method::PATCH, format!("/provinces/{}", id), patch, if_match)
+            .await
+    }
+
+    // The plan is only returned for dry runs
+    pub async fn delete_province(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<i64>,
+    ) -> Result<Option<DeletePlan>, ClientError> {
+        self.delete(format!("/provinces/{}", id), options, if_match)
+            .await
+    }
+
+    pub async fn get_regencies_by_province(
+        &self,
+        province_id: i64,
+    ) -> Result<Vec<Regency>, ClientError> {
+        self.get_list(format!("/provinces/{}/regencies", province_id))
+            .await
+    }
+
+    pub async fn get_one_regency(&self, id: i64) -> Result<Option<Regency>, ClientError> {
+        self.get_row(format!("/regencies/{}", id)).await
+    }
+
+    pub async fn get_regency_by_code(&self, code: &str) -> Result<Option<Regency>, ClientError> {
+        self.get_row(format!("/regencies/code/{}", code)).await
+    }
+
+    pub async fn create_regency(&self, regency: &Regency) -> Result<i64, ClientError> {
+        self.create("/regencies", regency).await
+    }
+
+    pub async fn update_regency(
+        &self,
+        id: i64,
+        regency: &Regency,
+        if_match: Option<i64>,
+    ) -> Result<i64, ClientError> {
+        self.update(Method::PUT, format!("/regencies/{}", id), regency, if_match)
+            .await
+    }
+
+    pub async fn patch_regency(
+        &self,
+        id: i64,
+        patch: &Value,
+        if_match: Option<i64>,
+    ) -> Result<i64, ClientError> {
+        self.update(Method::PATCH, format!("/regencies/{}", id), patch, if_match)
+            .await
+    }
+
+    pub async fn delete_regency(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<i64>,
+    ) -> Result<Option<DeletePlan>, ClientError> {
+        self.delete(format!("/regencies/{}", id), options, if_match)
+            .await
+    }
+
+    pub async fn get_districts_by_regency(
+        &self,
+        regency_id: i64,
+    ) -> Result<Vec<District>, ClientError> {
+        self.get_list(format!("/regencies/{}/districts", regency_id))
+            .await
+    }
+
+    pub async fn get_one_district(&self, id: i64) -> Result<Option<District>, ClientError> {
+        self.get_row(format!("/districts/{}", id)).await
+    }
+
+    pub async fn get_district_by_code(&self, code: &str) -> Result<Option<District>, ClientError> {
+        self.get_row(format!("/districts/code/{}", code)).await
+    }
+
+    pub async fn create_district(&self, district: &District) -> Result<i64, ClientError> {
+        self.create("/districts", district).await
+    }
+
+    pub async fn update_district(
+        &self,
+        id: i64,
+        district: &District,
+        if_match: Option<i64>,
+    ) -> Result<i64, ClientError> {
+        self.update(
+            Method::PUT,
+            format!("/districts/{}", id),
+            district,
+            if_match,
+        )
+        .await
+    }
+
+    pub async fn patch_district(
+        &self,
+        id: i64,
+        patch: &Value,
+        if_match: Option<i64>,
+    ) -> Result<i64, ClientError> {
+        self.update(Method::PATCH, format!("/districts/{}", id), patch, if_match)
+            .await
+    }
+
+    pub async fn delete_district(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<i64>,
+    ) -> Result<Option<DeletePlan>, ClientError> {
+        self.delete(format!("/districts/{}", id), options, if_match)
+            .await
+    }
+
+    pub async fn get_villages_by_district(
+        &self,
+        district_id: i64,
+    ) -> Result<Vec<Village>, ClientError> {
+        self.get_list(format!("/districts/{}/villages", district_id))
+            .await
+    }
+
+    pub async fn get_one_village(&self, id: i64) -> Result<Option<Village>, ClientError> {
+        self.get_row(format!("/villages/{}", id)).await
+    }
+
+    pub async fn get_village_by_code(&self, code: &str) -> Result<Option<Village>, ClientError> {
+        self.get_row(format!("/villages/code/{}", code)).await
+    }
+
+    pub async fn create_village(&self, village: &Village) -> Result<i64, ClientError> {
+        self.create("/villages", village).await
+    }
+
+    pub async fn update_village(
+        &self,
+        id: i64,
+        village: &Village,
+        if_match: Option<i64>,
+    ) -> Result<i64, ClientError> {
+        self.update(Method::PUT, format!("/villages/{}", id), village, if_match)
+            .await
+    }
+
+    pub async fn patch_village(
+        &self,
+        id: i64,
+        patch: &Value,
+        if_match: Option<i64>,
+    ) -> Result<i64, ClientError> {
+        self.update(Method::PATCH, format!("/villages/{}", id), patch, if_match)
+            .await
+    }
+
+    pub async fn delete_village(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<i64>,
+    ) -> Result<Option<DeletePlan>, ClientError> {
+        self.delete(format!("/villages/{}", id), options, if_match)
+            .await
+    }
+
+    pub async fn get_full_admin_data(
+        &self,
+        village_id: i64,
+    ) -> Result<Option<FullAdminData>, ClientError> {
+        self.get_row(format!("/villages/{}/details", village_id))
+            .await
+    }
+
+    // Create or rename the region with `code`; the parent follows from the code
+    pub async fn upsert_by_code(
+        &self,
+        level: Level,
+        code: &str,
+        name: &str,
+    ) -> Result<UpsertResult, ClientError> {
+        let body = UpsertRequest {
+            name: name.to_string(),
+        };
+        let call =
+            Call::new(Method::PUT, format!("/{}/code/{}", level.table(), code)).json(&body)?;
+        self.send(call).await?.json()
+    }
+
+    async fn get_list<T: DeserializeOwned>(&self, path: String) -> Result<Vec<T>, ClientError> {
+        self.send(Call::get(path)).await?.json()
+    }
+
+    async fn get_row<T: DeserializeOwned>(&self, path: String) -> Result<Option<T>, ClientError> {
+        match self.send_optional(Call::get(path)).await? {
+            Some(reply) => Ok(Some(reply.json()?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn create<T: Serialize>(&self, path: &str, row: &T) -> Result<i64, ClientError> {
+        let reply = self.send(Call::new(Method::POST, path).json(row)?).await?;
+        Ok(reply.json::<CreatedId>()?.id)
+    }
+
+    async fn update<T: Serialize>(
+        &self,
+        method: Method,
+        path: String,
+        body: &T,
+        if_match: Option<i64>,
+    ) -> Result<i64, ClientError> {
+        let call = Call::new(method, path).json(body)?.if_match(if_match);
+        let reply = self.send(call).await?;
+        reply.version().ok_or_else(|| ClientError::Api {
+            status: reply.status,
+            message: "response has no ETag".to_string(),
+        })
+    }
+
+    async fn delete(
+        &self,
+        path: String,
+        options: &DeleteOptions,
+        if_match: Option<i64>,
+    ) -> Result<Option<DeletePlan>, ClientError> {
+        let call = Call::new(Method::DELETE, path)
+            .query("cascade", options.cascade)
+            .query("dry_run", options.dry_run)
+            .query("hard", options.hard)
+            .if_match(if_match);
+        let reply = self.send(call).await?;
+        if options.dry_run {
+            Ok(Some(reply.json()?))
+        } else {
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use reqwest::Method;
+
+use wilayah::models::{FormatAddressRequest, FormattedAddress, ParseAddressRequest, ParsedAddress};
+
+use crate::client::{Call, Client};
+use crate::error::ClientError;
+
+// Routes of the server's `search` feature; a server built without it answers
+// them with NotFound
+impl Client {
+    // Append the region columns to a CSV keyed by the code or name in
+    // `column`, and return the enriched CSV
+    pub async fn enrich_csv(&self, csv: Vec<u8>, column: &str) -> Result<Vec<u8>, ClientError> {
+        let call = Call::new(Method::POST, "/enrich")
+            .query("column", column)
+            .body("text/csv", csv)
+            .idempotent();
+        Ok(self.send(call).await?.body)
+    }
+
+    pub async fn parse_address(&self, address: &str) -> Result<ParsedAddress, ClientError> {
+        let body = ParseAddressRequest {
+            address: address.to_string(),
+        };
+        let call = Call::new(Method::POST, "/address/parse")
+            .json(&body)?
+            .idempotent();
+        self.send(call).await?.json()
+    }
+
+    pub async fn format_address(
+        &self,
+        request: &FormatAddressRequest,
+    ) -> Result<Option<FormattedAddress>, ClientError> {
+        let call = Call::new(Method::POST, "/address/format")
+            .json(request)?
+            .idempotent();
+        match self.send_optional(call).await? {
+            Some(reply) => Ok(Some(reply.json()?)),
+            None => Ok(None),
+        }
+    }
+}
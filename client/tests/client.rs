@@ -0,0 +1,404 @@
+// The client against wilayah-service running in-process on a random port
+use actix_web::dev::ServerHandle;
+use actix_web::{web, App, HttpServer};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+
+use wilayah::auth::AdminToken;
+use wilayah::cache::ResponseCache;
+use wilayah::models::{
+    BatchItem, BatchResolution, BulkUpsertLine, ChangeFilter, CreateWebhook, DeleteOptions,
+    District, Province, Regency, UpsertStatus, Village,
+};
+use wilayah::{Database, Level, WilayahRepository};
+use wilayah_client::{Client, ClientError, RetryPolicy};
+
+const ADMIN_TOKEN: &str = "client-admin-token";
+
+struct Server {
+    // Removed with the database file when the server is dropped
+    _dir: TempDir,
+    db: web::Data<Database>,
+    handle: ServerHandle,
+    url: String,
+}
+
+// JAWA BARAT > KAB. BANDUNG > Bojongsoang > Lengkong, all with id 1
+async fn start() -> Server {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("wilayah.db");
+    let db = Database::new(path.to_str().unwrap()).unwrap();
+    db.create_province(&Province {
+        id: None,
+        version: None,
+        code: "32".to_string(),
+        name: "JAWA BARAT".to_string(),
+    })
+    .unwrap();
+    db.create_regency(&Regency {
+        id: None,
+        version: None,
+        code: "32.04".to_string(),
+        name: "KAB. BANDUNG".to_string(),
+        province_id: Some(1),
+    })
+    .unwrap();
+    db.create_district(&District {
+        id: None,
+        version: None,
+        code: "32.04.08".to_string(),
+        name: "Bojongsoang".to_string(),
+        regency_id: Some(1),
+    })
+    .unwrap();
+    db.create_village(&Village {
+        id: None,
+        version: None,
+        code: "32.04.08.2001".to_string(),
+        name: "Lengkong".to_string(),
+        district_id: Some(1),
+    })
+    .unwrap();
+
+    let db = web::Data::new(db);
+    let repository: Arc<dyn WilayahRepository> = db.clone().into_inner();
+    let repository = web::Data::from(repository);
+    let cache = web::Data::new(ResponseCache::new(
+        100,
+        Duration::from_secs(300),
+        Duration::from_secs(60),
+    ));
+    let app_db = db.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(app_db.clone())
+            .app_data(repository.clone())
+            .app_data(cache.clone())
+            .app_data(web::Data::new(AdminToken::new(Some(
+                ADMIN_TOKEN.to_string(),
+            ))))
+            .configure(wilayah::configure_all)
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let url = format!("http://{}", server.addrs()[0]);
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+
+    Server {
+        _dir: dir,
+        db,
+        handle,
+        url,
+    }
+}
+
+#[actix_web::test]
+async fn reads_regions() {
+    let server = start().await;
+    let client = Client::new(&server.url).unwrap();
+
+    let provinces = client.get_all_provinces().await.unwrap();
+    assert_eq!(provinces.len(), 1);
+    assert_eq!(provinces[0].version, Some(1));
+
+    let regency = client.get_regency_by_code("32.04").await.unwrap().unwrap();
+    assert_eq!(regency.name, "KAB. BANDUNG");
+    let districts = client.get_districts_by_regency(1).await.unwrap();
+    assert_eq!(districts[0].code, "32.04.08");
+    let villages = client.get_villages_by_district(1).await.unwrap();
+    assert_eq!(villages[0].name, "Lengkong");
+
+    let details = client.get_full_admin_data(1).await.unwrap().unwrap();
+    assert_eq!(details.province_name, "JAWA BARAT");
+
+    assert!(client.get_one_province(99).await.unwrap().is_none());
+    assert!(client
+        .get_village_by_code("32.04.08.9999")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(client.get_full_admin_data(99).await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn writes_regions() {
+    let server = start().await;
+    let client = Client::new(&server.url).unwrap();
+
+    let id = client
+        .create_regency(&Regency {
+            id: None,
+            version: None,
+            code: "32.73".to_string(),
+            name: "KOTA BANDUNG".to_string(),
+            province_id: Some(1),
+        })
+        .await
+        .unwrap();
+    assert_eq!(id, 2);
+
+    let mut regency = client.get_one_regency(id).await.unwrap().unwrap();
+    regency.name = "KOTA BDG".to_string();
+    let version = client
+        .update_regency(id, &regency, regency.version)
+        .await
+        .unwrap();
+    assert_eq!(version, 2);
+
+    // Based on the version before the update
+    let stale = client
+        .patch_regency(id, &json!({"name": "KOTA BANDUNG"}), Some(1))
+        .await;
+    assert!(matches!(stale, Err(ClientError::PreconditionFailed(2))));
+    let version = client
+        .patch_regency(id, &json!({"name": "KOTA BANDUNG"}), Some(2))
+        .await
+        .unwrap();
+    assert_eq!(version, 3);
+
+    let invalid = client
+        .create_district(&District {
+            id: None,
+            version: None,
+            code: "33.01.01".to_string(),
+            name: "X".to_string(),
+            regency_id: Some(1),
+        })
+        .await;
+    match invalid {
+        Err(ClientError::Validation(fields)) => assert_eq!(fields[0].field, "code"),
+        other => panic!("expected a validation error, got {:?}", other.err()),
+    }
+
+    let upserted = client
+        .upsert_by_code(Level::Village, "32.04.08.2002", "Bojongsoang")
+        .await
+        .unwrap();
+    assert_eq!(upserted.status, UpsertStatus::Created);
+
+    let refused = client
+        .delete_province(1, &DeleteOptions::default(), None)
+        .await;
+    match refused {
+        Err(ClientError::HasChildren(children)) => assert_eq!(children["regencies"], 2),
+        other => panic!("expected HasChildren, got {:?}", other.err()),
+    }
+    let cascade = DeleteOptions {
+        cascade: true,
+        dry_run: true,
+        hard: false,
+    };
+    let plan = client.delete_province(1, &cascade, None).await.unwrap();
+    assert_eq!(plan.unwrap().counts["villages"], 2);
+    let cascade = DeleteOptions {
+        dry_run: false,
+        ..cascade
+    };
+    assert!(client
+        .delete_province(1, &cascade, None)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(matches!(
+        client.delete_province(1, &cascade, None).await,
+        Err(ClientError::NotFound)
+    ));
+}
+
+#[actix_web::test]
+async fn resolves_and_upserts_batches() {
+    let server = start().await;
+    let client = Client::new(&server.url).unwrap();
+
+    let results = client
+        .batch_resolve(&[
+            BatchItem::Code("32.04.08".to_string()),
+            BatchItem::Id {
+                level: Level::Village,
+                id: 1,
+            },
+            BatchItem::Code("99".to_string()),
+        ])
+        .await
+        .unwrap();
+    assert!(matches!(results["32.04.08"], BatchResolution::Found(_)));
+    assert!(matches!(results["village:1"], BatchResolution::Found(_)));
+    assert!(matches!(results["99"], BatchResolution::NotFound));
+
+    let response = client
+        .bulk_upsert(&[
+            BulkUpsertLine {
+                code: "32.04.08.2002".to_string(),
+                name: "Bojongsoang".to_string(),
+            },
+            BulkUpsertLine {
+                code: "32.04.08.2001".to_string(),
+                name: "Lengkong".to_string(),
+            },
+        ])
+        .await
+        .unwrap();
+    assert_eq!(response.counts["created"], 1);
+    assert_eq!(response.counts["unchanged"], 1);
+}
+
+#[actix_web::test]
+async fn pages_through_changes() {
+    let server = start().await;
+    let client = Client::new(&server.url).unwrap();
+
+    let mut pages = client.changes("0").limit(3);
+    let mut sizes = Vec::new();
+    while let Some(changes) = pages.next_page().await.unwrap() {
+        sizes.push(changes.len());
+    }
+    assert_eq!(sizes, [3, 1]);
+    let head = pages.since().to_string();
+
+    client
+        .upsert_by_code(Level::Province, "51", "BALI")
+        .await
+        .unwrap();
+    let mut pages = client.changes(&head);
+    let changes = pages.next_page().await.unwrap().unwrap();
+    assert_eq!(changes[0].code, "51");
+    assert!(pages.next_page().await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn streams_events() {
+    let server = start().await;
+    let client = Client::new(&server.url).unwrap();
+
+    let filter = ChangeFilter {
+        levels: vec![Level::Province],
+        code_prefix: None,
+    };
+    let mut events = client.events(&filter, Some(0)).await.unwrap();
+    assert_eq!(events.next().await.unwrap().code, "32");
+
+    client
+        .upsert_by_code(Level::Province, "51", "BALI")
+        .await
+        .unwrap();
+    let change = events.next().await.unwrap();
+    assert_eq!(change.code, "51");
+    assert_eq!(events.last_seq(), Some(change.seq));
+}
+
+#[actix_web::test]
+async fn manages_webhooks() {
+    let server = start().await;
+    let anonymous = Client::new(&server.url).unwrap();
+    assert!(matches!(
+        anonymous.get_webhooks().await,
+        Err(ClientError::Api { status, .. }) if status == 401
+    ));
+    let client = Client::builder(&server.url)
+        .bearer_token(ADMIN_TOKEN)
+        .build()
+        .unwrap();
+
+    let webhook = client
+        .create_webhook(&CreateWebhook {
+            url: "https://cache.internal/hooks".to_string(),
+            secret: "0123456789abcdef".to_string(),
+            filter: ChangeFilter::default(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(client.get_webhooks().await.unwrap().len(), 1);
+    assert!(client
+        .get_webhook_deliveries(webhook.id, None, Some(10))
+        .await
+        .unwrap()
+        .is_empty());
+    client.delete_webhook(webhook.id).await.unwrap();
+    assert!(client.get_one_webhook(webhook.id).await.unwrap().is_none());
+
+    assert!(client.quality_report().await.unwrap().ok);
+    assert_eq!(client.cache_stats().await.unwrap().capacity, 100);
+    assert!(client.openapi().await.unwrap()["paths"]["/provinces"].is_object());
+    let data = client
+        .graphql("{ province(code: \"32\") { name } }", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(data["data"]["province"]["name"], "JAWA BARAT");
+}
+
+#[actix_web::test]
+async fn calls_search_routes() {
+    let server = start().await;
+    let client = Client::new(&server.url).unwrap();
+
+    let csv = client
+        .enrich_csv(b"kode\n32.04.08.2001\n".to_vec(), "kode")
+        .await
+        .unwrap();
+    assert!(String::from_utf8(csv).unwrap().contains("Lengkong"));
+
+    let parsed = client
+        .parse_address("Ds. Lengkong, Kec. Bojongsoang, Kab. Bandung")
+        .await
+        .unwrap();
+    assert_eq!(parsed.village.unwrap().code, "32.04.08.2001");
+}
+
+#[actix_web::test]
+async fn serves_cached_responses_when_offline() {
+    let server = start().await;
+    let client = Client::builder(&server.url)
+        .cache(10)
+        .retry(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    let provinces = client.get_all_provinces().await.unwrap();
+    // Revalidated with If-None-Match, answered with 304
+    assert_eq!(
+        client.get_all_provinces().await.unwrap().len(),
+        provinces.len()
+    );
+
+    // Writes behind the client's back change the revision and the ETag
+    server
+        .db
+        .upsert_by_code(Level::Province, "51", "BALI")
+        .unwrap();
+    assert_eq!(client.get_all_provinces().await.unwrap().len(), 2);
+
+    server.handle.stop(true).await;
+    assert_eq!(client.get_all_provinces().await.unwrap().len(), 2);
+    assert!(matches!(
+        client.get_one_province(1).await,
+        Err(ClientError::Http(_))
+    ));
+}
+
+#[actix_web::test]
+async fn retries_refused_connections() {
+    let client = Client::builder("http://127.0.0.1:9")
+        .retry(RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+        })
+        .build()
+        .unwrap();
+    let started = std::time::Instant::now();
+    assert!(matches!(
+        client.get_all_provinces().await,
+        Err(ClientError::Http(_))
+    ));
+    assert!(started.elapsed() >= Duration::from_millis(30));
+
+    assert!(matches!(
+        Client::new("localhost:8080"),
+        Err(ClientError::Config(_))
+    ));
+}
@@ -0,0 +1,875 @@
+use std::collections::BTreeSet;
+
+use crate::db::{Database, DatabaseError};
+use crate::models::{
+    AddressComponent, AddressStyle, AdminHierarchy, FormatAddressRequest, FormattedAddress,
+    FullAdminData, Level, ParsedAddress,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegencyKind {
+    Kabupaten,
+    Kota,
+}
+
+// Prefixes of regency names as stored in the database ("KAB. BANDUNG",
+// "KOTA BANDUNG"), longest first
+const REGENCY_NAME_PREFIXES: [(&str, RegencyKind); 6] = [
+    ("KABUPATEN ", RegencyKind::Kabupaten),
+    ("KAB. ", RegencyKind::Kabupaten),
+    ("KAB ", RegencyKind::Kabupaten),
+    ("KOTA ADMINISTRASI ", RegencyKind::Kota),
+    ("KOTA ADM. ", RegencyKind::Kota),
+    ("KOTA ", RegencyKind::Kota),
+];
+
+// Abbreviations recognized at the start of an address segment. Entries
+// ending with a dot may be glued to the following word ("Kec.Bojongsoang").
+const STREET_PREFIXES: [&str; 7] = ["jalan", "jln.", "jln", "jl.", "jl", "gang", "gg."];
+const NUMBER_PREFIXES: [&str; 3] = ["nomor", "no.", "no"];
+const VILLAGE_PREFIXES: [&str; 6] = ["kelurahan", "desa", "kel.", "kel", "ds.", "ds"];
+const DISTRICT_PREFIXES: [&str; 3] = ["kecamatan", "kec.", "kec"];
+const KABUPATEN_PREFIXES: [&str; 3] = ["kabupaten", "kab.", "kab"];
+const KOTA_PREFIXES: [&str; 1] = ["kota"];
+const PROVINCE_PREFIXES: [&str; 4] = ["provinsi", "propinsi", "prov.", "prov"];
+
+// Markers that start a new segment even without a separating comma, as in
+// "Ds. Bojongsoang Kec. Bojongsoang Kota Bandung". "Kota" is also an
+// ordinary word ("Jl. Kota Baru"), so it only starts one before the name of
+// a known kota, see split_at_markers.
+const SEGMENT_MARKERS: [&str; 13] = [
+    "desa",
+    "ds.",
+    "kelurahan",
+    "kel.",
+    "kecamatan",
+    "kec.",
+    "kabupaten",
+    "kab.",
+    "provinsi",
+    "prov.",
+    "rt",
+    "rt.",
+    "rt/rw",
+];
+
+// Words a kota name may span after "Kota" ("Kota Jakarta Timur")
+const MAX_KOTA_WORDS: usize = 3;
+
+// Words kept uppercase when title-casing names from the database
+const KEEP_UPPERCASE: [&str; 3] = ["DKI", "DI", "DIY"];
+
+#[derive(Debug, Clone, PartialEq)]
+struct RegionToken {
+    // Level named by the segment prefix, if any
+    level: Option<Level>,
+    kind: Option<RegencyKind>,
+    // Segment text without its prefix, as written
+    text: String,
+    // Uppercased, whitespace-collapsed `text`
+    key: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Tokens {
+    street: Option<String>,
+    rt: Option<String>,
+    rw: Option<String>,
+    postal_code: Option<String>,
+    regions: Vec<RegionToken>,
+}
+
+// Parse a free-text Indonesian address and resolve its administrative
+// components against the database. The regions are resolved together: the
+// chosen village/district/regency/province must belong to one hierarchy.
+pub fn parse_address(db: &Database, address: &str) -> Result<ParsedAddress, DatabaseError> {
+    let tokens = tokenize(address, &known_kota(db, address)?);
+    let candidates = lookup_candidates(db, &tokens.regions)?;
+
+    let mut parsed = ParsedAddress {
+        street: tokens.street,
+        rt: tokens.rt,
+        rw: tokens.rw,
+        postal_code: tokens.postal_code,
+        ..ParsedAddress::default()
+    };
+
+    let mut scored: Vec<(Score, &AdminHierarchy)> = candidates
+        .iter()
+        .map(|candidate| (score(candidate, &tokens.regions), candidate))
+        .collect();
+    // Best agreement first; among equals, the most specific region
+    scored.sort_by(|(a, ca), (b, cb)| b.rank().cmp(&a.rank()).then(cb.level.cmp(&ca.level)));
+
+    let Some((best, hierarchy)) = scored.first() else {
+        parsed.unmatched = tokens.regions.into_iter().map(|t| t.text).collect();
+        return Ok(parsed);
+    };
+
+    // Equally good candidates for different regions make the result a guess
+    let ties = scored
+        .iter()
+        .filter(|(score, candidate)| {
+            score.rank() == best.rank() && candidate.level == hierarchy.level
+        })
+        .map(|(_, candidate)| candidate.code())
+        .collect::<BTreeSet<_>>()
+        .len();
+
+    let input = |level: Level| {
+        best.matched[level_index(level)].map(|index| tokens.regions[index].text.clone())
+    };
+    parsed.province = Some(AddressComponent {
+        code: hierarchy.province_code.clone(),
+        name: hierarchy.province_name.clone(),
+        input: input(Level::Province),
+    });
+    parsed.regency = component(
+        &hierarchy.city_code,
+        &hierarchy.city_name,
+        input(Level::Regency),
+    );
+    parsed.district = component(
+        &hierarchy.region_code,
+        &hierarchy.region_name,
+        input(Level::District),
+    );
+    parsed.village = component(
+        &hierarchy.village_code,
+        &hierarchy.village_name,
+        input(Level::Village),
+    );
+
+    parsed.unmatched = tokens
+        .regions
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !best.matched.contains(&Some(*index)))
+        .map(|(_, token)| token.text.clone())
+        .collect();
+
+    let total = tokens.regions.len() as f64;
+    let agreement = (best.matches as f64 - best.conflicts as f64).max(0.0) / total;
+    parsed.confidence = (agreement / ties as f64 * 100.0).round() / 100.0;
+
+    Ok(parsed)
+}
+
+fn component(
+    code: &Option<String>,
+    name: &Option<String>,
+    input: Option<String>,
+) -> Option<AddressComponent> {
+    Some(AddressComponent {
+        code: code.clone()?,
+        name: name.clone()?,
+        input,
+    })
+}
+
+// Split a regency name from the database into its kind and bare name:
+// "KAB. BANDUNG" -> (Kabupaten, "BANDUNG")
+pub fn split_regency_name(name: &str) -> (Option<RegencyKind>, &str) {
+    let upper = name.to_uppercase();
+    for (prefix, kind) in REGENCY_NAME_PREFIXES {
+        if upper.starts_with(prefix) {
+            if let Some(bare) = name.get(prefix.len()..) {
+                return (Some(kind), bare.trim());
+            }
+        }
+    }
+    (None, name.trim())
+}
+
+pub fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase()
+}
+
+fn level_index(level: Level) -> usize {
+    match level {
+        Level::Province => 0,
+        Level::Regency => 1,
+        Level::District => 2,
+        Level::Village => 3,
+    }
+}
+
+// `kota` holds the bare names of the kota that "Kota" may introduce
+// mid-segment, as returned by known_kota
+fn tokenize(address: &str, kota: &BTreeSet<String>) -> Tokens {
+    let mut tokens = Tokens::default();
+
+    let mut segments: Vec<String> = address
+        .split([',', ';', '\n'])
+        .flat_map(|segment| split_at_markers(segment, kota))
+        .collect();
+
+    // The postal code is the last five-digit number, usually after the
+    // province ("Jawa Barat 40288")
+    for segment in segments.iter_mut().rev() {
+        let mut words: Vec<&str> = segment.split_whitespace().collect();
+        if let Some(last) = words.last() {
+            if last.len() == 5 && last.bytes().all(|b| b.is_ascii_digit()) {
+                tokens.postal_code = Some(last.to_string());
+                words.pop();
+                *segment = words.join(" ");
+                break;
+            }
+        }
+    }
+
+    for segment in segments.iter().filter(|segment| !segment.is_empty()) {
+        let lower = segment.to_lowercase();
+        if lower.starts_with("rt") || lower.starts_with("rw") {
+            let (rt, rw) = parse_rt_rw(&lower);
+            if rt.is_some() || rw.is_some() {
+                tokens.rt = tokens.rt.take().or(rt);
+                tokens.rw = tokens.rw.take().or(rw);
+                continue;
+            }
+        }
+
+        if strip_prefix(segment, &STREET_PREFIXES).is_some() {
+            tokens.street = Some(segment.clone());
+        } else if strip_prefix(segment, &NUMBER_PREFIXES).is_some() && tokens.street.is_some() {
+            let street = tokens.street.take().unwrap_or_default();
+            tokens.street = Some(format!("{} {}", street, segment));
+        } else if let Some(rest) = strip_prefix(segment, &VILLAGE_PREFIXES) {
+            tokens
+                .regions
+                .push(region(Some(Level::Village), None, rest));
+        } else if let Some(rest) = strip_prefix(segment, &DISTRICT_PREFIXES) {
+            tokens
+                .regions
+                .push(region(Some(Level::District), None, rest));
+        } else if let Some(rest) = strip_prefix(segment, &KABUPATEN_PREFIXES) {
+            let kind = Some(RegencyKind::Kabupaten);
+            tokens
+                .regions
+                .push(region(Some(Level::Regency), kind, rest));
+        } else if let Some(rest) = strip_prefix(segment, &KOTA_PREFIXES) {
+            let kind = Some(RegencyKind::Kota);
+            tokens
+                .regions
+                .push(region(Some(Level::Regency), kind, rest));
+        } else if let Some(rest) = strip_prefix(segment, &PROVINCE_PREFIXES) {
+            tokens
+                .regions
+                .push(region(Some(Level::Province), None, rest));
+        } else {
+            tokens.regions.push(region(None, None, segment));
+        }
+    }
+
+    tokens
+}
+
+fn region(level: Option<Level>, kind: Option<RegencyKind>, text: &str) -> RegionToken {
+    RegionToken {
+        level,
+        kind,
+        text: text.trim().to_string(),
+        key: normalize(text),
+    }
+}
+
+// Split "Ds. Bojongsoang Kec. Bojongsoang" into one segment per marker.
+// A marker directly after another one is part of the name ("Kel. Kota Baru"),
+// and "Kota" inside a segment only splits it before a name in `kota`.
+fn split_at_markers(segment: &str, kota: &BTreeSet<String>) -> Vec<String> {
+    let words: Vec<&str> = segment.split_whitespace().collect();
+    let mut segments: Vec<Vec<&str>> = Vec::new();
+    let mut previous_is_marker = false;
+    for (index, word) in words.iter().enumerate() {
+        let lower = word.to_lowercase();
+        let is_kota = lower == "kota" && names_kota(&words[index + 1..], kota);
+        let is_marker = is_kota
+            || SEGMENT_MARKERS.iter().any(|marker| {
+                lower == *marker || (marker.ends_with('.') && lower.starts_with(marker))
+            });
+        match segments.last_mut() {
+            Some(current) if !is_marker || previous_is_marker => current.push(word),
+            _ => segments.push(vec![word]),
+        }
+        // A marker glued to its name ("Kec.Sukasari") already has a name
+        previous_is_marker = is_kota || SEGMENT_MARKERS.contains(&lower.as_str());
+    }
+    segments.into_iter().map(|words| words.join(" ")).collect()
+}
+
+// Whether `words` start with one of the `kota` names
+fn names_kota(words: &[&str], kota: &BTreeSet<String>) -> bool {
+    (1..=words.len().min(MAX_KOTA_WORDS))
+        .any(|len| kota.contains(&normalize(&words[..len].join(" "))))
+}
+
+// Bare names of the kota that follow a "Kota" word inside a segment of
+// `address`, looked up under every prefix a kota is stored with
+fn known_kota(db: &Database, address: &str) -> Result<BTreeSet<String>, DatabaseError> {
+    let mut names = BTreeSet::new();
+    for segment in address.split([',', ';', '\n']) {
+        let words: Vec<&str> = segment.split_whitespace().collect();
+        for (index, word) in words.iter().enumerate().skip(1) {
+            if !word.eq_ignore_ascii_case("kota") {
+                continue;
+            }
+            let following = &words[index + 1..];
+            for len in 1..=following.len().min(MAX_KOTA_WORDS) {
+                let bare = normalize(&following[..len].join(" "));
+                for (prefix, kind) in REGENCY_NAME_PREFIXES {
+                    if kind == RegencyKind::Kota {
+                        names.insert(format!("{}{}", prefix, bare));
+                    }
+                }
+            }
+        }
+    }
+    if names.is_empty() {
+        return Ok(BTreeSet::new());
+    }
+
+    let names: Vec<String> = names.into_iter().collect();
+    Ok(db
+        .resolve_names(Level::Regency, &names)?
+        .iter()
+        .filter_map(|regency| regency.city_name.as_deref())
+        .map(|name| normalize(split_regency_name(name).1))
+        .collect())
+}
+
+// Return the rest of `segment` if it starts with one of `prefixes` as a
+// whole word ("Kab. Bandung", "Kab.Bandung", "Kabupaten Bandung")
+fn strip_prefix<'a>(segment: &'a str, prefixes: &[&str]) -> Option<&'a str> {
+    let lower = segment.to_lowercase();
+    prefixes.iter().find_map(|prefix| {
+        if !lower.starts_with(prefix) {
+            return None;
+        }
+        let rest = segment.get(prefix.len()..)?;
+        let whole_word =
+            prefix.ends_with('.') || rest.is_empty() || rest.starts_with(char::is_whitespace);
+        (whole_word && !rest.trim().is_empty()).then(|| rest.trim())
+    })
+}
+
+// "rt 01/rw 02", "rt.001 rw.002", "rt/rw 01/02", "rw 5"
+fn parse_rt_rw(lower: &str) -> (Option<String>, Option<String>) {
+    let numbers: Vec<&str> = lower
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .collect();
+    if lower.starts_with("rw") {
+        return (None, numbers.first().map(|n| n.to_string()));
+    }
+    let rw_named = lower.contains("rw");
+    (
+        numbers.first().map(|n| n.to_string()),
+        numbers.get(1).filter(|_| rw_named).map(|n| n.to_string()),
+    )
+}
+
+// Every region whose name equals one of the tokens, at the levels each
+// token may refer to
+fn lookup_candidates(
+    db: &Database,
+    tokens: &[RegionToken],
+) -> Result<Vec<AdminHierarchy>, DatabaseError> {
+    let mut candidates = Vec::new();
+    for level in [
+        Level::Province,
+        Level::Regency,
+        Level::District,
+        Level::Village,
+    ] {
+        let mut names = BTreeSet::new();
+        for token in tokens
+            .iter()
+            .filter(|t| t.level.is_none() || t.level == Some(level))
+        {
+            if level == Level::Regency {
+                for (prefix, kind) in REGENCY_NAME_PREFIXES {
+                    if token.kind.is_none() || token.kind == Some(kind) {
+                        names.insert(format!("{}{}", prefix, token.key));
+                    }
+                }
+            }
+            names.insert(token.key.clone());
+        }
+        if !names.is_empty() {
+            let names: Vec<String> = names.into_iter().collect();
+            candidates.extend(db.resolve_names(level, &names)?);
+        }
+    }
+    Ok(candidates)
+}
+
+#[derive(Debug)]
+struct Score {
+    matches: usize,
+    conflicts: usize,
+    // Token index matched at each level, province first
+    matched: [Option<usize>; 4],
+}
+
+impl Score {
+    fn rank(&self) -> isize {
+        self.matches as isize - self.conflicts as isize
+    }
+}
+
+// Count the tokens that agree with the candidate's hierarchy. A token naming
+// a level explicitly ("Kec. X") that disagrees with the candidate at that
+// level is a conflict.
+fn score(candidate: &AdminHierarchy, tokens: &[RegionToken]) -> Score {
+    let levels = [
+        (Level::Village, candidate.village_name.as_deref()),
+        (Level::District, candidate.region_name.as_deref()),
+        (Level::Regency, candidate.city_name.as_deref()),
+        (Level::Province, Some(candidate.province_name.as_str())),
+    ];
+
+    let mut score = Score {
+        matches: 0,
+        conflicts: 0,
+        matched: [None; 4],
+    };
+    let mut used = vec![false; tokens.len()];
+
+    for (level, name) in levels {
+        let Some(name) = name else { continue };
+
+        // Explicitly labelled tokens take precedence over unlabelled ones
+        let found = tokens
+            .iter()
+            .enumerate()
+            .filter(|(index, token)| !used[*index] && token.level == Some(level))
+            .chain(
+                tokens
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, token)| !used[*index] && token.level.is_none()),
+            )
+            .find(|(_, token)| name_matches(token, level, name))
+            .map(|(index, _)| index);
+
+        match found {
+            Some(index) => {
+                used[index] = true;
+                score.matches += 1;
+                score.matched[level_index(level)] = Some(index);
+            }
+            None => {
+                score.conflicts += tokens
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, token)| !used[*index] && token.level == Some(level))
+                    .count();
+            }
+        }
+    }
+
+    score
+}
+
+fn name_matches(token: &RegionToken, level: Level, name: &str) -> bool {
+    if normalize(name) == token.key {
+        return true;
+    }
+    if level != Level::Regency {
+        return false;
+    }
+    let (kind, bare) = split_regency_name(name);
+    normalize(bare) == token.key && (token.kind.is_none() || token.kind == kind)
+}
+
+// Format the hierarchy of a village as a postal address in the given style
+pub fn format_address(data: &FullAdminData, request: &FormatAddressRequest) -> FormattedAddress {
+    let street = request
+        .street
+        .as_deref()
+        .map(str::trim)
+        .filter(|street| !street.is_empty());
+    let rt_rw = match (&request.rt, &request.rw) {
+        (Some(rt), Some(rw)) => Some(format!("RT {}/RW {}", pad_rt_rw(rt), pad_rt_rw(rw))),
+        (Some(rt), None) => Some(format!("RT {}", pad_rt_rw(rt))),
+        (None, Some(rw)) => Some(format!("RW {}", pad_rt_rw(rw))),
+        (None, None) => None,
+    };
+
+    let village = format!(
+        "{} {}",
+        village_prefix(&data.village_code),
+        title_case(&data.village_name)
+    );
+    let district = format!("Kec. {}", title_case(&data.region_name));
+    let regency = format_regency_name(&data.city_name);
+    let province = match &request.postal_code {
+        Some(postal_code) => format!("{} {}", title_case(&data.province_name), postal_code.trim()),
+        None => title_case(&data.province_name),
+    };
+
+    let street_line = [street.map(str::to_string), rt_rw]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    let (lines, separator) = match request.style {
+        AddressStyle::SingleLine => {
+            let mut lines = street_line;
+            lines.extend([village, district, regency, province]);
+            (lines, ", ")
+        }
+        AddressStyle::MultiLine => {
+            let mut lines = Vec::new();
+            if !street_line.is_empty() {
+                lines.push(street_line.join(" "));
+            }
+            lines.extend([format!("{}, {}", village, district), regency, province]);
+            (lines, "\n")
+        }
+        AddressStyle::PosIndonesia => {
+            let mut lines = Vec::new();
+            if !street_line.is_empty() {
+                lines.push(street_line.join(" "));
+            }
+            lines.extend([village, district, regency, province]);
+            let lines = lines.iter().map(|line| line.to_uppercase()).collect();
+            (lines, "\n")
+        }
+    };
+
+    FormattedAddress {
+        style: request.style,
+        formatted: lines.join(separator),
+        lines,
+    }
+}
+
+// "KAB. BANDUNG" -> "Kab. Bandung", "KOTA ADM. JAKARTA PUSAT" -> "Kota Jakarta Pusat"
+pub fn format_regency_name(name: &str) -> String {
+    match split_regency_name(name) {
+        (Some(RegencyKind::Kabupaten), bare) => format!("Kab. {}", title_case(bare)),
+        (Some(RegencyKind::Kota), bare) => format!("Kota {}", title_case(bare)),
+        (None, bare) => title_case(bare),
+    }
+}
+
+// The first digit of the last code segment tells a kelurahan (1xxx) from a
+// desa (2xxx)
+fn village_prefix(village_code: &str) -> &'static str {
+    match village_code
+        .rsplit('.')
+        .next()
+        .and_then(|s| s.chars().next())
+    {
+        Some('1') => "Kel.",
+        _ => "Desa",
+    }
+}
+
+// "001"-style zero padding used on Indonesian addresses
+fn pad_rt_rw(value: &str) -> String {
+    let value = value.trim();
+    if !value.is_empty() && value.len() < 3 && value.bytes().all(|b| b.is_ascii_digit()) {
+        format!("{:0>3}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+// Title-case an uppercase name from the database, keeping acronyms such as
+// "DKI"/"DI" and roman numerals ("KOTO XI TARUSAN" -> "Koto XI Tarusan")
+pub fn title_case(name: &str) -> String {
+    name.split_whitespace()
+        .map(|word| {
+            let upper = word.to_uppercase();
+            if KEEP_UPPERCASE.contains(&upper.as_str()) || is_roman_numeral(&upper) {
+                return upper;
+            }
+            // Capitalize every part of "SIDOARJO-KRIAN" / "TANJUNG(PRIOK)"
+            let mut result = String::with_capacity(word.len());
+            let mut capitalize = true;
+            for c in word.chars() {
+                if capitalize {
+                    result.extend(c.to_uppercase());
+                } else {
+                    result.extend(c.to_lowercase());
+                }
+                capitalize = !c.is_alphanumeric() && c != '\'';
+            }
+            result
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_roman_numeral(word: &str) -> bool {
+    word.len() > 1 && word.chars().all(|c| matches!(c, 'I' | 'V' | 'X' | 'L'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kota(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn regions(tokens: &Tokens) -> Vec<(Option<Level>, Option<RegencyKind>, &str)> {
+        tokens
+            .regions
+            .iter()
+            .map(|token| (token.level, token.kind, token.text.as_str()))
+            .collect()
+    }
+
+    fn database() -> Database {
+        let db = Database::new(":memory:").unwrap();
+        for (level, code, name) in [
+            (Level::Province, "31", "DKI JAKARTA"),
+            (Level::Regency, "31.71", "KOTA ADM. JAKARTA PUSAT"),
+            (Level::Province, "32", "JAWA BARAT"),
+            (Level::Regency, "32.04", "KAB. BANDUNG"),
+            (Level::Regency, "32.73", "KOTA BANDUNG"),
+            (Level::District, "32.04.08", "BOJONGSOANG"),
+            (Level::District, "32.04.11", "DAYEUHKOLOT"),
+            (Level::Village, "32.04.08.2001", "SUKAMAJU"),
+            (Level::Village, "32.04.11.2001", "SUKAMAJU"),
+            (Level::Province, "63", "KALIMANTAN SELATAN"),
+            (Level::Regency, "63.02", "KAB. KOTA BARU"),
+        ] {
+            db.upsert_by_code(level, code, name).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn tokenizes_a_full_address() {
+        let tokens = tokenize(
+            "Jl. Raya Bojongsoang No. 12, RT 01/RW 02, Ds. Lengkong, Kec. Bojongsoang, \
+             Kab. Bandung, Jawa Barat 40288",
+            &kota(&[]),
+        );
+        assert_eq!(
+            tokens.street.as_deref(),
+            Some("Jl. Raya Bojongsoang No. 12")
+        );
+        assert_eq!(tokens.rt.as_deref(), Some("01"));
+        assert_eq!(tokens.rw.as_deref(), Some("02"));
+        assert_eq!(tokens.postal_code.as_deref(), Some("40288"));
+        assert_eq!(
+            regions(&tokens),
+            [
+                (Some(Level::Village), None, "Lengkong"),
+                (Some(Level::District), None, "Bojongsoang"),
+                (
+                    Some(Level::Regency),
+                    Some(RegencyKind::Kabupaten),
+                    "Bandung"
+                ),
+                (None, None, "Jawa Barat"),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_at_markers_without_commas() {
+        let tokens = tokenize(
+            "Ds. Sukamaju Kec.Dayeuhkolot Kota Bandung Prov. Jawa Barat",
+            &kota(&["BANDUNG"]),
+        );
+        assert_eq!(
+            regions(&tokens),
+            [
+                (Some(Level::Village), None, "Sukamaju"),
+                (Some(Level::District), None, "Dayeuhkolot"),
+                (Some(Level::Regency), Some(RegencyKind::Kota), "Bandung"),
+                (Some(Level::Province), None, "Jawa Barat"),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_at_kota_only_before_a_known_kota() {
+        let known = kota(&["BANDUNG"]);
+
+        let street = tokenize("Jl. Kota Baru No. 5 Kota Bandung", &known);
+        assert_eq!(street.street.as_deref(), Some("Jl. Kota Baru No. 5"));
+        assert_eq!(
+            regions(&street),
+            [(Some(Level::Regency), Some(RegencyKind::Kota), "Bandung")]
+        );
+
+        // Unknown, so part of the street
+        let unknown = tokenize("Jl. Kota Baru No. 5 Kota Bandung", &kota(&[]));
+        assert_eq!(
+            unknown.street.as_deref(),
+            Some("Jl. Kota Baru No. 5 Kota Bandung")
+        );
+        assert!(unknown.regions.is_empty());
+
+        // Right after another marker it is part of the name
+        let village = tokenize("Kel. Kota Bandung", &known);
+        assert_eq!(
+            regions(&village),
+            [(Some(Level::Village), None, "Kota Bandung")]
+        );
+
+        // After a comma it is a marker whatever follows
+        let regency = tokenize("Jl. Merdeka, Kota Baru", &kota(&[]));
+        assert_eq!(
+            regions(&regency),
+            [(Some(Level::Regency), Some(RegencyKind::Kota), "Baru")]
+        );
+    }
+
+    #[test]
+    fn looks_up_kota_names_after_kota() {
+        let db = database();
+        // KAB. KOTA BARU is a kabupaten, so "Kota Baru" names no kota
+        let known = known_kota(
+            &db,
+            "Jl. Kota Baru 1 Kota Bandung, Jl. X Kota Jakarta Pusat",
+        )
+        .unwrap();
+        assert_eq!(known, kota(&["BANDUNG", "JAKARTA PUSAT"]));
+        assert!(known_kota(&db, "Kota Bandung").unwrap().is_empty());
+
+        let parsed = parse_address(&db, "Jl. Kota Baru No. 5 Kota Bandung").unwrap();
+        assert_eq!(parsed.street.as_deref(), Some("Jl. Kota Baru No. 5"));
+        assert_eq!(parsed.regency.unwrap().code, "32.73");
+        assert!(parsed.unmatched.is_empty());
+    }
+
+    fn full_data(village_code: &str, city_name: &str) -> FullAdminData {
+        FullAdminData {
+            province_code: "31".to_string(),
+            province_name: "DKI JAKARTA".to_string(),
+            city_code: "31.71".to_string(),
+            city_name: city_name.to_string(),
+            region_code: "31.71.01".to_string(),
+            region_name: "GAMBIR".to_string(),
+            village_code: village_code.to_string(),
+            village_name: "CIDENG".to_string(),
+        }
+    }
+
+    fn request(style: AddressStyle) -> FormatAddressRequest {
+        FormatAddressRequest {
+            village_code: "31.71.01.1002".to_string(),
+            street: Some(" Jl. Abdul Muis No. 7 ".to_string()),
+            rt: Some("1".to_string()),
+            rw: Some("12".to_string()),
+            postal_code: Some("10160".to_string()),
+            style,
+        }
+    }
+
+    #[test]
+    fn formats_each_style() {
+        let data = full_data("31.71.01.1002", "KOTA ADM. JAKARTA PUSAT");
+
+        let single = format_address(&data, &request(AddressStyle::SingleLine));
+        assert_eq!(
+            single.formatted,
+            "Jl. Abdul Muis No. 7, RT 001/RW 012, Kel. Cideng, Kec. Gambir, \
+             Kota Jakarta Pusat, DKI Jakarta 10160"
+        );
+        assert_eq!(single.lines.len(), 6);
+
+        let multi = format_address(&data, &request(AddressStyle::MultiLine));
+        assert_eq!(
+            multi.lines,
+            [
+                "Jl. Abdul Muis No. 7 RT 001/RW 012",
+                "Kel. Cideng, Kec. Gambir",
+                "Kota Jakarta Pusat",
+                "DKI Jakarta 10160",
+            ]
+        );
+        assert_eq!(multi.formatted, multi.lines.join("\n"));
+
+        let pos = format_address(&data, &request(AddressStyle::PosIndonesia));
+        assert_eq!(
+            pos.lines,
+            [
+                "JL. ABDUL MUIS NO. 7 RT 001/RW 012",
+                "KEL. CIDENG",
+                "KEC. GAMBIR",
+                "KOTA JAKARTA PUSAT",
+                "DKI JAKARTA 10160",
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_without_the_optional_parts() {
+        let data = full_data("31.71.01.2002", "KAB. ADM. KEPULAUAN SERIBU");
+        let mut bare = FormatAddressRequest {
+            street: Some("  ".to_string()),
+            rt: None,
+            rw: None,
+            postal_code: None,
+            ..request(AddressStyle::MultiLine)
+        };
+        // A blank street is left out like a missing one, and 2xxx is a desa
+        assert_eq!(
+            format_address(&data, &bare).lines,
+            [
+                "Desa Cideng, Kec. Gambir",
+                "Kab. Adm. Kepulauan Seribu",
+                "DKI Jakarta"
+            ]
+        );
+
+        bare.rw = Some("3".to_string());
+        assert_eq!(format_address(&data, &bare).lines[0], "RW 003");
+        bare.rt = Some("0042".to_string());
+        bare.rw = None;
+        assert_eq!(format_address(&data, &bare).lines[0], "RT 0042");
+    }
+
+    #[test]
+    fn title_cases_names_from_the_database() {
+        assert_eq!(
+            format_regency_name("KABUPATEN BANDUNG BARAT"),
+            "Kab. Bandung Barat"
+        );
+        assert_eq!(
+            format_regency_name("KOTA ADMINISTRASI JAKARTA TIMUR"),
+            "Kota Jakarta Timur"
+        );
+        assert_eq!(format_regency_name("BANDUNG"), "Bandung");
+        assert_eq!(title_case("KOTO XI TARUSAN"), "Koto XI Tarusan");
+        assert_eq!(title_case("DI YOGYAKARTA"), "DI Yogyakarta");
+        assert_eq!(
+            title_case("SIDOARJO-KRIAN TANJUNG(PRIOK)"),
+            "Sidoarjo-Krian Tanjung(Priok)"
+        );
+        assert_eq!(title_case("MA'RUF  I"), "Ma'ruf I");
+    }
+
+    #[test]
+    fn scores_ambiguous_names() {
+        let db = database();
+
+        // Two villages share the name: either is a guess
+        let ambiguous = parse_address(&db, "Sukamaju").unwrap();
+        assert_eq!(ambiguous.confidence, 0.5);
+        assert!(ambiguous.village.is_some());
+
+        // The district settles it
+        let settled = parse_address(&db, "Ds. Sukamaju, Kec. Dayeuhkolot").unwrap();
+        assert_eq!(settled.village.unwrap().code, "32.04.11.2001");
+        assert_eq!(settled.confidence, 1.0);
+
+        // A labelled token that fits no candidate lowers the confidence
+        let conflict = parse_address(&db, "Ds. Sukamaju, Kec. Dayeuhkolot, Kota Bandung").unwrap();
+        assert_eq!(conflict.village.unwrap().code, "32.04.11.2001");
+        assert_eq!(conflict.unmatched, ["Bandung"]);
+        assert!(conflict.confidence < 1.0);
+    }
+}
@@ -0,0 +1,124 @@
+// Bearer token guarding the /admin scope. The admin API downloads the whole
+// database, webhook secrets included, and makes the server post to other
+// hosts, so without a configured token it is switched off entirely.
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse};
+use serde_json::json;
+
+// Registered by the app as `web::Data<AdminToken>`; missing data counts as
+// no token
+#[derive(Clone, Default)]
+pub struct AdminToken(Option<String>);
+
+impl AdminToken {
+    // Empty tokens are treated as none
+    pub fn new(token: Option<String>) -> AdminToken {
+        AdminToken(token.filter(|token| !token.is_empty()))
+    }
+
+    fn accepts(&self, given: &str) -> bool {
+        match &self.0 {
+            // Compare every byte, so the time taken does not leak the prefix
+            Some(token) => {
+                token.len() == given.len()
+                    && token
+                        .bytes()
+                        .zip(given.bytes())
+                        .fold(0, |diff, (a, b)| diff | (a ^ b))
+                        == 0
+            }
+            None => false,
+        }
+    }
+}
+
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let token = req
+        .app_data::<web::Data<AdminToken>>()
+        .map(|token| token.get_ref().clone())
+        .unwrap_or_default();
+    if token.0.is_none() {
+        let res = HttpResponse::Forbidden().json(json!({
+            "error": "Admin API is disabled, set ADMIN_TOKEN to enable it"
+        }));
+        return Ok(req.into_response(res));
+    }
+
+    let given = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !token.accepts(given.trim()) {
+        let res = HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .json(json!({
+                "error": "Missing or wrong admin token"
+            }));
+        return Ok(req.into_response(res));
+    }
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::App;
+
+    #[actix_web::test]
+    async fn guards_the_scope() {
+        let scope = || {
+            web::scope("/admin")
+                .wrap(from_fn(require_admin))
+                .route("/ping", web::get().to(HttpResponse::NoContent))
+        };
+        let request = |token: Option<&str>| {
+            let mut req = TestRequest::get().uri("/admin/ping");
+            if let Some(token) = token {
+                req = req.insert_header((header::AUTHORIZATION, token.to_string()));
+            }
+            req.to_request()
+        };
+
+        let disabled = init_service(App::new().service(scope())).await;
+        let res = call_service(&disabled, request(Some("Bearer s3cret"))).await;
+        assert_eq!(res.status(), 403);
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(AdminToken::new(Some("s3cret".to_string()))))
+                .service(scope()),
+        )
+        .await;
+        for token in [None, Some("Bearer wrong"), Some("s3cret")] {
+            let res = call_service(&app, request(token)).await;
+            assert_eq!(res.status(), 401, "{:?}", token);
+            assert_eq!(
+                res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+                "Bearer"
+            );
+        }
+        let res = call_service(&app, request(Some("Bearer s3cret"))).await;
+        assert_eq!(res.status(), 204);
+    }
+
+    #[test]
+    fn accepts_only_the_configured_token() {
+        let token = AdminToken::new(Some("s3cret".to_string()));
+        assert!(token.accepts("s3cret"));
+        assert!(!token.accepts("s3cre"));
+        assert!(!token.accepts("s3creT"));
+        assert!(!token.accepts(""));
+        assert!(!AdminToken::new(Some(String::new())).accepts(""));
+        assert!(!AdminToken::default().accepts(""));
+    }
+}
@@ -0,0 +1,269 @@
+// Backups of the live database into a directory, on demand or on a schedule,
+// keeping only the newest ones, and their restore over the database file.
+// The copies themselves come from Database::backup_to.
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+use crate::db::{Database, DatabaseError};
+use crate::models::BackupInfo;
+
+const PREFIX: &str = "wilayah-";
+const SUFFIX: &str = ".db";
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Database(#[from] DatabaseError),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+// wilayah-20261019T041012Z.db, in UTC so the names sort by age
+pub fn file_name(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    // Civil date of a day count since 1970-01-01, after Howard Hinnant
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{}{:04}{:02}{:02}T{:02}{:02}{:02}Z{}",
+        PREFIX,
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs / 60 % 60,
+        secs % 60,
+        SUFFIX
+    )
+}
+
+// Temporary name next to `path`, unique within this process
+fn temp_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(name)
+}
+
+// Back up into `dir` (created when missing) under the name of the current
+// time, then delete the oldest backups beyond `keep`; 0 keeps them all. The
+// copy gets its name only once complete, so a failed backup never looks
+// like one.
+pub fn write_backup(
+    db: &Database,
+    dir: &Path,
+    vacuum: bool,
+    keep: usize,
+) -> Result<PathBuf, BackupError> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(file_name(SystemTime::now()));
+    let temp = temp_path(&path);
+    if let Err(e) = db.backup_to(&temp, vacuum) {
+        let _ = fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    fs::rename(&temp, &path)?;
+    if keep > 0 {
+        prune(dir, keep)?;
+    }
+    Ok(path)
+}
+
+// Delete all but the newest `keep` backups in `dir`; other files are left
+// alone. Returns the deleted files.
+pub fn prune(dir: &Path, keep: usize) -> io::Result<Vec<PathBuf>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if name.starts_with(PREFIX) && name.ends_with(SUFFIX) {
+            names.push(name);
+        }
+    }
+    names.sort();
+    let stale = names.len().saturating_sub(keep);
+    let mut deleted = Vec::new();
+    for name in names.into_iter().take(stale) {
+        let path = dir.join(name);
+        fs::remove_file(&path)?;
+        deleted.push(path);
+    }
+    Ok(deleted)
+}
+
+// A backup in the system temp directory, already unlinked where the platform
+// allows it, for streaming to a client. Returns the file and its length.
+pub fn temp_backup(db: &Database, vacuum: bool) -> Result<(File, u64), BackupError> {
+    let path = temp_path(&std::env::temp_dir().join(file_name(SystemTime::now())));
+    let result = db
+        .backup_to(&path, vacuum)
+        .map_err(BackupError::from)
+        .and_then(|()| {
+            let file = File::open(&path)?;
+            let len = file.metadata()?.len();
+            Ok((file, len))
+        });
+    let _ = fs::remove_file(&path);
+    result
+}
+
+// Replace the database file at `database` with `backup` once the backup was
+// checked. The previous file is kept as `<database>.pre-restore`. Only for a
+// stopped server: a leftover journal means the file is still in use.
+pub fn restore(backup: &Path, database: &Path) -> Result<BackupInfo, BackupError> {
+    let info = Database::inspect_backup(backup).map_err(|e| match e {
+        DatabaseError::Validation(fields) => BackupError::Invalid(
+            fields
+                .iter()
+                .map(|field| format!("{} {}", field.field, field.message))
+                .collect::<Vec<_>>()
+                .join("; "),
+        ),
+        e => e.into(),
+    })?;
+    for suffix in ["-journal", "-wal"] {
+        let mut journal = database.as_os_str().to_os_string();
+        journal.push(suffix);
+        if Path::new(&journal).exists() {
+            return Err(BackupError::Invalid(format!(
+                "{} exists, stop the server before restoring",
+                Path::new(&journal).display()
+            )));
+        }
+    }
+
+    let temp = temp_path(database);
+    fs::copy(backup, &temp)?;
+    File::open(&temp)?.sync_all()?;
+    if database.exists() {
+        let mut previous = database.as_os_str().to_os_string();
+        previous.push(".pre-restore");
+        fs::copy(database, previous)?;
+    }
+    fs::rename(&temp, database)?;
+    Ok(info)
+}
+
+// Where and how often the server backs itself up
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    pub keep: usize,
+    pub vacuum: bool,
+}
+
+// First backup one interval after startup, so restarts do not pile them up
+#[cfg(feature = "http")]
+pub fn spawn_scheduler(db: actix_web::web::Data<Database>, schedule: Schedule) {
+    use log::{info, warn};
+
+    actix_rt::spawn(async move {
+        info!(
+            "Backing up to {} every {}s, keeping {}",
+            schedule.dir.display(),
+            schedule.interval.as_secs(),
+            schedule.keep
+        );
+        loop {
+            actix_rt::time::sleep(schedule.interval).await;
+            let (db, schedule) = (db.clone(), schedule.clone());
+            let result = actix_web::web::block(move || {
+                write_backup(&db, &schedule.dir, schedule.vacuum, schedule.keep)
+            })
+            .await;
+            match result {
+                Ok(Ok(path)) => info!("Wrote backup {}", path.display()),
+                Ok(Err(e)) => warn!("Backup failed: {}", e),
+                Err(e) => warn!("Backup failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Level;
+    use tempfile::TempDir;
+
+    #[test]
+    fn names_sort_by_time() {
+        assert_eq!(file_name(UNIX_EPOCH), "wilayah-19700101T000000Z.db");
+        let time = UNIX_EPOCH + Duration::from_secs(1_792_383_012);
+        assert_eq!(file_name(time), "wilayah-20261019T041012Z.db");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(file_name(leap_day), "wilayah-20000229T000000Z.db");
+    }
+
+    #[test]
+    fn backs_up_prunes_and_restores() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("wilayah.db");
+        let db = Database::new(path.to_str().unwrap()).unwrap();
+        db.upsert_by_code(Level::Province, "32", "JAWA BARAT")
+            .unwrap();
+
+        let backups = dir.path().join("backups");
+        fs::create_dir_all(&backups).unwrap();
+        for name in ["wilayah-20000101T000000Z.db", "wilayah-20000102T000000Z.db"] {
+            fs::write(backups.join(name), "old").unwrap();
+        }
+        fs::write(backups.join("notes.txt"), "kept").unwrap();
+        let backup = write_backup(&db, &backups, false, 2).unwrap();
+        let mut left: Vec<_> = fs::read_dir(&backups)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left.len(), 3);
+        assert_eq!(left[0], "notes.txt");
+        assert_eq!(left[1], "wilayah-20000102T000000Z.db");
+
+        let info = Database::inspect_backup(&backup).unwrap();
+        assert_eq!(info.revision, 1);
+        assert!(matches!(
+            restore(&backups.join("notes.txt"), &path),
+            Err(BackupError::Database(_))
+        ));
+
+        // Newer than this build
+        let newer = dir.path().join("newer.db");
+        db.backup_to(&newer, true).unwrap();
+        set_user_version(&newer, crate::db::SCHEMA_VERSION + 1);
+        let refused = restore(&newer, &path).unwrap_err().to_string();
+        assert!(refused.starts_with("schema_version is"), "{}", refused);
+
+        db.upsert_by_code(Level::Province, "51", "BALI").unwrap();
+        drop(db);
+        assert_eq!(restore(&backup, &path).unwrap(), info);
+        let db = Database::new(path.to_str().unwrap()).unwrap();
+        assert_eq!(db.get_all_provinces().unwrap().len(), 1);
+        assert!(dir.path().join("wilayah.db.pre-restore").exists());
+    }
+
+    fn set_user_version(path: &Path, version: i64) {
+        let conn = rusqlite::Connection::open(path).unwrap();
+        conn.pragma_update(None, "user_version", version).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+use actix_web::web;
+use lru::LruCache;
+use serde::Serialize;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::db::DatabaseError;
+use crate::models::CacheStats;
+use crate::negotiate::Representation;
+
+// Encoded response of a read, shared by every request for the same
+// representation that hits it
+pub struct CachedResponse {
+    // Row version for single rows, None for lists and joins
+    pub version: Option<i64>,
+    pub body: web::Bytes,
+    pub content_type: &'static str,
+    // Tells the ETags of the encodings apart, empty for JSON
+    pub etag_suffix: &'static str,
+}
+
+struct Entry {
+    revision: i64,
+    stored_at: Instant,
+    response: Arc<CachedResponse>,
+}
+
+// In-process LRU of read responses, keyed by the read and the suffix of its
+// representation. Entries belong to the dataset revision they were built
+// from, so any write invalidates them. The TTL only matters
+// when the SQLite file is changed behind the server's back.
+pub struct ResponseCache {
+    entries: Mutex<LruCache<(String, &'static str), Entry>>,
+    ttl: Duration,
+    // Advertised in Cache-Control to clients, CDNs and gateways
+    max_age: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize, ttl: Duration, max_age: Duration) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        ResponseCache {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+            max_age,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    // The cached response for `key` in `representation` at `revision`, or
+    // the result of `load` which is encoded and cached in turn. `load`
+    // returns the row version, if any, and the body; errors (NotFound
+    // included) are not cached.
+    pub fn get_or_load<T, F>(
+        &self,
+        key: String,
+        representation: Representation,
+        revision: i64,
+        load: F,
+    ) -> Result<Arc<CachedResponse>, DatabaseError>
+    where
+        T: Serialize,
+        F: FnOnce() -> Result<(Option<i64>, T), DatabaseError>,
+    {
+        let key = (key, representation.suffix());
+        if let Some(response) = self.get(&key, revision) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(response);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let (version, value) = load()?;
+        let (body, content_type, etag_suffix) =
+            representation.encode(serde_json::to_vec(&value).unwrap_or_default());
+        let response = Arc::new(CachedResponse {
+            version,
+            body: web::Bytes::from(body),
+            content_type,
+            etag_suffix,
+        });
+        self.entries.lock().unwrap().put(
+            key,
+            Entry {
+                revision,
+                stored_at: Instant::now(),
+                response: response.clone(),
+            },
+        );
+        Ok(response)
+    }
+
+    fn get(&self, key: &(String, &'static str), revision: i64) -> Option<Arc<CachedResponse>> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get(key)?;
+        if entry.revision == revision && entry.stored_at.elapsed() < self.ttl {
+            return Some(entry.response.clone());
+        }
+        entries.pop(key);
+        None
+    }
+
+    pub fn stats(&self, revision: i64) -> CacheStats {
+        let entries = self.entries.lock().unwrap();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: entries.len(),
+            capacity: entries.cap().get(),
+            ttl_secs: self.ttl.as_secs(),
+            max_age_secs: self.max_age.as_secs(),
+            revision: revision.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::Message;
+    use actix_web::http::header;
+    use actix_web::test::TestRequest;
+
+    fn cache() -> ResponseCache {
+        ResponseCache::new(2, Duration::from_secs(60), Duration::from_secs(60))
+    }
+
+    fn representation(accept: &str) -> Representation {
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT, accept))
+            .to_http_request();
+        Representation::new(&req, Message::Province)
+    }
+
+    fn json() -> Representation {
+        representation("application/json")
+    }
+
+    #[test]
+    fn writes_invalidate_entries() {
+        let cache = cache();
+        let load = || Ok((Some(3), "JAWA BARAT"));
+        cache.get_or_load("a".into(), json(), 1, load).unwrap();
+        let hit = cache.get_or_load("a".into(), json(), 1, load).unwrap();
+        assert_eq!(hit.version, Some(3));
+        assert_eq!(&hit.body[..], b"\"JAWA BARAT\"");
+        // A newer revision misses and reloads
+        cache.get_or_load("a".into(), json(), 2, load).unwrap();
+
+        let stats = cache.stats(2);
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+    }
+
+    #[test]
+    fn keeps_each_representation_apart() {
+        let cache = ResponseCache::new(4, Duration::from_secs(60), Duration::from_secs(60));
+        let load = || Ok((Some(3), "BALI"));
+        let msgpack = representation("application/msgpack");
+
+        let json = cache.get_or_load("a".into(), json(), 1, load).unwrap();
+        assert_eq!(json.content_type, "application/json");
+        let encoded = cache.get_or_load("a".into(), msgpack, 1, load).unwrap();
+        assert_eq!(encoded.content_type, "application/msgpack");
+        assert_eq!(
+            rmp_serde::from_slice::<String>(&encoded.body).unwrap(),
+            "BALI"
+        );
+        let hit = cache.get_or_load("a".into(), msgpack, 1, load).unwrap();
+        assert!(Arc::ptr_eq(&encoded, &hit));
+
+        let stats = cache.stats(1);
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 2));
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = cache();
+        let load = || Ok((None, 1));
+        cache.get_or_load("a".into(), json(), 1, load).unwrap();
+        cache.get_or_load("b".into(), json(), 1, load).unwrap();
+        cache.get_or_load("a".into(), json(), 1, load).unwrap();
+        cache.get_or_load("c".into(), json(), 1, load).unwrap();
+
+        assert!(cache.get(&("a".into(), ""), 1).is_some());
+        assert!(cache.get(&("b".into(), ""), 1).is_none());
+        assert_eq!(cache.stats(1).entries, 2);
+    }
+}
@@ -1,9 +1,120 @@
+use rusqlite::types::ToSql;
 use rusqlite::OptionalExtension;
-use rusqlite::{params, Connection, OpenFlags, Result};
-use std::sync::{Arc, Mutex};
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, Result, Transaction};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
-
-use crate::models::{District, FullAdminData, Province, Regency, Village};
+use tokio::sync::broadcast;
+
+use crate::models::{
+    AdminHierarchy, BackupInfo, BulkUpsertLine, Change, ChangeFeed, ChangeFilter, ChangeOp,
+    CreateWebhook, DeleteOptions, DeletePlan, DeliveryStatus, District, FieldError,
+    ForeignKeyViolation, FullAdminData, Level, LevelCount, PendingDelivery, Province, QualityIssue,
+    QualityReport, Regency, RegionRow, Severity, UpsertResult, UpsertStatus, Village, Webhook,
+    WebhookDelivery, WebhookEvent,
+};
+use crate::rules::{self, RuleStore};
+use crate::validation::{self, Parent};
+
+// Upper bound of bound parameters per `IN (...)` query in the batch lookups
+const BATCH_CHUNK_SIZE: usize = 500;
+
+// Schema version of this build, the number of migrations below
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+// Schema migrations, applied in order by Database::new. `PRAGMA
+// user_version` records how many have run. The first one matches the layout
+// of the published dataset; the later ones alter the file, which is why
+// commands that only read use Database::open_read_only.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE IF NOT EXISTS provinces (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        code TEXT NOT NULL,
+        name TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS regencies (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        code TEXT NOT NULL,
+        name TEXT NOT NULL,
+        province_id INTEGER REFERENCES provinces(id)
+    );
+    CREATE TABLE IF NOT EXISTS districts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        code TEXT NOT NULL,
+        name TEXT NOT NULL,
+        regency_id INTEGER REFERENCES regencies(id)
+    );
+    CREATE TABLE IF NOT EXISTS villages (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        code TEXT NOT NULL,
+        name TEXT NOT NULL,
+        district_id INTEGER REFERENCES districts(id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_provinces_code ON provinces(code);
+    CREATE INDEX IF NOT EXISTS idx_regencies_code ON regencies(code);
+    CREATE INDEX IF NOT EXISTS idx_regencies_province_id ON regencies(province_id);
+    CREATE INDEX IF NOT EXISTS idx_districts_code ON districts(code);
+    CREATE INDEX IF NOT EXISTS idx_districts_regency_id ON districts(regency_id);
+    CREATE INDEX IF NOT EXISTS idx_villages_code ON villages(code);
+    CREATE INDEX IF NOT EXISTS idx_villages_district_id ON villages(district_id);
+    ",
+    // Soft delete: rows with a deleted_at timestamp are hidden from every read
+    "
+    ALTER TABLE provinces ADD COLUMN deleted_at TEXT;
+    ALTER TABLE regencies ADD COLUMN deleted_at TEXT;
+    ALTER TABLE districts ADD COLUMN deleted_at TEXT;
+    ALTER TABLE villages ADD COLUMN deleted_at TEXT;
+    ",
+    // Row versions for optimistic concurrency (ETag / If-Match)
+    "
+    ALTER TABLE provinces ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE regencies ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE districts ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE villages ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    ",
+    // Change feed, one row per insert/update/delete in seq order
+    "
+    CREATE TABLE changes (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        level TEXT NOT NULL,
+        row_id INTEGER NOT NULL,
+        code TEXT NOT NULL,
+        op TEXT NOT NULL,
+        changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+    );
+    ",
+    // Webhook subscriptions and their delivery log
+    "
+    CREATE TABLE webhooks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        url TEXT NOT NULL,
+        secret TEXT NOT NULL,
+        levels TEXT NOT NULL DEFAULT '',
+        code_prefix TEXT,
+        last_seq INTEGER NOT NULL,
+        created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+    );
+    CREATE TABLE webhook_deliveries (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        webhook_id INTEGER NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+        seq INTEGER NOT NULL,
+        event TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'pending',
+        attempts INTEGER NOT NULL DEFAULT 0,
+        next_attempt_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+        response_status INTEGER,
+        last_error TEXT,
+        created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+        delivered_at TEXT
+    );
+    CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries(status, next_attempt_at);
+    CREATE INDEX idx_webhook_deliveries_webhook_id ON webhook_deliveries(webhook_id);
+    ",
+];
 
 #[derive(Error, Debug)]
 pub enum DatabaseError {
@@ -11,10 +122,42 @@ pub enum DatabaseError {
     SQLiteError(#[from] rusqlite::Error),
     #[error("No data found")]
     NotFound,
+    #[error("Validation failed")]
+    Validation(Vec<FieldError>),
+    // Number of live rows per child table blocking a non-cascading delete
+    #[error("Row still has children")]
+    HasChildren(BTreeMap<String, usize>),
+    // If-Match did not match; carries the current version of the row
+    #[error("Row was modified, current version is {0}")]
+    PreconditionFailed(i64),
+}
+
+// Latest change of the dataset. Everything derived from the data (cached
+// responses, list ETags, Last-Modified) is valid for one revision.
+#[derive(Debug, Clone, Copy)]
+pub struct Revision {
+    pub seq: i64,
+    pub modified: SystemTime,
+}
+
+impl Revision {
+    // Revision `seq` made now, in whole seconds as in Last-Modified
+    pub(crate) fn now(seq: i64) -> Revision {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Revision {
+            seq,
+            modified: UNIX_EPOCH + Duration::from_secs(now.as_secs()),
+        }
+    }
 }
 
 pub struct Database {
     conn: Arc<Mutex<Connection>>,
+    // Pinged after every commit that recorded changes
+    changes: broadcast::Sender<()>,
+    revision: Arc<RwLock<Revision>>,
 }
 
 impl Database {
@@ -26,22 +169,109 @@ impl Database {
                 | OpenFlags::SQLITE_OPEN_CREATE
                 | OpenFlags::SQLITE_OPEN_FULL_MUTEX,
         )?;
+        Self::from_connection(conn)
+    }
+
+    // For commands that only read: the file is opened read-only and never
+    // migrated. A file behind this build's schema, such as the published
+    // dataset, is read through a migrated in-memory copy instead.
+    pub fn open_read_only(path: &str) -> Result<Self, DatabaseError> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_FULL_MUTEX,
+        )?;
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version >= SCHEMA_VERSION {
+            return Self::with_connection(conn);
+        }
+        let mut copy = Connection::open_in_memory_with_flags(
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_FULL_MUTEX,
+        )?;
+        copy_pages(&conn, &mut copy)?;
+        Self::from_connection(copy)
+    }
+
+    // A private in-memory copy of `image`, the bytes of an SQLite database
+    // file. Writes are never persisted.
+    #[cfg(feature = "embedded")]
+    pub fn from_image(image: &[u8]) -> Result<Self, DatabaseError> {
+        use rusqlite::{ffi, serialize::OwnedData, DatabaseName};
+        use std::ptr::NonNull;
+
+        let mut conn = Connection::open_in_memory_with_flags(
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_FULL_MUTEX,
+        )?;
+        let no_memory = || rusqlite::Error::SqliteFailure(ffi::Error::new(ffi::SQLITE_NOMEM), None);
+        // SAFETY: deserialize takes ownership of a buffer that must come from
+        // sqlite3_malloc; it is allocated with the image's length and filled
+        // before being handed over.
+        let data = unsafe {
+            let ptr = ffi::sqlite3_malloc64(image.len() as u64).cast::<u8>();
+            let ptr = NonNull::new(ptr).ok_or_else(no_memory)?;
+            std::ptr::copy_nonoverlapping(image.as_ptr(), ptr.as_ptr(), image.len());
+            OwnedData::from_raw_nonnull(ptr, image.len())
+        };
+        conn.deserialize(DatabaseName::Main, data, false)?;
+        Self::from_connection(conn)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self, DatabaseError> {
+        migrate(&conn)?;
+        Self::with_connection(conn)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, DatabaseError> {
+        // Without any recorded change the data is as old as this process
+        let revision = latest_revision(&conn)?.unwrap_or(Revision::now(0));
 
         let conn = Arc::new(Mutex::new(conn));
+        let (changes, _) = broadcast::channel(16);
+
+        Ok(Database {
+            conn,
+            changes,
+            revision: Arc::new(RwLock::new(revision)),
+        })
+    }
+
+    pub fn revision(&self) -> Revision {
+        *self.revision.read().unwrap()
+    }
+
+    // Wakes up whenever new rows were added to the change log. Missed pings
+    // do not matter, readers always catch up from the log itself.
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<()> {
+        self.changes.subscribe()
+    }
 
-        Ok(Database { conn })
+    // Commit a transaction that called record_change
+    fn commit(&self, tx: Transaction) -> Result<(), DatabaseError> {
+        let revision = latest_revision(&tx)?;
+        tx.commit()?;
+        if let Some(revision) = revision {
+            *self.revision.write().unwrap() = revision;
+        }
+        // Nobody listening is not an error
+        let _ = self.changes.send(());
+        Ok(())
     }
 
     // SECTION: Provinces
     pub fn get_all_provinces(&self) -> Result<Vec<Province>, DatabaseError> {
         let conn = self.conn.lock().unwrap();
 
-        let mut stmt = conn.prepare("SELECT id, code, name FROM provinces")?;
+        let mut stmt =
+            conn.prepare("SELECT id, code, name, version FROM provinces WHERE deleted_at IS NULL")?;
         let province_iter = stmt.query_map([], |row| {
             Ok(Province {
                 id: row.get(0)?,
                 code: row.get(1)?,
                 name: row.get(2)?,
+                version: row.get(3)?,
             })
         })?;
 
@@ -54,7 +284,9 @@ impl Database {
 
     pub fn get_one_province(&self, province_id: i64) -> Result<Option<Province>, DatabaseError> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, code, name FROM provinces WHERE id = ?1")?;
+        let mut stmt = conn.prepare(
+            "SELECT id, code, name, version FROM provinces WHERE id = ?1 AND deleted_at IS NULL",
+        )?;
 
         let province = stmt
             .query_row(params![province_id], |row| {
@@ -62,6 +294,7 @@ impl Database {
                     id: row.get(0)?,
                     code: row.get(1)?,
                     name: row.get(2)?,
+                    version: row.get(3)?,
                 })
             })
             .optional()?;
@@ -71,20 +304,35 @@ impl Database {
 
     pub fn create_province(&self, province: &Province) -> Result<i64, DatabaseError> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
+        validation::validate(Level::Province, &province.code, &province.name, None)?;
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
             "INSERT INTO provinces (code, name) VALUES (?1, ?2)",
             params![province.code, province.name],
         )?;
-        Ok(conn.last_insert_rowid())
+        let id = tx.last_insert_rowid();
+        record_change(&tx, Level::Province, id, &province.code, ChangeOp::Insert)?;
+        self.commit(tx)?;
+        Ok(id)
     }
 
     // Update an existing province
-    pub fn update_province(&self, id: i64, province: &Province) -> Result<(), DatabaseError> {
+    pub fn update_province(
+        &self,
+        id: i64,
+        province: &Province,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
         let conn = self.conn.lock().unwrap();
+        let version = rules::check_version(&*conn, Level::Province, id, if_match)?;
+        rules::check_code_change(&*conn, Level::Province, id, &province.code)?;
+        validation::validate(Level::Province, &province.code, &province.name, None)?;
 
         // Check if the province exists before updating
-        let affected_rows = conn.execute(
-            "UPDATE provinces SET code = ?1, name = ?2 WHERE id = ?3",
+        let tx = conn.unchecked_transaction()?;
+        let affected_rows = tx.execute(
+            "UPDATE provinces SET code = ?1, name = ?2, version = version + 1
+            WHERE id = ?3 AND deleted_at IS NULL",
             params![province.code, province.name, id],
         )?;
 
@@ -92,23 +340,20 @@ impl Database {
         if affected_rows == 0 {
             return Err(DatabaseError::NotFound);
         }
+        record_change(&tx, Level::Province, id, &province.code, ChangeOp::Update)?;
+        self.commit(tx)?;
 
-        Ok(())
+        Ok(version + 1)
     }
 
     // Delete a province
-    pub fn delete_province(&self, id: i64) -> Result<(), DatabaseError> {
-        let conn = self.conn.lock().unwrap();
-
-        // Check if the province exists and can be deleted
-        let affected_rows = conn.execute("DELETE FROM provinces WHERE id = ?1", params![id])?;
-
-        // If no rows were affected, it means the province doesn't exist
-        if affected_rows == 0 {
-            return Err(DatabaseError::NotFound);
-        }
-
-        Ok(())
+    pub fn delete_province(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        self.delete_subtree(Level::Province, id, options, if_match)
     }
     // !SECTION: Provinces
 
@@ -119,7 +364,7 @@ impl Database {
     ) -> Result<Vec<Regency>, DatabaseError> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn
-            .prepare("SELECT id, code, name, province_id FROM regencies WHERE province_id = ?1")?;
+            .prepare("SELECT id, code, name, province_id, version FROM regencies WHERE province_id = ?1 AND deleted_at IS NULL")?;
 
         let regency_iter = stmt.query_map(params![province_id], |row| {
             Ok(Regency {
@@ -127,6 +372,7 @@ impl Database {
                 code: row.get(1)?,
                 name: row.get(2)?,
                 province_id: row.get(3)?,
+                version: row.get(4)?,
             })
         })?;
 
@@ -140,7 +386,7 @@ impl Database {
     pub fn get_one_regency(&self, regency_id: i64) -> Result<Option<Regency>, DatabaseError> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn
-            .prepare("SELECT id, code, name, province_id FROM regencies WHERE id = ?1")?;
+            .prepare("SELECT id, code, name, province_id, version FROM regencies WHERE id = ?1 AND deleted_at IS NULL")?;
 
         let regency = stmt
             .query_row(params![regency_id], |row| {
@@ -149,6 +395,7 @@ impl Database {
                     code: row.get(1)?,
                     name: row.get(2)?,
                     province_id: row.get(3)?,
+                    version: row.get(4)?,
                 })
             })
             .optional()?;
@@ -158,19 +405,46 @@ impl Database {
 
     pub fn create_regency(&self, regency: &Regency) -> Result<i64, DatabaseError> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
+        validate_with_parent(
+            &conn,
+            Level::Regency,
+            &regency.code,
+            &regency.name,
+            regency.province_id,
+        )?;
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
             "INSERT INTO regencies (code, name, province_id) VALUES (?1, ?2, ?3)",
             params![regency.code, regency.name, regency.province_id],
         )?;
-        Ok(conn.last_insert_rowid())
+        let id = tx.last_insert_rowid();
+        record_change(&tx, Level::Regency, id, &regency.code, ChangeOp::Insert)?;
+        self.commit(tx)?;
+        Ok(id)
     }
 
-    pub fn update_regency(&self, id: i64, regency: &Regency) -> Result<(), DatabaseError> {
+    pub fn update_regency(
+        &self,
+        id: i64,
+        regency: &Regency,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
         let conn = self.conn.lock().unwrap();
+        let version = rules::check_version(&*conn, Level::Regency, id, if_match)?;
+        rules::check_code_change(&*conn, Level::Regency, id, &regency.code)?;
+        validate_with_parent(
+            &conn,
+            Level::Regency,
+            &regency.code,
+            &regency.name,
+            regency.province_id,
+        )?;
 
         // Check if the regency exists before updating
-        let affected_rows = conn.execute(
-            "UPDATE regencies SET code = ?1, name = ?2, province_id = ?3 WHERE id = ?4",
+        let tx = conn.unchecked_transaction()?;
+        let affected_rows = tx.execute(
+            "UPDATE regencies SET code = ?1, name = ?2, province_id = ?3, version = version + 1
+            WHERE id = ?4 AND deleted_at IS NULL",
             params![regency.code, regency.name, regency.province_id, id],
         )?;
 
@@ -178,22 +452,19 @@ impl Database {
         if affected_rows == 0 {
             return Err(DatabaseError::NotFound);
         }
+        record_change(&tx, Level::Regency, id, &regency.code, ChangeOp::Update)?;
+        self.commit(tx)?;
 
-        Ok(())
+        Ok(version + 1)
     }
 
-    pub fn delete_regency(&self, id: i64) -> Result<(), DatabaseError> {
-        let conn = self.conn.lock().unwrap();
-
-        // Check if the regency exists and can be deleted
-        let affected_rows = conn.execute("DELETE FROM regencies WHERE id = ?1", params![id])?;
-
-        // If no rows were affected, it means the regency doesn't exist
-        if affected_rows == 0 {
-            return Err(DatabaseError::NotFound);
-        }
-
-        Ok(())
+    pub fn delete_regency(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        self.delete_subtree(Level::Regency, id, options, if_match)
     }
     // !SECTION: Regencies
 
@@ -204,7 +475,7 @@ impl Database {
     ) -> Result<Vec<District>, DatabaseError> {
         let conn = self.conn.lock().unwrap();
         let mut stmt =
-            conn.prepare("SELECT id, code, name, regency_id FROM districts WHERE regency_id = ?1")?;
+            conn.prepare("SELECT id, code, name, regency_id, version FROM districts WHERE regency_id = ?1 AND deleted_at IS NULL")?;
 
         let district_iter = stmt.query_map(params![regency_id], |row| {
             Ok(District {
@@ -212,6 +483,7 @@ impl Database {
                 code: row.get(1)?,
                 name: row.get(2)?,
                 regency_id: row.get(3)?,
+                version: row.get(4)?,
             })
         })?;
 
@@ -224,8 +496,9 @@ impl Database {
 
     pub fn get_one_district(&self, district_id: i64) -> Result<Option<District>, DatabaseError> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn
-            .prepare("SELECT id, code, name, regency_id FROM districts WHERE id = ?1")?;
+        let mut stmt = conn.prepare(
+            "SELECT id, code, name, regency_id, version FROM districts WHERE id = ?1 AND deleted_at IS NULL",
+        )?;
 
         let district = stmt
             .query_row(params![district_id], |row| {
@@ -234,6 +507,7 @@ impl Database {
                     code: row.get(1)?,
                     name: row.get(2)?,
                     regency_id: row.get(3)?,
+                    version: row.get(4)?,
                 })
             })
             .optional()?;
@@ -243,19 +517,46 @@ impl Database {
 
     pub fn create_district(&self, district: &District) -> Result<i64, DatabaseError> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
+        validate_with_parent(
+            &conn,
+            Level::District,
+            &district.code,
+            &district.name,
+            district.regency_id,
+        )?;
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
             "INSERT INTO districts (code, name, regency_id) VALUES (?1, ?2, ?3)",
             params![district.code, district.name, district.regency_id],
         )?;
-        Ok(conn.last_insert_rowid())
+        let id = tx.last_insert_rowid();
+        record_change(&tx, Level::District, id, &district.code, ChangeOp::Insert)?;
+        self.commit(tx)?;
+        Ok(id)
     }
 
-    pub fn update_district(&self, id: i64, district: &District) -> Result<(), DatabaseError> {
+    pub fn update_district(
+        &self,
+        id: i64,
+        district: &District,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
         let conn = self.conn.lock().unwrap();
+        let version = rules::check_version(&*conn, Level::District, id, if_match)?;
+        rules::check_code_change(&*conn, Level::District, id, &district.code)?;
+        validate_with_parent(
+            &conn,
+            Level::District,
+            &district.code,
+            &district.name,
+            district.regency_id,
+        )?;
 
         // Check if the district exists before updating
-        let affected_rows = conn.execute(
-            "UPDATE districts SET code = ?1, name = ?2, regency_id = ?3 WHERE id = ?4",
+        let tx = conn.unchecked_transaction()?;
+        let affected_rows = tx.execute(
+            "UPDATE districts SET code = ?1, name = ?2, regency_id = ?3, version = version + 1
+            WHERE id = ?4 AND deleted_at IS NULL",
             params![district.code, district.name, district.regency_id, id],
         )?;
 
@@ -263,22 +564,19 @@ impl Database {
         if affected_rows == 0 {
             return Err(DatabaseError::NotFound);
         }
+        record_change(&tx, Level::District, id, &district.code, ChangeOp::Update)?;
+        self.commit(tx)?;
 
-        Ok(())
+        Ok(version + 1)
     }
 
-    pub fn delete_district(&self, id: i64) -> Result<(), DatabaseError> {
-        let conn = self.conn.lock().unwrap();
-
-        // Check if the district exists and can be deleted
-        let affected_rows = conn.execute("DELETE FROM districts WHERE id = ?1", params![id])?;
-
-        // If no rows were affected, it means the district doesn't exist
-        if affected_rows == 0 {
-            return Err(DatabaseError::NotFound);
-        }
-
-        Ok(())
+    pub fn delete_district(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        self.delete_subtree(Level::District, id, options, if_match)
     }
     // !SECTION: Districts
 
@@ -289,7 +587,7 @@ impl Database {
     ) -> Result<Vec<Village>, DatabaseError> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn
-            .prepare("SELECT id, code, name, district_id FROM villages WHERE district_id = ?1")?;
+            .prepare("SELECT id, code, name, district_id, version FROM villages WHERE district_id = ?1 AND deleted_at IS NULL")?;
 
         let village_iter = stmt.query_map(params![district_id], |row| {
             Ok(Village {
@@ -297,6 +595,7 @@ impl Database {
                 code: row.get(1)?,
                 name: row.get(2)?,
                 district_id: row.get(3)?,
+                version: row.get(4)?,
             })
         })?;
 
@@ -309,8 +608,9 @@ impl Database {
 
     pub fn get_one_village(&self, village_id: i64) -> Result<Option<Village>, DatabaseError> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn
-            .prepare("SELECT id, code, name, district_id FROM villages WHERE id = ?1")?;
+        let mut stmt = conn.prepare(
+            "SELECT id, code, name, district_id, version FROM villages WHERE id = ?1 AND deleted_at IS NULL",
+        )?;
 
         let village = stmt
             .query_row(params![village_id], |row| {
@@ -319,6 +619,7 @@ impl Database {
                     code: row.get(1)?,
                     name: row.get(2)?,
                     district_id: row.get(3)?,
+                    version: row.get(4)?,
                 })
             })
             .optional()?;
@@ -328,19 +629,46 @@ impl Database {
 
     pub fn create_village(&self, village: &Village) -> Result<i64, DatabaseError> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
+        validate_with_parent(
+            &conn,
+            Level::Village,
+            &village.code,
+            &village.name,
+            village.district_id,
+        )?;
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
             "INSERT INTO villages (code, name, district_id) VALUES (?1, ?2, ?3)",
             params![village.code, village.name, village.district_id],
         )?;
-        Ok(conn.last_insert_rowid())
+        let id = tx.last_insert_rowid();
+        record_change(&tx, Level::Village, id, &village.code, ChangeOp::Insert)?;
+        self.commit(tx)?;
+        Ok(id)
     }
 
-    pub fn update_village(&self, id: i64, village: &Village) -> Result<(), DatabaseError> {
+    pub fn update_village(
+        &self,
+        id: i64,
+        village: &Village,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
         let conn = self.conn.lock().unwrap();
+        let version = rules::check_version(&*conn, Level::Village, id, if_match)?;
+        rules::check_code_change(&*conn, Level::Village, id, &village.code)?;
+        validate_with_parent(
+            &conn,
+            Level::Village,
+            &village.code,
+            &village.name,
+            village.district_id,
+        )?;
 
         // Check if the village exists before updating
-        let affected_rows = conn.execute(
-            "UPDATE villages SET code = ?1, name = ?2, district_id = ?3 WHERE id = ?4",
+        let tx = conn.unchecked_transaction()?;
+        let affected_rows = tx.execute(
+            "UPDATE villages SET code = ?1, name = ?2, district_id = ?3, version = version + 1
+            WHERE id = ?4 AND deleted_at IS NULL",
             params![village.code, village.name, village.district_id, id],
         )?;
 
@@ -348,66 +676,639 @@ impl Database {
         if affected_rows == 0 {
             return Err(DatabaseError::NotFound);
         }
+        record_change(&tx, Level::Village, id, &village.code, ChangeOp::Update)?;
+        self.commit(tx)?;
 
-        Ok(())
+        Ok(version + 1)
     }
 
-    pub fn delete_village(&self, id: i64) -> Result<(), DatabaseError> {
+    pub fn delete_village(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        self.delete_subtree(Level::Village, id, options, if_match)
+    }
+
+    pub fn get_full_admin_data(
+        &self,
+        village_id: i64,
+    ) -> Result<Option<FullAdminData>, DatabaseError> {
         let conn = self.conn.lock().unwrap();
+        query_full_admin_data(&conn, "v.id = ?1", &village_id)
+    }
 
-        // Check if the village exists and can be deleted
-        let affected_rows = conn.execute("DELETE FROM villages WHERE id = ?1", params![id])?;
+    pub fn get_full_admin_data_by_code(
+        &self,
+        village_code: &str,
+    ) -> Result<Option<FullAdminData>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        query_full_admin_data(&conn, "v.code = ?1", &village_code)
+    }
+    // !SECTION: Villages
 
-        // If no rows were affected, it means the village doesn't exist
-        if affected_rows == 0 {
-            return Err(DatabaseError::NotFound);
+    // SECTION: Delete
+    // Soft delete (or with `hard`, remove) the rows of rules::delete_plan in
+    // one transaction. `dry_run` reports the plan without touching anything.
+    fn delete_subtree(
+        &self,
+        level: Level,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let plan = rules::delete_plan(&*tx, level, id, options, if_match)?;
+        if options.dry_run {
+            return Ok(plan);
+        }
+
+        // Children first, so a hard delete never leaves dangling references
+        for level in [
+            Level::Village,
+            Level::District,
+            Level::Regency,
+            Level::Province,
+        ] {
+            let ids: Vec<i64> = plan
+                .rows
+                .iter()
+                .filter(|row| row.level == level)
+                .map(|row| row.id)
+                .collect();
+            for chunk in ids.chunks(BATCH_CHUNK_SIZE) {
+                let placeholders = vec!["?"; chunk.len()].join(", ");
+                let sql = if options.hard {
+                    format!(
+                        "DELETE FROM {} WHERE id IN ({})",
+                        level.table(),
+                        placeholders
+                    )
+                } else {
+                    format!(
+                        "UPDATE {} SET deleted_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'),
+                        version = version + 1 WHERE id IN ({})",
+                        level.table(),
+                        placeholders
+                    )
+                };
+                tx.execute(&sql, params_from_iter(chunk.iter()))?;
+            }
+        }
+        for row in &plan.rows {
+            record_change(&tx, row.level, row.id, &row.code, ChangeOp::Delete)?;
+        }
+
+        self.commit(tx)?;
+        Ok(plan)
+    }
+    // !SECTION: Delete
+
+    // SECTION: Batch
+    pub fn resolve_codes(
+        &self,
+        level: Level,
+        codes: &[String],
+    ) -> Result<Vec<AdminHierarchy>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let rows = resolve_hierarchies(&conn, level, "code", codes)?;
+        Ok(rows.into_iter().map(|(_, hierarchy)| hierarchy).collect())
+    }
+
+    // Case-insensitive exact match on the name of the given level; `names`
+    // must already be uppercased
+    pub fn resolve_names(
+        &self,
+        level: Level,
+        names: &[String],
+    ) -> Result<Vec<AdminHierarchy>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let rows = resolve_hierarchies(&conn, level, "name", names)?;
+        Ok(rows.into_iter().map(|(_, hierarchy)| hierarchy).collect())
+    }
+
+    pub fn resolve_ids(
+        &self,
+        level: Level,
+        ids: &[i64],
+    ) -> Result<Vec<(i64, AdminHierarchy)>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        resolve_hierarchies(&conn, level, "id", ids)
+    }
+    // !SECTION: Batch
+
+    // SECTION: Upsert
+    pub fn find_id_by_code(&self, level: Level, code: &str) -> Result<Option<i64>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        find_id_by_code(&conn, level, code)
+    }
+
+    // Insert or update the row of `level` with this code. The parent is
+    // found by the code minus its last segment.
+    pub fn upsert_by_code(
+        &self,
+        level: Level,
+        code: &str,
+        name: &str,
+    ) -> Result<UpsertResult, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        let result = upsert_row(&tx, level, code, name)?;
+        self.commit(tx)?;
+        Ok(result)
+    }
+
+    // Upsert many rows in one transaction. Parents are applied before their
+    // children whatever the input order. Results follow the input order; a
+    // row failing validation does not stop the others.
+    pub fn upsert_many(
+        &self,
+        rows: &[BulkUpsertLine],
+    ) -> Result<Vec<Result<UpsertResult, Vec<FieldError>>>, DatabaseError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let mut order: Vec<usize> = (0..rows.len()).collect();
+        order.sort_by_key(|&i| Level::from_code(&rows[i].code));
+
+        let mut results = vec![Err(Vec::new()); rows.len()];
+        for i in order {
+            let row = &rows[i];
+            let Some(level) = Level::from_code(&row.code) else {
+                results[i] = Err(vec![FieldError::new("code", "is not a region code")]);
+                continue;
+            };
+            results[i] = match upsert_row(&tx, level, &row.code, &row.name) {
+                Ok(result) => Ok(result),
+                Err(DatabaseError::Validation(fields)) => Err(fields),
+                Err(e) => return Err(e),
+            };
+        }
+
+        self.commit(tx)?;
+        Ok(results)
+    }
+    // !SECTION: Upsert
+
+    // SECTION: Regions
+    // Live rows of one level by id, in any order. Used by the GraphQL loaders
+    // to fetch many rows in one query.
+    pub fn regions_by_id(
+        &self,
+        level: Level,
+        ids: &[i64],
+    ) -> Result<Vec<RegionRow>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        select_region_rows(&conn, level, "id", ids, true)
+    }
+
+    // Live rows of one level whose parent is any of `parent_ids`, by code
+    pub fn regions_by_parent(
+        &self,
+        level: Level,
+        parent_ids: &[i64],
+    ) -> Result<Vec<RegionRow>, DatabaseError> {
+        let column = level.parent_column().expect("level with a parent");
+        let conn = self.conn.lock().unwrap();
+        select_region_rows(&conn, level, column, parent_ids, true)
+    }
+
+    // Live rows of one level whose name contains `query`, case-insensitive,
+    // by code
+    pub fn search_names(
+        &self,
+        level: Level,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<AdminHierarchy>, DatabaseError> {
+        let (select, from, live) = hierarchy_query(level);
+        let alias = &level.table()[..1];
+        let pattern = format!(
+            "%{}%",
+            query
+                .to_uppercase()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let sql = format!(
+            "SELECT {} FROM {} WHERE UPPER({alias}.name) LIKE ?1 ESCAPE '\\' AND {}
+            ORDER BY {alias}.code LIMIT ?2",
+            select, from, live
+        );
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![pattern, limit as i64], |row| {
+            hierarchy_row(level, row)
+        })?;
+        let mut found = Vec::new();
+        for row in rows {
+            found.push(row?.1);
+        }
+        Ok(found)
+    }
+    // !SECTION: Regions
+
+    // SECTION: Backup
+    // Point-in-time copy of the whole database at `dest`, which must not
+    // exist, taken while the server keeps running. A database file is read
+    // through a connection of its own, so other requests only wait while
+    // SQLite copies the pages. VACUUM INTO also compacts the copy.
+    pub fn backup_to(&self, dest: &Path, vacuum: bool) -> Result<(), DatabaseError> {
+        let path = {
+            let conn = self.conn.lock().unwrap();
+            conn.path()
+                .filter(|path| !path.is_empty())
+                .map(str::to_string)
+        };
+        match path {
+            Some(path) => {
+                let source = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+                copy_database(&source, dest, vacuum)
+            }
+            // In-memory databases are only reachable through this connection
+            None => copy_database(&self.conn.lock().unwrap(), dest, vacuum),
+        }
+    }
+
+    // Checks a backup before it is restored: an intact SQLite file with the
+    // region tables, of a schema version this build can migrate from
+    pub fn inspect_backup(path: &Path) -> Result<BackupInfo, DatabaseError> {
+        let invalid = |field: &str, message: String| {
+            DatabaseError::Validation(vec![FieldError::new(field, message)])
+        };
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let integrity: Vec<String> = conn
+            .prepare("PRAGMA integrity_check")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        if integrity != ["ok"] {
+            return Err(invalid("integrity_check", integrity.join("; ")));
+        }
+        let schema_version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if !(0..=SCHEMA_VERSION).contains(&schema_version) {
+            return Err(invalid(
+                "schema_version",
+                format!(
+                    "is {}, this build supports up to {}",
+                    schema_version, SCHEMA_VERSION
+                ),
+            ));
         }
+        let tables: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'
+            AND name IN ('provinces', 'regencies', 'districts', 'villages')",
+            [],
+            |row| row.get(0),
+        )?;
+        if tables != 4 {
+            return Err(invalid(
+                "tables",
+                "provinces, regencies, districts and villages are required".to_string(),
+            ));
+        }
+        // The published dataset predates the change log
+        let has_changes: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'changes')",
+            [],
+            |row| row.get(0),
+        )?;
+        let revision = match has_changes {
+            true => latest_revision(&conn)?.map_or(0, |revision| revision.seq),
+            false => 0,
+        };
+        Ok(BackupInfo {
+            schema_version,
+            revision,
+        })
+    }
+    // !SECTION: Backup
+
+    // SECTION: Changes
+    // Seq of the latest change, 0 when the feed is empty
+    pub fn change_head(&self) -> Result<i64, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let head = conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM changes", [], |row| {
+            row.get(0)
+        })?;
+        Ok(head)
+    }
 
+    // Changes with seq greater than `since`, oldest first. Inserts and updates
+    // carry the row as it is now; deletes are tombstones.
+    pub fn changes_since(&self, since: i64, limit: usize) -> Result<ChangeFeed, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        query_changes(&conn, since, limit)
+    }
+    // !SECTION: Changes
+
+    // SECTION: Webhooks
+    // New subscriptions start at the current head of the change feed, so
+    // only later changes are delivered
+    pub fn create_webhook(&self, webhook: &CreateWebhook) -> Result<Webhook, DatabaseError> {
+        validation::validate_webhook(webhook)?;
+        let conn = self.conn.lock().unwrap();
+
+        let levels = webhook
+            .filter
+            .levels
+            .iter()
+            .map(|level| level.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        conn.execute(
+            "INSERT INTO webhooks (url, secret, levels, code_prefix, last_seq)
+            VALUES (?1, ?2, ?3, ?4, (SELECT COALESCE(MAX(seq), 0) FROM changes))",
+            params![
+                webhook.url,
+                webhook.secret,
+                levels,
+                webhook.filter.code_prefix
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+        query_webhooks(&conn, Some(id))?
+            .pop()
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    pub fn get_webhooks(&self) -> Result<Vec<Webhook>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        query_webhooks(&conn, None)
+    }
+
+    pub fn get_one_webhook(&self, id: i64) -> Result<Option<Webhook>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        Ok(query_webhooks(&conn, Some(id))?.pop())
+    }
+
+    pub fn delete_webhook(&self, id: i64) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM webhook_deliveries WHERE webhook_id = ?1",
+            params![id],
+        )?;
+        if tx.execute("DELETE FROM webhooks WHERE id = ?1", params![id])? == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        tx.commit()?;
         Ok(())
     }
 
-    pub fn get_full_admin_data(
+    // Delivery log of a webhook, newest first
+    pub fn get_webhook_deliveries(
         &self,
-        village_id: i64,
-    ) -> Result<Option<FullAdminData>, DatabaseError> {
+        webhook_id: i64,
+        status: Option<DeliveryStatus>,
+        limit: usize,
+    ) -> Result<Vec<WebhookDelivery>, DatabaseError> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "
-            SELECT 
-                p.code as province_code, 
-                p.name as province_name, 
-                r.code as regency_code, 
-                r.name as regency_name, 
-                d.code as district_code, 
-                d.name as district_name, 
-                v.code as village_code, 
-                v.name as village_name
-            FROM villages v
-            JOIN districts d ON v.district_id = d.id
-            JOIN regencies r ON d.regency_id = r.id
-            JOIN provinces p ON r.province_id = p.id
-            WHERE v.id = ?1
-        ",
+            "SELECT id, webhook_id, seq, event, status, attempts, next_attempt_at,
+                response_status, last_error, created_at, delivered_at
+            FROM webhook_deliveries
+            WHERE webhook_id = ?1 AND (?2 IS NULL OR status = ?2)
+            ORDER BY id DESC LIMIT ?3",
         )?;
+        let deliveries = stmt
+            .query_map(
+                params![webhook_id, status.map(|s| s.as_str()), limit as i64],
+                |row| {
+                    Ok(WebhookDelivery {
+                        id: row.get(0)?,
+                        webhook_id: row.get(1)?,
+                        seq: row.get(2)?,
+                        event: row.get(3)?,
+                        status: DeliveryStatus::parse(&row.get::<_, String>(4)?)
+                            .unwrap_or(DeliveryStatus::Pending),
+                        attempts: row.get(5)?,
+                        next_attempt_at: row.get(6)?,
+                        response_status: row.get(7)?,
+                        last_error: row.get(8)?,
+                        created_at: row.get(9)?,
+                        delivered_at: row.get(10)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(deliveries)
+    }
 
-        let full_data = stmt
-            .query_row(params![village_id], |row| {
-                Ok(FullAdminData {
-                    province_code: row.get(0)?,
-                    province_name: row.get(1)?,
-                    city_code: row.get(2)?,
-                    city_name: row.get(3)?,
-                    region_code: row.get(4)?,
-                    region_name: row.get(5)?,
-                    village_code: row.get(6)?,
-                    village_name: row.get(7)?,
+    // Queue deliveries for changes past the cursor of each webhook. Returns
+    // whether more changes are waiting.
+    pub fn enqueue_webhook_deliveries(&self, limit: usize) -> Result<bool, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let webhooks = query_webhooks(&conn, None)?;
+        let Some(since) = webhooks.iter().map(|webhook| webhook.last_seq).min() else {
+            return Ok(false);
+        };
+        let feed = query_changes(&conn, since, limit)?;
+        let next: i64 = feed.next.parse().unwrap_or(since);
+
+        let tx = conn.unchecked_transaction()?;
+        for webhook in &webhooks {
+            for change in &feed.changes {
+                if change.seq <= webhook.last_seq || !webhook.filter.matches(change) {
+                    continue;
+                }
+                let event = change.event();
+                let payload = serde_json::to_string(change).unwrap_or_default();
+                tx.execute(
+                    "INSERT INTO webhook_deliveries (webhook_id, seq, event, payload)
+                    VALUES (?1, ?2, ?3, ?4)",
+                    params![webhook.id, change.seq, event, payload],
+                )?;
+            }
+            if next > webhook.last_seq {
+                tx.execute(
+                    "UPDATE webhooks SET last_seq = ?1 WHERE id = ?2",
+                    params![next, webhook.id],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(feed.has_more)
+    }
+
+    // Deliveries whose next attempt is due, oldest first
+    pub fn due_webhook_deliveries(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<PendingDelivery>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT d.id, w.url, w.secret, d.attempts, d.event, d.payload
+            FROM webhook_deliveries d
+            JOIN webhooks w ON d.webhook_id = w.id
+            WHERE d.status = 'pending'
+            AND d.next_attempt_at <= strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            ORDER BY d.id LIMIT ?1",
+        )?;
+        let rows = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut deliveries = Vec::with_capacity(rows.len());
+        for (id, url, secret, attempts, event, payload) in rows {
+            // Payloads are only ever written by enqueue_webhook_deliveries
+            let Ok(change) = serde_json::from_str(&payload) else {
+                continue;
+            };
+            deliveries.push(PendingDelivery {
+                id,
+                url,
+                secret,
+                attempts,
+                event: WebhookEvent {
+                    delivery_id: id,
+                    event,
+                    change,
+                },
+            });
+        }
+        Ok(deliveries)
+    }
+
+    pub fn mark_webhook_delivered(
+        &self,
+        id: i64,
+        response_status: u16,
+    ) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE webhook_deliveries SET status = 'delivered', attempts = attempts + 1,
+                response_status = ?1, last_error = NULL, next_attempt_at = NULL,
+                delivered_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            WHERE id = ?2",
+            params![response_status, id],
+        )?;
+        Ok(())
+    }
+
+    // Record a failed attempt. Without `retry_in_secs` the delivery is given up.
+    pub fn mark_webhook_failed(
+        &self,
+        id: i64,
+        response_status: Option<u16>,
+        error: &str,
+        retry_in_secs: Option<u64>,
+    ) -> Result<(), DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE webhook_deliveries SET attempts = attempts + 1,
+                response_status = ?1, last_error = ?2,
+                status = CASE WHEN ?3 IS NULL THEN 'failed' ELSE 'pending' END,
+                next_attempt_at = CASE WHEN ?3 IS NULL THEN NULL
+                    ELSE strftime('%Y-%m-%dT%H:%M:%fZ', 'now', '+' || ?3 || ' seconds') END
+            WHERE id = ?4",
+            params![response_status, error, retry_in_secs.map(|s| s as i64), id],
+        )?;
+        Ok(())
+    }
+    // !SECTION: Webhooks
+
+    // SECTION: Quality
+    pub fn quality_report(&self) -> Result<QualityReport, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut issues = Vec::new();
+
+        for level in [
+            Level::Province,
+            Level::Regency,
+            Level::District,
+            Level::Village,
+        ] {
+            check_malformed_codes(&conn, level, &mut issues)?;
+            check_duplicate_codes(&conn, level, &mut issues)?;
+            check_duplicate_sibling_names(&conn, level, &mut issues)?;
+            if level.parent().is_some() {
+                check_orphans(&conn, level, &mut issues)?;
+                check_code_prefixes(&conn, level, &mut issues)?;
+            }
+            if level.child().is_some() {
+                check_empty_levels(&conn, level, &mut issues)?;
+            }
+        }
+
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        let integrity_check = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+
+        let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+        let foreign_key_check = stmt
+            .query_map([], |row| {
+                Ok(ForeignKeyViolation {
+                    table: row.get(0)?,
+                    rowid: row.get(1)?,
+                    parent: row.get(2)?,
+                    fkid: row.get(3)?,
                 })
-            })
-            .optional()?;
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut counts = BTreeMap::new();
+        for issue in &issues {
+            *counts.entry(issue.check.clone()).or_insert(0) += 1;
+        }
+        let warnings = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Warning)
+            .count();
+        let mut errors = issues.len() - warnings + foreign_key_check.len();
+        if integrity_check != ["ok"] {
+            errors += integrity_check.len();
+        }
 
-        Ok(full_data)
+        Ok(QualityReport {
+            ok: errors == 0,
+            errors,
+            warnings,
+            counts,
+            integrity_check,
+            foreign_key_check,
+            issues,
+        })
     }
-    // !SECTION: Villages
+
+    // Live and soft-deleted rows per level, provinces first
+    pub fn level_counts(&self) -> Result<Vec<LevelCount>, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let mut counts = Vec::new();
+        for level in [
+            Level::Province,
+            Level::Regency,
+            Level::District,
+            Level::Village,
+        ] {
+            let sql = format!(
+                "SELECT COUNT(*) FILTER (WHERE deleted_at IS NULL),
+                    COUNT(*) FILTER (WHERE deleted_at IS NOT NULL)
+                FROM {}",
+                level.table()
+            );
+            let (live, deleted) = conn.query_row(&sql, [], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            counts.push(LevelCount {
+                level,
+                live,
+                deleted,
+            });
+        }
+        Ok(counts)
+    }
+    // !SECTION: Quality
 }
 
 // Implement Clone to allow easy sharing between threads
@@ -415,6 +1316,1023 @@ impl Clone for Database {
     fn clone(&self) -> Self {
         Database {
             conn: Arc::clone(&self.conn),
+            changes: self.changes.clone(),
+            revision: Arc::clone(&self.revision),
+        }
+    }
+}
+
+fn latest_revision(conn: &Connection) -> Result<Option<Revision>, DatabaseError> {
+    let latest = conn
+        .query_row(
+            "SELECT seq, CAST(strftime('%s', changed_at) AS INTEGER) FROM changes
+            ORDER BY seq DESC LIMIT 1",
+            [],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+        )
+        .optional()?;
+    Ok(latest.map(|(seq, secs)| Revision {
+        seq,
+        modified: UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64),
+    }))
+}
+
+fn migrate(conn: &Connection) -> Result<(), DatabaseError> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version.max(0) as usize) {
+        conn.execute_batch(&format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            migration,
+            index + 1
+        ))?;
+    }
+    Ok(())
+}
+
+fn copy_database(source: &Connection, dest: &Path, vacuum: bool) -> Result<(), DatabaseError> {
+    if vacuum {
+        source.execute("VACUUM INTO ?1", params![dest.to_string_lossy()])?;
+    } else {
+        copy_pages(source, &mut Connection::open(dest)?)?;
+    }
+    Ok(())
+}
+
+// Copy every page of `source` over `target` with the online backup API
+fn copy_pages(source: &Connection, target: &mut Connection) -> Result<(), DatabaseError> {
+    let backup = rusqlite::backup::Backup::new(source, target)?;
+    // All pages in one step, so the copy is of a single point in time.
+    // Busy or locked while a writer commits; try again shortly.
+    while backup.step(-1)? != rusqlite::backup::StepResult::Done {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Ok(())
+}
+
+// Rows of `level` below the given parents, with whether they are soft-deleted
+fn child_rows(
+    conn: &Connection,
+    level: Level,
+    parent_ids: &[i64],
+    include_deleted: bool,
+) -> Result<Vec<(i64, String, bool)>, DatabaseError> {
+    let column = level.parent_column().expect("level with a parent");
+    let filter = if include_deleted {
+        ""
+    } else {
+        "AND deleted_at IS NULL"
+    };
+
+    let mut rows = Vec::new();
+    for chunk in parent_ids.chunks(BATCH_CHUNK_SIZE) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let sql = format!(
+            "SELECT id, code, deleted_at IS NOT NULL FROM {} WHERE {} IN ({}) {}",
+            level.table(),
+            column,
+            placeholders,
+            filter
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let chunk_rows = stmt.query_map(params_from_iter(chunk.iter()), |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+        for row in chunk_rows {
+            rows.push(row?);
+        }
+    }
+    Ok(rows)
+}
+
+// Append to the change feed, inside the transaction of the write itself
+fn record_change(
+    conn: &Connection,
+    level: Level,
+    id: i64,
+    code: &str,
+    op: ChangeOp,
+) -> Result<(), DatabaseError> {
+    conn.execute(
+        "INSERT INTO changes (level, row_id, code, op) VALUES (?1, ?2, ?3, ?4)",
+        params![level.as_str(), id, code, op.as_str()],
+    )?;
+    Ok(())
+}
+
+fn query_changes(conn: &Connection, since: i64, limit: usize) -> Result<ChangeFeed, DatabaseError> {
+    let head: i64 = conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM changes", [], |row| {
+        row.get(0)
+    })?;
+
+    // One extra row tells whether another page follows
+    let mut stmt = conn.prepare(
+        "SELECT seq, level, row_id, code, op, changed_at FROM changes
+        WHERE seq > ?1 ORDER BY seq LIMIT ?2",
+    )?;
+    let mut entries = stmt
+        .query_map(params![since, limit as i64 + 1], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let has_more = entries.len() > limit;
+    entries.truncate(limit);
+
+    let mut ids: HashMap<Level, Vec<i64>> = HashMap::new();
+    let mut parsed = Vec::with_capacity(entries.len());
+    for (seq, level, id, code, op, changed_at) in entries {
+        let (Some(level), Some(op)) = (Level::parse(&level), ChangeOp::parse(&op)) else {
+            continue;
+        };
+        if op != ChangeOp::Delete {
+            ids.entry(level).or_default().push(id);
+        }
+        parsed.push((seq, level, id, code, op, changed_at));
+    }
+
+    let mut rows = HashMap::new();
+    for (level, ids) in ids {
+        for row in region_rows(conn, level, &ids)? {
+            rows.insert((level, row.id), row);
+        }
+    }
+
+    let next = parsed.last().map(|entry| entry.0).unwrap_or(since);
+    let changes = parsed
+        .into_iter()
+        .map(|(seq, level, id, code, op, changed_at)| Change {
+            seq,
+            op,
+            level,
+            id,
+            code,
+            changed_at,
+            data: match op {
+                ChangeOp::Delete => None,
+                _ => rows.get(&(level, id)).cloned(),
+            },
+        })
+        .collect();
+
+    Ok(ChangeFeed {
+        changes,
+        next: next.to_string(),
+        head: head.to_string(),
+        has_more,
+    })
+}
+
+fn query_webhooks(conn: &Connection, id: Option<i64>) -> Result<Vec<Webhook>, DatabaseError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, url, levels, code_prefix, created_at, last_seq FROM webhooks
+        WHERE ?1 IS NULL OR id = ?1 ORDER BY id",
+    )?;
+    let webhooks = stmt
+        .query_map(params![id], |row| {
+            let levels: String = row.get(2)?;
+            Ok(Webhook {
+                id: row.get(0)?,
+                url: row.get(1)?,
+                filter: ChangeFilter {
+                    levels: levels.split(',').filter_map(Level::parse).collect(),
+                    code_prefix: row.get(3)?,
+                },
+                created_at: row.get(4)?,
+                last_seq: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(webhooks)
+}
+
+// Rows of one level by id, soft-deleted ones included
+fn region_rows(
+    conn: &Connection,
+    level: Level,
+    ids: &[i64],
+) -> Result<Vec<RegionRow>, DatabaseError> {
+    select_region_rows(conn, level, "id", ids, false)
+}
+
+// Rows of one level where `column` is any of `keys`, by code for each key
+fn select_region_rows(
+    conn: &Connection,
+    level: Level,
+    column: &str,
+    keys: &[i64],
+    live_only: bool,
+) -> Result<Vec<RegionRow>, DatabaseError> {
+    let filter = if live_only {
+        "AND deleted_at IS NULL"
+    } else {
+        ""
+    };
+
+    let mut rows = Vec::new();
+    for chunk in keys.chunks(BATCH_CHUNK_SIZE) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let sql = format!(
+            "SELECT id, code, name, {}, version FROM {} WHERE {} IN ({}) {} ORDER BY code",
+            level.parent_column().unwrap_or("NULL"),
+            level.table(),
+            column,
+            placeholders,
+            filter
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let chunk_rows = stmt.query_map(params_from_iter(chunk.iter()), |row| {
+            Ok(RegionRow {
+                id: row.get(0)?,
+                code: row.get(1)?,
+                name: row.get(2)?,
+                parent_id: row.get(3)?,
+                version: row.get(4)?,
+            })
+        })?;
+        for row in chunk_rows {
+            rows.push(row?);
+        }
+    }
+    Ok(rows)
+}
+
+fn find_id_by_code(
+    conn: &Connection,
+    level: Level,
+    code: &str,
+) -> Result<Option<i64>, DatabaseError> {
+    let sql = format!(
+        "SELECT id FROM {} WHERE code = ?1 AND deleted_at IS NULL ORDER BY id LIMIT 1",
+        level.table()
+    );
+    Ok(conn
+        .query_row(&sql, params![code], |row| row.get(0))
+        .optional()?)
+}
+
+fn upsert_row(
+    conn: &Connection,
+    level: Level,
+    code: &str,
+    name: &str,
+) -> Result<UpsertResult, DatabaseError> {
+    let parent_id = rules::upsert_parent(conn, level, code, name)?;
+    let parent_column = level.parent_column();
+
+    let sql = format!(
+        "SELECT id, name, {}, version FROM {}
+        WHERE code = ?1 AND deleted_at IS NULL ORDER BY id LIMIT 1",
+        parent_column.unwrap_or("NULL"),
+        level.table()
+    );
+    let existing: Option<(i64, String, Option<i64>, i64)> = conn
+        .query_row(&sql, params![code], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .optional()?;
+
+    let (id, status, version) = match existing {
+        Some((id, current_name, current_parent, version))
+            if current_name == name && current_parent == parent_id =>
+        {
+            (id, UpsertStatus::Unchanged, version)
+        }
+        Some((id, _, _, version)) => {
+            match parent_column {
+                Some(column) => conn.execute(
+                    &format!(
+                        "UPDATE {} SET name = ?1, {} = ?2, version = version + 1 WHERE id = ?3",
+                        level.table(),
+                        column
+                    ),
+                    params![name, parent_id, id],
+                )?,
+                None => conn.execute(
+                    &format!(
+                        "UPDATE {} SET name = ?1, version = version + 1 WHERE id = ?2",
+                        level.table()
+                    ),
+                    params![name, id],
+                )?,
+            };
+            (id, UpsertStatus::Updated, version + 1)
+        }
+        None => {
+            match parent_column {
+                Some(column) => conn.execute(
+                    &format!(
+                        "INSERT INTO {} (code, name, {}) VALUES (?1, ?2, ?3)",
+                        level.table(),
+                        column
+                    ),
+                    params![code, name, parent_id],
+                )?,
+                None => conn.execute(
+                    &format!("INSERT INTO {} (code, name) VALUES (?1, ?2)", level.table()),
+                    params![code, name],
+                )?,
+            };
+            (conn.last_insert_rowid(), UpsertStatus::Created, 1)
+        }
+    };
+
+    match status {
+        UpsertStatus::Created => record_change(conn, level, id, code, ChangeOp::Insert)?,
+        UpsertStatus::Updated => record_change(conn, level, id, code, ChangeOp::Update)?,
+        _ => {}
+    }
+
+    Ok(UpsertResult {
+        level,
+        id,
+        code: code.to_string(),
+        status,
+        version,
+    })
+}
+
+fn validate_with_parent(
+    conn: &Connection,
+    level: Level,
+    code: &str,
+    name: &str,
+    parent_id: Option<i64>,
+) -> Result<(), DatabaseError> {
+    let parent_level = level.parent().expect("level with a parent");
+    let parent_code = match parent_id {
+        Some(id) => lookup_code(conn, parent_level, id)?,
+        None => None,
+    };
+    let parent = Parent {
+        field: level.parent_column().expect("level with a parent"),
+        id: parent_id,
+        code: parent_code.as_deref(),
+    };
+    validation::validate(level, code, name, Some(parent))
+}
+
+fn lookup_code(conn: &Connection, level: Level, id: i64) -> Result<Option<String>, DatabaseError> {
+    let sql = format!(
+        "SELECT code FROM {} WHERE id = ?1 AND deleted_at IS NULL",
+        level.table()
+    );
+    Ok(conn
+        .query_row(&sql, params![id], |row| row.get(0))
+        .optional()?)
+}
+
+impl RuleStore for Connection {
+    fn live_row(&self, level: Level, id: i64) -> Result<Option<(i64, String)>, DatabaseError> {
+        let sql = format!(
+            "SELECT version, code FROM {} WHERE id = ?1 AND deleted_at IS NULL",
+            level.table()
+        );
+        Ok(self
+            .query_row(&sql, params![id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?)
+    }
+
+    fn find_id(&self, level: Level, code: &str) -> Result<Option<i64>, DatabaseError> {
+        find_id_by_code(self, level, code)
+    }
+
+    fn child_rows(
+        &self,
+        level: Level,
+        parent_ids: &[i64],
+        include_deleted: bool,
+    ) -> Result<Vec<(i64, String, bool)>, DatabaseError> {
+        child_rows(self, level, parent_ids, include_deleted)
+    }
+}
+
+fn issue(
+    check: &str,
+    severity: Severity,
+    level: Level,
+    id: Option<i64>,
+    code: Option<String>,
+    detail: String,
+) -> QualityIssue {
+    QualityIssue {
+        check: check.to_string(),
+        severity,
+        level,
+        id,
+        code,
+        detail,
+    }
+}
+
+fn check_malformed_codes(
+    conn: &Connection,
+    level: Level,
+    issues: &mut Vec<QualityIssue>,
+) -> Result<(), DatabaseError> {
+    // e.g. [0-9][0-9].[0-9][0-9] for regencies
+    let pattern = validation::segment_lengths(level)
+        .iter()
+        .map(|len| "[0-9]".repeat(*len))
+        .collect::<Vec<_>>()
+        .join(".");
+    let sql = format!(
+        "SELECT id, code FROM {} WHERE (code IS NULL OR code NOT GLOB ?1)
+        AND deleted_at IS NULL",
+        level.table()
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![pattern], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+    })?;
+    for row in rows {
+        let (id, code) = row?;
+        let detail = format!("code is not a valid {} code", level.as_str());
+        issues.push(issue(
+            "malformed_code",
+            Severity::Error,
+            level,
+            Some(id),
+            code,
+            detail,
+        ));
+    }
+    Ok(())
+}
+
+fn check_duplicate_codes(
+    conn: &Connection,
+    level: Level,
+    issues: &mut Vec<QualityIssue>,
+) -> Result<(), DatabaseError> {
+    let sql = format!(
+        "SELECT code, group_concat(id, ', ') FROM {}
+        WHERE code IS NOT NULL AND deleted_at IS NULL
+        GROUP BY code HAVING COUNT(*) > 1",
+        level.table()
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for row in rows {
+        let (code, ids) = row?;
+        let detail = format!("code is used by ids {}", ids);
+        issues.push(issue(
+            "duplicate_code",
+            Severity::Error,
+            level,
+            None,
+            Some(code),
+            detail,
+        ));
+    }
+    Ok(())
+}
+
+fn check_duplicate_sibling_names(
+    conn: &Connection,
+    level: Level,
+    issues: &mut Vec<QualityIssue>,
+) -> Result<(), DatabaseError> {
+    let group = match level.parent_column() {
+        Some(column) => format!("{}, UPPER(TRIM(name))", column),
+        None => "UPPER(TRIM(name))".to_string(),
+    };
+    let sql = format!(
+        "SELECT MIN(name), group_concat(code, ', ') FROM {}
+        WHERE name IS NOT NULL AND deleted_at IS NULL
+        GROUP BY {} HAVING COUNT(*) > 1",
+        level.table(),
+        group
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for row in rows {
+        let (name, codes) = row?;
+        let detail = format!("name '{}' is shared by siblings {}", name, codes);
+        issues.push(issue(
+            "duplicate_sibling_name",
+            Severity::Warning,
+            level,
+            None,
+            None,
+            detail,
+        ));
+    }
+    Ok(())
+}
+
+fn check_orphans(
+    conn: &Connection,
+    level: Level,
+    issues: &mut Vec<QualityIssue>,
+) -> Result<(), DatabaseError> {
+    let parent = level.parent().expect("level with a parent");
+    let column = level.parent_column().expect("level with a parent");
+    let sql = format!(
+        "SELECT c.id, c.code, c.{column} FROM {} c
+        LEFT JOIN {} p ON c.{column} = p.id AND p.deleted_at IS NULL
+        WHERE p.id IS NULL AND c.deleted_at IS NULL",
+        level.table(),
+        parent.table()
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, Option<i64>>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (id, code, parent_id) = row?;
+        let detail = match parent_id {
+            Some(parent_id) => format!("{} {} does not exist", column, parent_id),
+            None => format!("{} is not set", column),
+        };
+        issues.push(issue(
+            "orphan",
+            Severity::Error,
+            level,
+            Some(id),
+            code,
+            detail,
+        ));
+    }
+    Ok(())
+}
+
+fn check_code_prefixes(
+    conn: &Connection,
+    level: Level,
+    issues: &mut Vec<QualityIssue>,
+) -> Result<(), DatabaseError> {
+    let parent = level.parent().expect("level with a parent");
+    let column = level.parent_column().expect("level with a parent");
+    let sql = format!(
+        "SELECT c.id, c.code, p.code FROM {} c
+        JOIN {} p ON c.{column} = p.id
+        WHERE substr(c.code, 1, length(p.code) + 1) IS NOT p.code || '.'
+        AND c.deleted_at IS NULL",
+        level.table(),
+        parent.table()
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, Option<String>>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (id, code, parent_code) = row?;
+        let detail = format!(
+            "code does not start with the code of its {} {}",
+            parent.as_str(),
+            parent_code.unwrap_or_default()
+        );
+        issues.push(issue(
+            "code_prefix_mismatch",
+            Severity::Error,
+            level,
+            Some(id),
+            code,
+            detail,
+        ));
+    }
+    Ok(())
+}
+
+fn check_empty_levels(
+    conn: &Connection,
+    level: Level,
+    issues: &mut Vec<QualityIssue>,
+) -> Result<(), DatabaseError> {
+    let child = level.child().expect("level with children");
+    let sql = format!(
+        "SELECT l.id, l.code FROM {} l
+        WHERE l.deleted_at IS NULL AND NOT EXISTS
+        (SELECT 1 FROM {} c WHERE c.{} = l.id AND c.deleted_at IS NULL)",
+        level.table(),
+        child.table(),
+        child.parent_column().unwrap_or_default()
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+    })?;
+    for row in rows {
+        let (id, code) = row?;
+        let detail = format!("{} has no {} rows", level.as_str(), child.as_str());
+        issues.push(issue(
+            "empty_level",
+            Severity::Warning,
+            level,
+            Some(id),
+            code,
+            detail,
+        ));
+    }
+    Ok(())
+}
+
+fn query_full_admin_data(
+    conn: &Connection,
+    filter: &str,
+    param: &dyn ToSql,
+) -> Result<Option<FullAdminData>, DatabaseError> {
+    let mut stmt = conn.prepare(&format!(
+        "
+        SELECT 
+            p.code as province_code, 
+            p.name as province_name, 
+            r.code as regency_code, 
+            r.name as regency_name, 
+            d.code as district_code, 
+            d.name as district_name, 
+            v.code as village_code, 
+            v.name as village_name
+        FROM villages v
+        JOIN districts d ON v.district_id = d.id
+        JOIN regencies r ON d.regency_id = r.id
+        JOIN provinces p ON r.province_id = p.id
+        WHERE {}
+        AND v.deleted_at IS NULL AND d.deleted_at IS NULL
+        AND r.deleted_at IS NULL AND p.deleted_at IS NULL
+    ",
+        filter
+    ))?;
+
+    let full_data = stmt
+        .query_row([param], |row| {
+            Ok(FullAdminData {
+                province_code: row.get(0)?,
+                province_name: row.get(1)?,
+                city_code: row.get(2)?,
+                city_name: row.get(3)?,
+                region_code: row.get(4)?,
+                region_name: row.get(5)?,
+                village_code: row.get(6)?,
+                village_name: row.get(7)?,
+            })
+        })
+        .optional()?;
+
+    Ok(full_data)
+}
+
+// SELECT list, FROM clause and liveness filter joining a row of `level` with
+// its ancestors; read with `hierarchy_row`
+fn hierarchy_query(level: Level) -> (&'static str, &'static str, &'static str) {
+    match level {
+        Level::Province => (
+            "p.id, p.code, p.name, NULL, NULL, NULL, NULL, NULL, NULL",
+            "provinces p",
+            "p.deleted_at IS NULL",
+        ),
+        Level::Regency => (
+            "r.id, p.code, p.name, r.code, r.name, NULL, NULL, NULL, NULL",
+            "regencies r
+            JOIN provinces p ON r.province_id = p.id",
+            "r.deleted_at IS NULL AND p.deleted_at IS NULL",
+        ),
+        Level::District => (
+            "d.id, p.code, p.name, r.code, r.name, d.code, d.name, NULL, NULL",
+            "districts d
+            JOIN regencies r ON d.regency_id = r.id
+            JOIN provinces p ON r.province_id = p.id",
+            "d.deleted_at IS NULL AND r.deleted_at IS NULL AND p.deleted_at IS NULL",
+        ),
+        Level::Village => (
+            "v.id, p.code, p.name, r.code, r.name, d.code, d.name, v.code, v.name",
+            "villages v
+            JOIN districts d ON v.district_id = d.id
+            JOIN regencies r ON d.regency_id = r.id
+            JOIN provinces p ON r.province_id = p.id",
+            "v.deleted_at IS NULL AND d.deleted_at IS NULL
+            AND r.deleted_at IS NULL AND p.deleted_at IS NULL",
+        ),
+    }
+}
+
+fn hierarchy_row(level: Level, row: &rusqlite::Row) -> Result<(i64, AdminHierarchy)> {
+    Ok((
+        row.get(0)?,
+        AdminHierarchy {
+            level,
+            province_code: row.get(1)?,
+            province_name: row.get(2)?,
+            city_code: row.get(3)?,
+            city_name: row.get(4)?,
+            region_code: row.get(5)?,
+            region_name: row.get(6)?,
+            village_code: row.get(7)?,
+            village_name: row.get(8)?,
+        },
+    ))
+}
+
+// Resolve many rows of one level to their hierarchy with one joined
+// `IN (...)` query per chunk, instead of one get_full_admin_data per row.
+// Returns the row id of the resolved entity next to its hierarchy.
+fn resolve_hierarchies<T: ToSql>(
+    conn: &Connection,
+    level: Level,
+    column: &str,
+    values: &[T],
+) -> Result<Vec<(i64, AdminHierarchy)>, DatabaseError> {
+    let (select, from, live) = hierarchy_query(level);
+    let alias = &level.table()[..1];
+    let key = match column {
+        "name" => format!("UPPER({}.name)", alias),
+        _ => format!("{}.{}", alias, column),
+    };
+
+    let mut resolved = Vec::new();
+    for chunk in values.chunks(BATCH_CHUNK_SIZE) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {} IN ({}) AND {}",
+            select, from, key, placeholders, live
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(chunk.iter()), |row| {
+            hierarchy_row(level, row)
+        })?;
+        for row in rows {
+            resolved.push(row?);
         }
     }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn opens_read_only_without_migrating() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("published.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.execute(
+            "INSERT INTO provinces (code, name) VALUES ('32', 'JAWA BARAT')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+        let published = std::fs::read(&path).unwrap();
+
+        // Behind the schema: read through a migrated copy
+        let db = Database::open_read_only(path.to_str().unwrap()).unwrap();
+        assert_eq!(db.get_all_provinces().unwrap()[0].version, Some(1));
+        drop(db);
+        assert_eq!(std::fs::read(&path).unwrap(), published);
+
+        // Up to date: the file itself, which refuses writes
+        drop(Database::new(path.to_str().unwrap()).unwrap());
+        let db = Database::open_read_only(path.to_str().unwrap()).unwrap();
+        assert_eq!(db.get_all_provinces().unwrap().len(), 1);
+        assert!(db.upsert_by_code(Level::Province, "51", "BALI").is_err());
+    }
+
+    // One district with enough villages to need three lookup chunks
+    fn batch_database() -> (Database, Vec<String>) {
+        let villages: Vec<String> = (0..BATCH_CHUNK_SIZE * 2 + 1)
+            .map(|i| format!("32.04.08.{}", 1001 + i))
+            .collect();
+        let mut rows = vec![
+            ("32".to_string(), "JAWA BARAT"),
+            ("32.04".to_string(), "KAB. BANDUNG"),
+            ("32.04.08".to_string(), "BOJONGSOANG"),
+        ];
+        rows.extend(villages.iter().map(|code| (code.clone(), "DESA")));
+        let rows: Vec<BulkUpsertLine> = rows
+            .into_iter()
+            .map(|(code, name)| BulkUpsertLine {
+                code,
+                name: name.to_string(),
+            })
+            .collect();
+
+        let db = Database::new(":memory:").unwrap();
+        assert!(db.upsert_many(&rows).unwrap().iter().all(|row| row.is_ok()));
+        (db, villages)
+    }
+
+    #[test]
+    fn resolves_codes_across_chunks() {
+        let (db, villages) = batch_database();
+        let mut codes = villages.clone();
+        // Unknown codes on both sides of a chunk boundary
+        codes.insert(BATCH_CHUNK_SIZE - 1, "32.04.08.9998".to_string());
+        codes.insert(BATCH_CHUNK_SIZE + 1, "32.04.08.9999".to_string());
+
+        let resolved = db.resolve_codes(Level::Village, &codes).unwrap();
+        let mut found: Vec<String> = resolved.iter().map(|h| h.code().to_string()).collect();
+        found.sort();
+        assert_eq!(found, villages);
+
+        let last = resolved
+            .iter()
+            .find(|h| h.code() == villages[villages.len() - 1]);
+        let last = last.unwrap();
+        assert_eq!(last.province_name, "JAWA BARAT");
+        assert_eq!(last.city_name.as_deref(), Some("KAB. BANDUNG"));
+        assert_eq!(last.region_name.as_deref(), Some("BOJONGSOANG"));
+    }
+
+    #[test]
+    fn resolves_ids_across_chunks() {
+        let (db, villages) = batch_database();
+        let ids: Vec<i64> = (1..=villages.len() as i64 + 2).collect();
+
+        let resolved = db.resolve_ids(Level::Village, &ids).unwrap();
+        let mut found: Vec<i64> = resolved.iter().map(|(id, _)| *id).collect();
+        found.sort();
+        assert_eq!(found, (1..=villages.len() as i64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn resolves_codes_and_names_alike() {
+        let (db, _) = batch_database();
+        let codes = ["32.04".to_string(), "32.99".to_string()];
+        let names = ["KAB. BANDUNG".to_string(), "KAB. GARUT".to_string()];
+
+        let by_code = db.resolve_codes(Level::Regency, &codes).unwrap();
+        let by_name = db.resolve_names(Level::Regency, &names).unwrap();
+        assert_eq!(by_code.len(), 1);
+        assert_eq!(by_name.len(), 1);
+        assert_eq!(by_code[0].code(), "32.04");
+        assert_eq!(by_name[0].code(), "32.04");
+
+        // Stored names are uppercased for the match; the input must be too
+        let district = db
+            .resolve_names(Level::District, &["BOJONGSOANG".to_string()])
+            .unwrap();
+        assert_eq!(district[0].code(), "32.04.08");
+        assert!(db
+            .resolve_names(Level::District, &["Bojongsoang".to_string()])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn reports_quality_issues() {
+        let db = Database::new(":memory:").unwrap();
+        for (level, code, name) in [
+            (Level::Province, "32", "JAWA BARAT"),
+            (Level::Regency, "32.04", "KAB. BANDUNG"),
+            (Level::District, "32.04.08", "BOJONGSOANG"),
+            (Level::Village, "32.04.08.2001", "LENGKONG"),
+        ] {
+            db.upsert_by_code(level, code, name).unwrap();
+        }
+        let report = db.quality_report().unwrap();
+        assert!(report.ok);
+        assert!(report.issues.is_empty());
+
+        // States validation refuses, as left behind by older imports
+        db.conn
+            .lock()
+            .unwrap()
+            .execute_batch(
+                "PRAGMA foreign_keys = OFF;
+                INSERT INTO villages (code, name, district_id) VALUES
+                ('32.04.08.2002', 'Bojongsoang', 1),
+                ('32.04.08.2002', 'BOJONGSOANG ', 1),
+                ('32.04.08.20O3', 'Tegalluar', 1),
+                ('32.04.09.1001', 'Cangkuang', 99);
+                PRAGMA foreign_keys = ON;",
+            )
+            .unwrap();
+        let report = db.quality_report().unwrap();
+        let found = |check: &str| -> Vec<(Severity, Option<String>)> {
+            report
+                .issues
+                .iter()
+                .filter(|issue| issue.check == check)
+                .map(|issue| (issue.severity, issue.code.clone()))
+                .collect()
+        };
+
+        assert_eq!(
+            found("duplicate_code"),
+            vec![(Severity::Error, Some("32.04.08.2002".to_string()))]
+        );
+        assert_eq!(
+            found("malformed_code"),
+            vec![(Severity::Error, Some("32.04.08.20O3".to_string()))]
+        );
+        assert_eq!(
+            found("orphan"),
+            vec![(Severity::Error, Some("32.04.09.1001".to_string()))]
+        );
+        assert_eq!(
+            found("duplicate_sibling_name"),
+            vec![(Severity::Warning, None)]
+        );
+        // The orphan is also a foreign key violation
+        assert_eq!(report.foreign_key_check.len(), 1);
+        assert_eq!(report.foreign_key_check[0].table, "villages");
+        assert!(!report.ok);
+        assert_eq!(report.errors, 4);
+        assert_eq!(report.warnings, 1);
+    }
+
+    #[test]
+    fn upserts_create_then_update() {
+        let db = Database::new(":memory:").unwrap();
+        let ops = |since: i64| -> Vec<(ChangeOp, String)> {
+            db.changes_since(since, 100)
+                .unwrap()
+                .changes
+                .into_iter()
+                .map(|change| (change.op, change.code))
+                .collect()
+        };
+
+        let created = db
+            .upsert_by_code(Level::Province, "32", "JAWA BARAT")
+            .unwrap();
+        assert_eq!(created.status, UpsertStatus::Created);
+        assert_eq!(created.version, 1);
+        let head = db.change_head().unwrap();
+        assert_eq!(ops(0), vec![(ChangeOp::Insert, "32".to_string())]);
+
+        // Same name: no write, no change, same version
+        let unchanged = db
+            .upsert_by_code(Level::Province, "32", "JAWA BARAT")
+            .unwrap();
+        assert_eq!(unchanged.status, UpsertStatus::Unchanged);
+        assert_eq!((unchanged.id, unchanged.version), (created.id, 1));
+        assert_eq!(db.change_head().unwrap(), head);
+
+        let updated = db.upsert_by_code(Level::Province, "32", "JABAR").unwrap();
+        assert_eq!(updated.status, UpsertStatus::Updated);
+        assert_eq!((updated.id, updated.version), (created.id, 2));
+        assert_eq!(ops(head), vec![(ChangeOp::Update, "32".to_string())]);
+        let province = db.get_one_province(created.id).unwrap().unwrap();
+        assert_eq!(
+            (province.name.as_str(), province.version),
+            ("JABAR", Some(2))
+        );
+    }
+
+    #[test]
+    fn upserts_parents_before_children() {
+        let db = Database::new(":memory:").unwrap();
+        let rows: Vec<BulkUpsertLine> = [
+            ("32.04.08.2002", "BOJONGSOANG"),
+            ("32.04.08", "BOJONGSOANG"),
+            ("32.04", "KAB. BANDUNG"),
+            ("32", "JAWA BARAT"),
+            ("32.04", "KABUPATEN BANDUNG"),
+            ("33.01", "KAB. CILACAP"),
+        ]
+        .into_iter()
+        .map(|(code, name)| BulkUpsertLine {
+            code: code.to_string(),
+            name: name.to_string(),
+        })
+        .collect();
+
+        let results = db.upsert_many(&rows).unwrap();
+        let statuses: Vec<_> = results
+            .iter()
+            .map(|result| result.as_ref().map(|row| row.status))
+            .collect();
+        assert_eq!(
+            statuses[..5],
+            [
+                Ok(UpsertStatus::Created),
+                Ok(UpsertStatus::Created),
+                Ok(UpsertStatus::Created),
+                Ok(UpsertStatus::Created),
+                Ok(UpsertStatus::Updated),
+            ]
+        );
+        // The parent is found by code, not by an id in the input
+        let village = results[0].as_ref().unwrap();
+        let district = results[1].as_ref().unwrap();
+        assert_eq!(
+            db.get_one_village(village.id).unwrap().unwrap().district_id,
+            Some(district.id)
+        );
+        assert_eq!(
+            results[5].as_ref().unwrap_err()[0].message,
+            "no province with code 33 exists"
+        );
+    }
 }
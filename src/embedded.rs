@@ -0,0 +1,47 @@
+// The dataset compressed into the binary by build.rs, for images without a
+// database volume. Every call decompresses a fresh in-memory copy.
+use flate2::read::GzDecoder;
+use std::io::Read;
+
+use crate::db::{Database, DatabaseError};
+
+static IMAGE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/wilayah.db.gz"));
+
+// Size of the compressed snapshot in bytes
+pub fn compressed_len() -> usize {
+    IMAGE.len()
+}
+
+pub fn database() -> Result<Database, DatabaseError> {
+    let mut image = Vec::new();
+    GzDecoder::new(IMAGE)
+        .read_to_end(&mut image)
+        .expect("build.rs writes valid gzip");
+    Database::from_image(&image)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Database;
+    use crate::models::Level;
+    use tempfile::TempDir;
+
+    #[test]
+    fn copies_the_image_into_memory() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("wilayah.db");
+        let file = Database::new(path.to_str().unwrap()).unwrap();
+        file.upsert_by_code(Level::Province, "32", "JAWA BARAT")
+            .unwrap();
+        drop(file);
+
+        let image = std::fs::read(&path).unwrap();
+        let db = Database::from_image(&image).unwrap();
+        assert_eq!(db.get_all_provinces().unwrap()[0].name, "JAWA BARAT");
+        assert_eq!(db.revision().seq, 1);
+
+        db.upsert_by_code(Level::Province, "51", "BALI").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), image);
+        assert!(Database::from_image(b"not a database").is_err());
+    }
+}
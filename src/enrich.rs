@@ -0,0 +1,364 @@
+use csv::{Reader, ReaderBuilder, StringRecord, Writer, WriterBuilder};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use thiserror::Error;
+
+use crate::db::{Database, DatabaseError};
+use crate::models::{AdminHierarchy, Level};
+
+// Records resolved together; keeps each lookup a handful of IN (...) queries
+const CHUNK_SIZE: usize = 1000;
+
+// Columns appended to every row of the uploaded CSV
+pub const ENRICH_COLUMNS: [&str; 9] = [
+    "province_code",
+    "province_name",
+    "regency_code",
+    "regency_name",
+    "district_code",
+    "district_name",
+    "village_code",
+    "village_name",
+    "match_status",
+];
+
+#[derive(Error, Debug)]
+pub enum EnrichError {
+    #[error("Invalid CSV: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("Column '{0}' not found in CSV header")]
+    MissingColumn(String),
+    #[error("{0}")]
+    Database(#[from] DatabaseError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MatchStatus {
+    Code,
+    Name,
+    Ambiguous,
+    NotFound,
+    Empty,
+}
+
+impl MatchStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MatchStatus::Code => "matched_code",
+            MatchStatus::Name => "matched_name",
+            MatchStatus::Ambiguous => "ambiguous",
+            MatchStatus::NotFound => "not_found",
+            MatchStatus::Empty => "empty",
+        }
+    }
+}
+
+// A CSV whose header has been read and holds the key column. The records
+// are then read, resolved and written a chunk at a time, so only one chunk
+// is held in memory whatever the size of the input.
+pub struct Enrichment<R> {
+    reader: Reader<R>,
+    headers: StringRecord,
+    key_index: usize,
+    delimiter: u8,
+}
+
+impl<R: Read> Enrichment<R> {
+    // Read the header and find `column` in it
+    pub fn new(input: R, column: &str, delimiter: u8) -> Result<Enrichment<R>, EnrichError> {
+        let mut reader = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_reader(input);
+        let headers = reader.headers()?.clone();
+        let key_index = headers
+            .iter()
+            .position(|header| header.trim() == column)
+            .ok_or_else(|| EnrichError::MissingColumn(column.to_string()))?;
+        Ok(Enrichment {
+            reader,
+            headers,
+            key_index,
+            delimiter,
+        })
+    }
+
+    // Append the region hierarchy of the key column to every record.
+    // Values shaped like a Kemendagri code are looked up by code; anything
+    // else is matched case-insensitively against village names, then
+    // district names. Output is flushed after every chunk.
+    pub fn write_to<W: Write>(mut self, db: &Database, output: W) -> Result<(), EnrichError> {
+        let mut writer = WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .flexible(true)
+            .from_writer(output);
+
+        let mut output_headers = self.headers.clone();
+        output_headers.extend(ENRICH_COLUMNS);
+        writer.write_record(&output_headers)?;
+
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        for record in self.reader.records() {
+            chunk.push(record?);
+            if chunk.len() == CHUNK_SIZE {
+                write_chunk(db, &mut writer, &chunk, self.key_index)?;
+                writer.flush().map_err(csv::Error::from)?;
+                chunk.clear();
+            }
+        }
+        write_chunk(db, &mut writer, &chunk, self.key_index)?;
+        writer.flush().map_err(csv::Error::from)?;
+        Ok(())
+    }
+}
+
+// Enrich a CSV held in memory
+pub fn enrich_csv(
+    db: &Database,
+    input: &[u8],
+    column: &str,
+    delimiter: u8,
+) -> Result<Vec<u8>, EnrichError> {
+    let mut output = Vec::new();
+    Enrichment::new(input, column, delimiter)?.write_to(db, &mut output)?;
+    Ok(output)
+}
+
+fn write_chunk<W: Write>(
+    db: &Database,
+    writer: &mut Writer<W>,
+    records: &[StringRecord],
+    key_index: usize,
+) -> Result<(), EnrichError> {
+    let keys: Vec<&str> = records
+        .iter()
+        .map(|record| record.get(key_index).unwrap_or_default().trim())
+        .collect();
+    let matches = resolve_keys(db, &keys)?;
+
+    for (record, key) in records.iter().zip(keys) {
+        let (hierarchy, status) = match matches.get(key) {
+            Some((hierarchy, status)) => (hierarchy.as_ref(), *status),
+            None => (None, MatchStatus::Empty),
+        };
+
+        let mut output = record.clone();
+        let field = |value: &Option<String>| value.clone().unwrap_or_default();
+        match hierarchy {
+            Some(h) => output.extend([
+                h.province_code.clone(),
+                h.province_name.clone(),
+                field(&h.city_code),
+                field(&h.city_name),
+                field(&h.region_code),
+                field(&h.region_name),
+                field(&h.village_code),
+                field(&h.village_name),
+            ]),
+            None => output.extend(std::iter::repeat_n(String::new(), 8)),
+        }
+        output.push_field(status.as_str());
+        writer.write_record(&output)?;
+    }
+    Ok(())
+}
+
+type KeyMatch = (Option<AdminHierarchy>, MatchStatus);
+
+fn resolve_keys(db: &Database, keys: &[&str]) -> Result<HashMap<String, KeyMatch>, EnrichError> {
+    let mut matches = HashMap::new();
+    let mut codes: HashMap<Level, Vec<String>> = HashMap::new();
+    let mut names = Vec::new();
+
+    for key in keys.iter().filter(|key| !key.is_empty()) {
+        if matches.contains_key(*key) {
+            continue;
+        }
+        matches.insert(key.to_string(), (None, MatchStatus::NotFound));
+        match Level::from_code(key) {
+            Some(level) => codes.entry(level).or_default().push(key.to_string()),
+            None => names.push(normalize_name(key)),
+        }
+    }
+
+    for (level, codes) in codes {
+        for hierarchy in db.resolve_codes(level, &codes)? {
+            matches.insert(
+                hierarchy.code().to_string(),
+                (Some(hierarchy), MatchStatus::Code),
+            );
+        }
+    }
+
+    if !names.is_empty() {
+        let by_name = match_names(db, &names)?;
+        for key in keys.iter().filter(|key| Level::from_code(key).is_none()) {
+            if let Some(found) = by_name.get(&normalize_name(key)) {
+                matches.insert(key.to_string(), found.clone());
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+// Villages are tried first; names without a village match fall back to
+// districts. A name shared by several regions is reported as ambiguous.
+fn match_names(db: &Database, names: &[String]) -> Result<HashMap<String, KeyMatch>, EnrichError> {
+    let mut matches = HashMap::new();
+    let mut remaining = names.to_vec();
+
+    for level in [Level::Village, Level::District] {
+        if remaining.is_empty() {
+            break;
+        }
+
+        let mut grouped: HashMap<String, Vec<AdminHierarchy>> = HashMap::new();
+        for hierarchy in db.resolve_names(level, &remaining)? {
+            grouped
+                .entry(normalize_name(hierarchy.name()))
+                .or_default()
+                .push(hierarchy);
+        }
+
+        remaining.retain(|name| !grouped.contains_key(name));
+        for (name, mut candidates) in grouped {
+            let found = if candidates.len() == 1 {
+                (candidates.pop(), MatchStatus::Name)
+            } else {
+                (None, MatchStatus::Ambiguous)
+            };
+            matches.insert(name, found);
+        }
+    }
+
+    Ok(matches)
+}
+
+fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Database {
+        let db = Database::new(":memory:").unwrap();
+        for (level, code, name) in [
+            (Level::Province, "32", "JAWA BARAT"),
+            (Level::Regency, "32.04", "KAB. BANDUNG"),
+            (Level::District, "32.04.08", "Bojongsoang"),
+            (Level::District, "32.04.11", "Dayeuhkolot"),
+            (Level::Village, "32.04.08.2001", "Lengkong"),
+            (Level::Village, "32.04.08.2002", "Sukamaju"),
+            (Level::Village, "32.04.11.2001", "Sukamaju"),
+        ] {
+            db.upsert_by_code(level, code, name).unwrap();
+        }
+        db
+    }
+
+    fn enrich(db: &Database, input: &str, column: &str, delimiter: u8) -> Vec<Vec<String>> {
+        let output = enrich_csv(db, input.as_bytes(), column, delimiter).unwrap();
+        ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(output.as_slice())
+            .records()
+            .map(|record| record.unwrap().iter().map(String::from).collect())
+            .collect()
+    }
+
+    #[test]
+    fn reports_how_each_key_matched() {
+        let db = database();
+        let rows = enrich(
+            &db,
+            "toko;kode\nA;32.04.08.2001\nB; lengkong \nC;Sukamaju\nD;Dayeuhkolot\nE;32.99\nF;\nG\n",
+            "kode",
+            b';',
+        );
+
+        assert_eq!(rows[0][..2], ["toko", "kode"]);
+        assert_eq!(rows[0][2..], ENRICH_COLUMNS);
+        let status: Vec<_> = rows[1..]
+            .iter()
+            .map(|row| row.last().unwrap().as_str())
+            .collect();
+        assert_eq!(
+            status,
+            [
+                "matched_code",
+                "matched_name",
+                "ambiguous",
+                "matched_name",
+                "not_found",
+                "empty",
+                "empty"
+            ]
+        );
+        assert_eq!(rows[1][8], "32.04.08.2001");
+        assert_eq!(rows[2][9], "Lengkong");
+        // A district match leaves the village columns empty
+        assert_eq!(rows[4][6..10], ["32.04.11", "Dayeuhkolot", "", ""]);
+        // Short records keep their own fields and still get every column
+        assert_eq!(rows[7].len(), 1 + ENRICH_COLUMNS.len());
+        for row in [&rows[3], &rows[5], &rows[6]] {
+            assert!(row[2..10].iter().all(String::is_empty), "{:?}", row);
+        }
+    }
+
+    #[test]
+    fn resolves_across_chunks() {
+        let db = database();
+        let mut input = String::from("kode\n");
+        for i in 0..2 * CHUNK_SIZE + 1 {
+            input.push_str(if i % 2 == 0 {
+                "32.04.08.2002\n"
+            } else {
+                "Lengkong\n"
+            });
+        }
+
+        let rows = enrich(&db, &input, "kode", b',');
+        assert_eq!(rows.len(), 2 * CHUNK_SIZE + 2);
+        for (i, row) in rows[1..].iter().enumerate() {
+            let (code, status) = if i % 2 == 0 {
+                ("32.04.08.2002", "matched_code")
+            } else {
+                ("32.04.08.2001", "matched_name")
+            };
+            assert_eq!(
+                (row[7].as_str(), row[9].as_str()),
+                (code, status),
+                "row {}",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn checks_the_header_before_any_record() {
+        // Never read past the header
+        struct HeaderOnly(&'static [u8]);
+        impl Read for HeaderOnly {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.0.is_empty() {
+                    panic!("read past the header");
+                }
+                let len = self.0.read(buf)?;
+                Ok(len)
+            }
+        }
+
+        let missing = Enrichment::new(HeaderOnly(b"nama,kode\n"), "desa", b',');
+        assert!(matches!(missing, Err(EnrichError::MissingColumn(column)) if column == "desa"));
+        let invalid = Enrichment::new(&b"nama,\xff\n"[..], "nama", b',');
+        assert!(matches!(invalid, Err(EnrichError::Csv(_))));
+    }
+}
@@ -0,0 +1,152 @@
+use actix_web::web;
+use futures_util::{stream, Stream, StreamExt};
+use log::warn;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::db::{Database, DatabaseError};
+use crate::models::{Change, ChangeFilter};
+
+// Comment line sent when nothing happened for a while, keeps proxies from
+// closing idle connections
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+// Changes read from the log per query while catching up
+const BATCH_SIZE: usize = 500;
+// Reconnect delay suggested to EventSource clients, in milliseconds
+const RETRY_MS: u64 = 3000;
+
+struct State {
+    db: web::Data<Database>,
+    filter: ChangeFilter,
+    changes: broadcast::Receiver<()>,
+    cursor: i64,
+    catch_up: bool,
+}
+
+// Stream of SSE frames for changes after `since`, or after the current head
+// when None. The change log is the source of truth; the broadcast only says
+// when to read it again, so nothing is lost while a client is slow.
+pub fn stream(
+    db: web::Data<Database>,
+    filter: ChangeFilter,
+    since: Option<i64>,
+) -> Result<impl Stream<Item = Result<web::Bytes, actix_web::Error>>, DatabaseError> {
+    // Subscribe before reading the head so no change falls in between
+    let changes = db.subscribe_changes();
+    let cursor = match since {
+        Some(since) => since,
+        None => db.change_head()?,
+    };
+    let state = State {
+        db,
+        filter,
+        changes,
+        cursor,
+        catch_up: since.is_some(),
+    };
+
+    let retry = web::Bytes::from(format!("retry: {}\n\n", RETRY_MS));
+    let frames = stream::unfold(state, |mut state| async move {
+        loop {
+            if state.catch_up {
+                let feed = match state.db.changes_since(state.cursor, BATCH_SIZE) {
+                    Ok(feed) => feed,
+                    Err(e) => {
+                        warn!("Failed to read changes for /events: {}", e);
+                        return None;
+                    }
+                };
+                state.cursor = feed.next.parse().unwrap_or(state.cursor);
+                state.catch_up = feed.has_more;
+
+                let frames: String = feed
+                    .changes
+                    .iter()
+                    .filter(|change| state.filter.matches(change))
+                    .map(frame)
+                    .collect();
+                if !frames.is_empty() {
+                    return Some((Ok(web::Bytes::from(frames)), state));
+                }
+                if state.catch_up {
+                    continue;
+                }
+            }
+
+            match actix_rt::time::timeout(KEEPALIVE_INTERVAL, state.changes.recv()).await {
+                // Lagging only means several commits happened, the log has them all
+                Ok(Ok(())) | Ok(Err(RecvError::Lagged(_))) => state.catch_up = true,
+                Ok(Err(RecvError::Closed)) => return None,
+                Err(_) => return Some((Ok(web::Bytes::from_static(b": keepalive\n\n")), state)),
+            }
+        }
+    });
+
+    Ok(stream::once(async move { Ok(retry) }).chain(frames))
+}
+
+// One SSE event; the seq doubles as the id sent back in Last-Event-ID
+fn frame(change: &Change) -> String {
+    format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        change.seq,
+        change.event(),
+        serde_json::to_string(change).unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Level, Province};
+
+    fn create_province(db: &Database, code: &str, name: &str) {
+        db.create_province(&Province {
+            id: None,
+            version: None,
+            code: code.to_string(),
+            name: name.to_string(),
+        })
+        .unwrap();
+    }
+
+    async fn next_frame<S>(events: &mut S) -> String
+    where
+        S: Stream<Item = Result<web::Bytes, actix_web::Error>> + Unpin,
+    {
+        let bytes = events.next().await.unwrap().unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn pushes_matching_changes_as_they_happen() {
+        let db = web::Data::new(Database::new(":memory:").unwrap());
+        create_province(&db, "31", "DKI JAKARTA");
+        let filter = ChangeFilter {
+            levels: vec![Level::Province],
+            code_prefix: Some("32".to_string()),
+        };
+        let mut events = Box::pin(stream(db.clone(), filter, None).unwrap());
+        assert_eq!(next_frame(&mut events).await, "retry: 3000\n\n");
+
+        // 51 is filtered out, 32 arrives without polling
+        create_province(&db, "51", "BALI");
+        create_province(&db, "32", "JAWA BARAT");
+        let frame = next_frame(&mut events).await;
+        assert!(frame.starts_with("id: 3\nevent: province.created\ndata: "));
+        assert!(frame.contains("\"JAWA BARAT\""));
+    }
+
+    #[actix_rt::test]
+    async fn resumes_after_last_event_id() {
+        let db = web::Data::new(Database::new(":memory:").unwrap());
+        create_province(&db, "31", "DKI JAKARTA");
+        create_province(&db, "32", "JAWA BARAT");
+
+        let mut events = Box::pin(stream(db.clone(), ChangeFilter::default(), Some(1)).unwrap());
+        next_frame(&mut events).await;
+        let frame = next_frame(&mut events).await;
+        assert!(frame.starts_with("id: 2\n"));
+        assert!(!frame.contains("id: 1\n"));
+    }
+}
@@ -0,0 +1,474 @@
+use actix_web::web;
+use async_graphql::connection::{self, Connection, Edge};
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Error, Object, OutputType, Request, Response,
+    Result, Schema, SimpleObject,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::db::{Database, DatabaseError};
+use crate::models::{Level, RegionRow};
+
+pub type WilayahSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+// Deepest nesting of selections. Walking down from the root, provinces >
+// edges > node > regencies > ... > villages > edges > node > name is 13.
+const MAX_DEPTH: usize = 15;
+// Each field costs 1, connections multiply their selection by the page size
+const MAX_COMPLEXITY: usize = 20_000;
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+pub fn schema() -> WilayahSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+// Run a request against `db`. The loader is per request so its cache never
+// outlives a revision of the data.
+pub async fn execute(
+    schema: &WilayahSchema,
+    db: web::Data<Database>,
+    request: Request,
+) -> Response {
+    let loader = DataLoader::new(RegionLoader { db: db.clone() }, actix_rt::spawn);
+    schema.execute(request.data(db).data(loader)).await
+}
+
+// Batches the parent and children lookups of one query into a query per level
+pub struct RegionLoader {
+    db: web::Data<Database>,
+}
+
+// A row of `level` by id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct RowKey(Level, i64);
+
+// The children of a row of `level`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ChildrenKey(Level, i64);
+
+impl Loader<RowKey> for RegionLoader {
+    type Value = RegionRow;
+    type Error = Arc<DatabaseError>;
+
+    async fn load(&self, keys: &[RowKey]) -> Result<HashMap<RowKey, RegionRow>, Self::Error> {
+        let mut ids: HashMap<Level, Vec<i64>> = HashMap::new();
+        for RowKey(level, id) in keys {
+            ids.entry(*level).or_default().push(*id);
+        }
+
+        let mut rows = HashMap::new();
+        for (level, ids) in ids {
+            for row in self.db.regions_by_id(level, &ids)? {
+                rows.insert(RowKey(level, row.id), row);
+            }
+        }
+        Ok(rows)
+    }
+}
+
+impl Loader<ChildrenKey> for RegionLoader {
+    type Value = Vec<RegionRow>;
+    type Error = Arc<DatabaseError>;
+
+    async fn load(
+        &self,
+        keys: &[ChildrenKey],
+    ) -> Result<HashMap<ChildrenKey, Vec<RegionRow>>, Self::Error> {
+        let mut parent_ids: HashMap<Level, Vec<i64>> = HashMap::new();
+        for ChildrenKey(level, id) in keys {
+            parent_ids.entry(*level).or_default().push(*id);
+        }
+
+        let mut children: HashMap<ChildrenKey, Vec<RegionRow>> = HashMap::new();
+        for (level, ids) in parent_ids {
+            let Some(child) = level.child() else {
+                continue;
+            };
+            for row in self.db.regions_by_parent(child, &ids)? {
+                if let Some(parent_id) = row.parent_id {
+                    children
+                        .entry(ChildrenKey(level, parent_id))
+                        .or_default()
+                        .push(row);
+                }
+            }
+        }
+        Ok(children)
+    }
+}
+
+async fn load_row(ctx: &Context<'_>, level: Level, id: Option<i64>) -> Result<Option<RegionRow>> {
+    let Some(id) = id else {
+        return Ok(None);
+    };
+    let loader = ctx.data_unchecked::<DataLoader<RegionLoader>>();
+    Ok(loader.load_one(RowKey(level, id)).await?)
+}
+
+async fn load_children(ctx: &Context<'_>, level: Level, id: i64) -> Result<Vec<RegionRow>> {
+    let loader = ctx.data_unchecked::<DataLoader<RegionLoader>>();
+    Ok(loader
+        .load_one(ChildrenKey(level, id))
+        .await?
+        .unwrap_or_default())
+}
+
+// Root lookups take either an id or a code
+async fn find(
+    ctx: &Context<'_>,
+    level: Level,
+    id: Option<i64>,
+    code: Option<String>,
+) -> Result<Option<RegionRow>> {
+    let id = match (id, code) {
+        (Some(id), None) => Some(id),
+        (None, Some(code)) => ctx
+            .data_unchecked::<web::Data<Database>>()
+            .find_id_by_code(level, &code)?,
+        _ => return Err(Error::new("Pass exactly one of id or code")),
+    };
+    load_row(ctx, level, id).await
+}
+
+// Page size a connection field is charged for in the complexity limit
+fn page_size(first: Option<i32>, last: Option<i32>) -> usize {
+    first
+        .or(last)
+        .map_or(DEFAULT_PAGE_SIZE, |n| n.max(0) as usize)
+}
+
+#[derive(SimpleObject)]
+pub struct TotalCount {
+    // Rows across all pages
+    total_count: usize,
+}
+
+type Page<T> = Connection<usize, T, TotalCount>;
+
+// Relay-style page over `rows`; the cursor is the position in the list
+async fn paginate<T: OutputType>(
+    rows: Vec<RegionRow>,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+    node: fn(RegionRow) -> T,
+) -> Result<Page<T>> {
+    connection::query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<usize>, before: Option<usize>, first, last| async move {
+            if first.or(last).is_some_and(|n| n > MAX_PAGE_SIZE) {
+                return Err(Error::new(format!(
+                    "first and last may be at most {}",
+                    MAX_PAGE_SIZE
+                )));
+            }
+            let total_count = rows.len();
+            let mut start = after.map_or(0, |after| after + 1).min(total_count);
+            let mut end = before.unwrap_or(total_count).clamp(start, total_count);
+            match (first, last) {
+                (Some(first), _) => end = end.min(start + first),
+                (None, Some(last)) => start = start.max(end.saturating_sub(last)),
+                (None, None) => end = end.min(start + DEFAULT_PAGE_SIZE),
+            }
+
+            let mut page = Connection::with_additional_fields(
+                start > 0,
+                end < total_count,
+                TotalCount { total_count },
+            );
+            page.edges.extend(
+                rows.into_iter()
+                    .enumerate()
+                    .skip(start)
+                    .take(end - start)
+                    .map(|(position, row)| Edge::new(position, node(row))),
+            );
+            Ok::<_, Error>(page)
+        },
+    )
+    .await
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    async fn province(
+        &self,
+        ctx: &Context<'_>,
+        id: Option<i64>,
+        code: Option<String>,
+    ) -> Result<Option<Province>> {
+        Ok(find(ctx, Level::Province, id, code).await?.map(Province))
+    }
+
+    async fn regency(
+        &self,
+        ctx: &Context<'_>,
+        id: Option<i64>,
+        code: Option<String>,
+    ) -> Result<Option<Regency>> {
+        Ok(find(ctx, Level::Regency, id, code).await?.map(Regency))
+    }
+
+    async fn district(
+        &self,
+        ctx: &Context<'_>,
+        id: Option<i64>,
+        code: Option<String>,
+    ) -> Result<Option<District>> {
+        Ok(find(ctx, Level::District, id, code).await?.map(District))
+    }
+
+    async fn village(
+        &self,
+        ctx: &Context<'_>,
+        id: Option<i64>,
+        code: Option<String>,
+    ) -> Result<Option<Village>> {
+        Ok(find(ctx, Level::Village, id, code).await?.map(Village))
+    }
+
+    #[graphql(complexity = "page_size(first, last) * child_complexity")]
+    async fn provinces(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Province>> {
+        let mut rows: Vec<RegionRow> = ctx
+            .data_unchecked::<web::Data<Database>>()
+            .get_all_provinces()?
+            .into_iter()
+            .map(|province| RegionRow {
+                id: province.id.unwrap_or_default(),
+                code: province.code,
+                name: province.name,
+                parent_id: None,
+                version: province.version.unwrap_or_default(),
+            })
+            .collect();
+        rows.sort_by(|a, b| a.code.cmp(&b.code));
+        paginate(rows, after, before, first, last, Province).await
+    }
+}
+
+pub struct Province(RegionRow);
+
+#[Object]
+impl Province {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn version(&self) -> i64 {
+        self.0.version
+    }
+
+    async fn code(&self) -> &str {
+        &self.0.code
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    #[graphql(complexity = "page_size(first, last) * child_complexity")]
+    async fn regencies(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Regency>> {
+        let rows = load_children(ctx, Level::Province, self.0.id).await?;
+        paginate(rows, after, before, first, last, Regency).await
+    }
+}
+
+pub struct Regency(RegionRow);
+
+#[Object]
+impl Regency {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn version(&self) -> i64 {
+        self.0.version
+    }
+
+    async fn code(&self) -> &str {
+        &self.0.code
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn province_id(&self) -> Option<i64> {
+        self.0.parent_id
+    }
+
+    async fn province(&self, ctx: &Context<'_>) -> Result<Option<Province>> {
+        Ok(load_row(ctx, Level::Province, self.0.parent_id)
+            .await?
+            .map(Province))
+    }
+
+    #[graphql(complexity = "page_size(first, last) * child_complexity")]
+    async fn districts(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<District>> {
+        let rows = load_children(ctx, Level::Regency, self.0.id).await?;
+        paginate(rows, after, before, first, last, District).await
+    }
+}
+
+pub struct District(RegionRow);
+
+#[Object]
+impl District {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn version(&self) -> i64 {
+        self.0.version
+    }
+
+    async fn code(&self) -> &str {
+        &self.0.code
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn regency_id(&self) -> Option<i64> {
+        self.0.parent_id
+    }
+
+    async fn regency(&self, ctx: &Context<'_>) -> Result<Option<Regency>> {
+        Ok(load_row(ctx, Level::Regency, self.0.parent_id)
+            .await?
+            .map(Regency))
+    }
+
+    #[graphql(complexity = "page_size(first, last) * child_complexity")]
+    async fn villages(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Village>> {
+        let rows = load_children(ctx, Level::District, self.0.id).await?;
+        paginate(rows, after, before, first, last, Village).await
+    }
+}
+
+pub struct Village(RegionRow);
+
+#[Object]
+impl Village {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn version(&self) -> i64 {
+        self.0.version
+    }
+
+    async fn code(&self) -> &str {
+        &self.0.code
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn district_id(&self) -> Option<i64> {
+        self.0.parent_id
+    }
+
+    async fn district(&self, ctx: &Context<'_>) -> Result<Option<District>> {
+        Ok(load_row(ctx, Level::District, self.0.parent_id)
+            .await?
+            .map(District))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Province as NewProvince, Regency as NewRegency};
+
+    #[actix_rt::test]
+    async fn resolves_parents_and_pages_children() {
+        let db = web::Data::new(Database::new(":memory:").unwrap());
+        let province_id = db
+            .create_province(&NewProvince {
+                id: None,
+                version: None,
+                code: "32".to_string(),
+                name: "JAWA BARAT".to_string(),
+            })
+            .unwrap();
+        for (code, name) in [("32.04", "KAB. BANDUNG"), ("32.73", "KOTA BANDUNG")] {
+            db.create_regency(&NewRegency {
+                id: None,
+                version: None,
+                code: code.to_string(),
+                name: name.to_string(),
+                province_id: Some(province_id),
+            })
+            .unwrap();
+        }
+
+        let response = execute(
+            &schema(),
+            db,
+            Request::new(
+                r#"{
+                    regency(code: "32.73") { name province { name } }
+                    province(code: "32") {
+                        regencies(first: 1, after: "0") {
+                            totalCount
+                            pageInfo { hasPreviousPage hasNextPage }
+                            edges { node { code } }
+                        }
+                    }
+                }"#,
+            ),
+        )
+        .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["regency"]["province"]["name"], "JAWA BARAT");
+        let regencies = &data["province"]["regencies"];
+        assert_eq!(regencies["totalCount"], 2);
+        assert_eq!(regencies["pageInfo"]["hasPreviousPage"], true);
+        assert_eq!(regencies["pageInfo"]["hasNextPage"], false);
+        assert_eq!(regencies["edges"][0]["node"]["code"], "32.73");
+    }
+}
@@ -1,22 +1,326 @@
-use actix_web::{web, HttpResponse, Responder};
-use serde_json::json;
+use actix_web::http::header::{self, Header};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use async_graphql::http::GraphiQLSource;
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use utoipa::IntoParams;
 
-use crate::db::Database;
-use crate::models::{District, Province, Regency, Village};
+use crate::backup;
+use crate::cache::{CachedResponse, ResponseCache};
+use crate::db::{Database, DatabaseError, Revision};
+use crate::events;
+use crate::graphql::{self, WilayahSchema};
+use crate::models::{
+    BackupOptions, BatchItem, BatchResolution, BatchResolveRequest, BatchResolveResponse,
+    BulkUpsertLine, BulkUpsertResponse, BulkUpsertResult, CacheStats, ChangeFeed, ChangeFilter,
+    CreateWebhook, CreatedId, DeleteOptions, DeletePlan, DeliveryStatus, District, ErrorResponse,
+    FieldError, FullAdminData, HasChildrenResponse, Level, Province, QualityReport, Regency,
+    UpsertRequest, UpsertResult, UpsertStatus, ValidationErrorResponse, Village, Webhook,
+    WebhookDelivery,
+};
+use crate::negotiate::{self, Representation};
+use crate::proto;
+use crate::repository::WilayahRepository;
+use crate::validation;
+use crate::webhooks::UrlPolicy;
 
-pub async fn get_all_provinces(db: web::Data<Database>) -> impl Responder {
-    match db.get_all_provinces() {
-        Ok(provinces) => HttpResponse::Ok().json(provinces),
+#[cfg(feature = "search")]
+mod search;
+#[cfg(feature = "search")]
+pub use search::*;
+
+fn validation_failed(fields: Vec<FieldError>) -> HttpResponse {
+    HttpResponse::UnprocessableEntity().json(ValidationErrorResponse {
+        error: "Validation failed".to_string(),
+        fields,
+    })
+}
+
+// ETag of a row version in the encoding the client negotiated
+fn etag(req: &HttpRequest, version: i64) -> header::EntityTag {
+    version_etag(version, negotiate::etag_suffix(req))
+}
+
+// ETag of a row version in the encoding with the given suffix
+fn version_etag(version: i64, suffix: &str) -> header::EntityTag {
+    header::EntityTag::new_strong(format!("{}{}", version, suffix))
+}
+
+// Versions listed in If-Match, None when the header is absent or `*`. The
+// encoding suffix is ignored, a version is the same in every encoding. Weak
+// or unparseable tags never match, so they leave an empty list.
+fn if_match(req: &HttpRequest) -> Option<Vec<i64>> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return None;
+    }
+    match header::IfMatch::parse(req) {
+        Ok(header::IfMatch::Any) => None,
+        Ok(header::IfMatch::Items(tags)) => Some(
+            tags.iter()
+                .filter(|tag| !tag.weak)
+                .filter_map(|tag| tag.tag().split('-').next()?.parse().ok())
+                .collect(),
+        ),
+        Err(_) => Some(Vec::new()),
+    }
+}
+
+// ETag of lists and joins, which change with any write to the dataset.
+// The prefix keeps it apart from row versions used with If-Match.
+fn revision_etag(revision: i64, suffix: &str) -> header::EntityTag {
+    header::EntityTag::new_strong(format!("r{}{}", revision, suffix))
+}
+
+// 200 with a cacheable read, or 304 when the client's copy is current. Single
+// rows are tagged with their version, everything else with the revision.
+// The body is already in the representation the client negotiated, and the
+// tags carry the suffix of the encoding it actually came out in.
+fn cached_response(
+    req: &HttpRequest,
+    cache: &ResponseCache,
+    revision: &Revision,
+    response: &CachedResponse,
+) -> HttpResponse {
+    let tag = match response.version {
+        Some(version) => version_etag(version, response.etag_suffix),
+        None => revision_etag(revision.seq, response.etag_suffix),
+    };
+    let last_modified = header::HttpDate::from(revision.modified);
+    // If-Modified-Since only counts without If-None-Match
+    let not_modified = if req.headers().contains_key(header::IF_NONE_MATCH) {
+        match header::IfNoneMatch::parse(req) {
+            Ok(header::IfNoneMatch::Any) => true,
+            Ok(header::IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.weak_eq(&tag)),
+            Err(_) => false,
+        }
+    } else {
+        matches!(header::IfModifiedSince::parse(req), Ok(since) if last_modified <= since.0)
+    };
+
+    let mut builder = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    builder
+        .insert_header(header::ETag(tag))
+        .insert_header(header::LastModified(last_modified))
+        .insert_header(header::CacheControl(vec![
+            header::CacheDirective::Public,
+            header::CacheDirective::MaxAge(cache.max_age().as_secs() as u32),
+        ]));
+    if not_modified {
+        return builder.finish();
+    }
+    builder
+        .content_type(response.content_type)
+        .body(response.body.clone())
+}
+
+fn precondition_failed(req: &HttpRequest, version: i64) -> HttpResponse {
+    HttpResponse::PreconditionFailed()
+        .insert_header(header::ETag(etag(req, version)))
+        .json(json!({
+            "error": format!("If-Match does not match the current version {}", version)
+        }))
+}
+
+// 204 with the new ETag after a PUT or PATCH
+fn update_response(
+    req: &HttpRequest,
+    result: Result<i64, DatabaseError>,
+    entity: &str,
+) -> HttpResponse {
+    match result {
+        Ok(version) => HttpResponse::NoContent()
+            .insert_header(header::ETag(etag(req, version)))
+            .finish(),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
+            "error": format!("{} not found", entity)
+        })),
+        Err(DatabaseError::PreconditionFailed(version)) => precondition_failed(req, version),
+        Err(DatabaseError::Validation(fields)) => validation_failed(fields),
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to update {}: {}", entity.to_lowercase(), e)
+        })),
+    }
+}
+
+// Apply a JSON Merge Patch (RFC 7386) to the current row and save it. Without
+// If-Match, the row must still be at the version the patch was applied to.
+fn patch_response<T: Serialize + DeserializeOwned>(
+    req: &HttpRequest,
+    entity: &str,
+    current: Result<Option<T>, DatabaseError>,
+    patch: Value,
+    update: impl FnOnce(&T, &[i64]) -> Result<i64, DatabaseError>,
+) -> HttpResponse {
+    let current = match current {
+        Ok(Some(current)) => current,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(json!({
+                "error": format!("{} not found", entity)
+            }))
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(json!({
+                "error": format!("Failed to fetch {}: {}", entity.to_lowercase(), e)
+            }))
+        }
+    };
+
+    let mut target = serde_json::to_value(&current).unwrap_or_default();
+    let version = target["version"].as_i64().unwrap_or_default();
+    merge_patch(&mut target, patch);
+    let patched: T = match serde_json::from_value(target) {
+        Ok(patched) => patched,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(json!({
+                "error": format!("Patched {} is invalid: {}", entity.to_lowercase(), e)
+            }))
+        }
+    };
+
+    let expected = if_match(req).unwrap_or_else(|| vec![version]);
+    update_response(req, update(&patched, &expected), entity)
+}
+
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(fields) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(object) = target {
+        for (key, value) in fields {
+            if value.is_null() {
+                object.remove(&key);
+            } else {
+                merge_patch(object.entry(key).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+// 201 when the row was inserted, 200 otherwise, both with the row's ETag
+fn upsert_response(
+    req: &HttpRequest,
+    result: Result<UpsertResult, DatabaseError>,
+    entity: &str,
+) -> HttpResponse {
+    match result {
+        Ok(result) => {
+            let mut response = match result.status {
+                UpsertStatus::Created => HttpResponse::Created(),
+                _ => HttpResponse::Ok(),
+            };
+            response
+                .insert_header(header::ETag(etag(req, result.version)))
+                .insert_header((
+                    header::LOCATION,
+                    format!("/{}/{}", result.level.table(), result.id),
+                ))
+                .json(result)
+        }
+        Err(DatabaseError::Validation(fields)) => validation_failed(fields),
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to upsert {}: {}", entity.to_lowercase(), e)
+        })),
+    }
+}
+
+// 204 once deleted, 200 with the plan for a dry run
+fn delete_response(
+    req: &HttpRequest,
+    result: Result<DeletePlan, DatabaseError>,
+    entity: &str,
+) -> HttpResponse {
+    match result {
+        Ok(plan) if plan.dry_run => HttpResponse::Ok().json(plan),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
+            "error": format!("{} not found", entity)
+        })),
+        Err(DatabaseError::PreconditionFailed(version)) => precondition_failed(req, version),
+        Err(DatabaseError::HasChildren(children)) => {
+            HttpResponse::Conflict().json(HasChildrenResponse {
+                error: format!("{} still has children, retry with cascade=true", entity),
+                children,
+            })
+        }
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to delete {}: {}", entity.to_lowercase(), e)
+        })),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/provinces",
+    tag = "provinces",
+    params(("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")),
+    responses(
+        (status = 200, description = "All provinces", body = Vec<Province>, headers(("ETag" = String, description = "Dataset revision"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_all_provinces(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+) -> impl Responder {
+    let revision = db.revision();
+    let key = "provinces".to_string();
+    let representation = Representation::new(&req, proto::Message::Provinces);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        Ok((None, db.get_all_provinces()?))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
         Err(e) => HttpResponse::InternalServerError().json(json!({
             "error": format!("Failed to fetch provinces: {}", e)
         })),
     }
 }
 
-pub async fn get_one_province(db: web::Data<Database>, path: web::Path<i64>) -> impl Responder {
-    match db.get_one_province(path.into_inner()) {
-        Ok(Some(province)) => HttpResponse::Ok().json(province),
-        Ok(None) => HttpResponse::NotFound().json(json!({
+#[utoipa::path(
+    get,
+    path = "/provinces/{id}",
+    tag = "provinces",
+    params(
+        ("id" = i64, Path, description = "Province ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "Province found", body = Province, headers(("ETag" = String, description = "Row version"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 404, description = "Province not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_one_province(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let revision = db.revision();
+    let key = format!("provinces/{}", id);
+    let representation = Representation::new(&req, proto::Message::Province);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        let province = db.get_one_province(id)?.ok_or(DatabaseError::NotFound)?;
+        Ok((province.version, province))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
             "error": "Province not found"
         })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
@@ -25,10 +329,38 @@ pub async fn get_one_province(db: web::Data<Database>, path: web::Path<i64>) ->
     }
 }
 
-pub async fn get_one_regency(db: web::Data<Database>, path: web::Path<i64>) -> impl Responder {
-    match db.get_one_regency(path.into_inner()) {
-        Ok(Some(regency)) => HttpResponse::Ok().json(regency),
-        Ok(None) => HttpResponse::NotFound().json(json!({
+#[utoipa::path(
+    get,
+    path = "/regencies/{id}",
+    tag = "regencies",
+    params(
+        ("id" = i64, Path, description = "Regency ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "Regency found", body = Regency, headers(("ETag" = String, description = "Row version"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 404, description = "Regency not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_one_regency(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let revision = db.revision();
+    let key = format!("regencies/{}", id);
+    let representation = Representation::new(&req, proto::Message::Regency);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        let regency = db.get_one_regency(id)?.ok_or(DatabaseError::NotFound)?;
+        Ok((regency.version, regency))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
             "error": "Regency not found"
         })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
@@ -37,10 +369,38 @@ pub async fn get_one_regency(db: web::Data<Database>, path: web::Path<i64>) -> i
     }
 }
 
-pub async fn get_one_district(db: web::Data<Database>, path: web::Path<i64>) -> impl Responder {
-    match db.get_one_district(path.into_inner()) {
-        Ok(Some(district)) => HttpResponse::Ok().json(district),
-        Ok(None) => HttpResponse::NotFound().json(json!({
+#[utoipa::path(
+    get,
+    path = "/districts/{id}",
+    tag = "districts",
+    params(
+        ("id" = i64, Path, description = "District ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "District found", body = District, headers(("ETag" = String, description = "Row version"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 404, description = "District not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_one_district(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let revision = db.revision();
+    let key = format!("districts/{}", id);
+    let representation = Representation::new(&req, proto::Message::District);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        let district = db.get_one_district(id)?.ok_or(DatabaseError::NotFound)?;
+        Ok((district.version, district))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
             "error": "District not found"
         })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
@@ -49,10 +409,38 @@ pub async fn get_one_district(db: web::Data<Database>, path: web::Path<i64>) ->
     }
 }
 
-pub async fn get_one_village(db: web::Data<Database>, path: web::Path<i64>) -> impl Responder {
-    match db.get_one_village(path.into_inner()) {
-        Ok(Some(village)) => HttpResponse::Ok().json(village),
-        Ok(None) => HttpResponse::NotFound().json(json!({
+#[utoipa::path(
+    get,
+    path = "/villages/{id}",
+    tag = "villages",
+    params(
+        ("id" = i64, Path, description = "Village ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "Village found", body = Village, headers(("ETag" = String, description = "Row version"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 404, description = "Village not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_one_village(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let revision = db.revision();
+    let key = format!("villages/{}", id);
+    let representation = Representation::new(&req, proto::Message::Village);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        let village = db.get_one_village(id)?.ok_or(DatabaseError::NotFound)?;
+        Ok((village.version, village))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
             "error": "Village not found"
         })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
@@ -61,172 +449,1590 @@ pub async fn get_one_village(db: web::Data<Database>, path: web::Path<i64>) -> i
     }
 }
 
-pub async fn create_province(db: web::Data<Database>, province: web::Json<Province>) -> impl Responder {
+#[utoipa::path(
+    post,
+    path = "/provinces",
+    tag = "provinces",
+    request_body = Province,
+    responses(
+        (status = 201, description = "Province created", body = CreatedId),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid fields", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn create_province(
+    db: web::Data<dyn WilayahRepository>,
+    province: web::Json<Province>,
+) -> impl Responder {
     match db.create_province(&province.into_inner()) {
         Ok(id) => HttpResponse::Created().json(json!({
             "id": id
         })),
+        Err(DatabaseError::Validation(fields)) => validation_failed(fields),
         Err(e) => HttpResponse::InternalServerError().json(json!({
             "error": format!("Failed to create province: {}", e)
         })),
     }
 }
 
-pub async fn create_regency(db: web::Data<Database>, regency: web::Json<Regency>) -> impl Responder {
+#[utoipa::path(
+    post,
+    path = "/regencies",
+    tag = "regencies",
+    request_body = Regency,
+    responses(
+        (status = 201, description = "Regency created", body = CreatedId),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid fields or parent", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn create_regency(
+    db: web::Data<dyn WilayahRepository>,
+    regency: web::Json<Regency>,
+) -> impl Responder {
     match db.create_regency(&regency.into_inner()) {
         Ok(id) => HttpResponse::Created().json(json!({
             "id": id
         })),
+        Err(DatabaseError::Validation(fields)) => validation_failed(fields),
         Err(e) => HttpResponse::InternalServerError().json(json!({
             "error": format!("Failed to create regency: {}", e)
         })),
     }
 }
 
-pub async fn create_district(db: web::Data<Database>, district: web::Json<District>) -> impl Responder {
+#[utoipa::path(
+    post,
+    path = "/districts",
+    tag = "districts",
+    request_body = District,
+    responses(
+        (status = 201, description = "District created", body = CreatedId),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid fields or parent", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn create_district(
+    db: web::Data<dyn WilayahRepository>,
+    district: web::Json<District>,
+) -> impl Responder {
     match db.create_district(&district.into_inner()) {
         Ok(id) => HttpResponse::Created().json(json!({
             "id": id
         })),
+        Err(DatabaseError::Validation(fields)) => validation_failed(fields),
         Err(e) => HttpResponse::InternalServerError().json(json!({
             "error": format!("Failed to create district: {}", e)
         })),
     }
 }
 
-pub async fn create_village(db: web::Data<Database>, village: web::Json<Village>) -> impl Responder {
+#[utoipa::path(
+    post,
+    path = "/villages",
+    tag = "villages",
+    request_body = Village,
+    responses(
+        (status = 201, description = "Village created", body = CreatedId),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid fields or parent", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn create_village(
+    db: web::Data<dyn WilayahRepository>,
+    village: web::Json<Village>,
+) -> impl Responder {
     match db.create_village(&village.into_inner()) {
         Ok(id) => HttpResponse::Created().json(json!({
             "id": id
         })),
+        Err(DatabaseError::Validation(fields)) => validation_failed(fields),
         Err(e) => HttpResponse::InternalServerError().json(json!({
             "error": format!("Failed to create village: {}", e)
         })),
     }
 }
 
-pub async fn update_province(db: web::Data<Database>, path: web::Path<i64>, province: web::Json<Province>) -> impl Responder {
-    match db.update_province(path.into_inner(), &province.into_inner()) {
-        Ok(()) => HttpResponse::NoContent().finish(),
+#[utoipa::path(
+    put,
+    path = "/provinces/{id}",
+    tag = "provinces",
+    params(
+        ("id" = i64, Path, description = "Province ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on")
+    ),
+    request_body = Province,
+    responses(
+        (status = 204, description = "Province updated", headers(("ETag" = String, description = "New row version"))),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid fields, or code changed while children exist", body = ValidationErrorResponse),
+        (status = 404, description = "Province not found", body = ErrorResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn update_province(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    province: web::Json<Province>,
+) -> impl Responder {
+    let if_match = if_match(&req);
+    update_response(
+        &req,
+        db.update_province(
+            path.into_inner(),
+            &province.into_inner(),
+            if_match.as_deref(),
+        ),
+        "Province",
+    )
+}
+
+#[utoipa::path(
+    patch,
+    path = "/provinces/{id}",
+    tag = "provinces",
+    params(
+        ("id" = i64, Path, description = "Province ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the patch is based on")
+    ),
+    request_body(content = Object, content_type = "application/merge-patch+json", description = "JSON Merge Patch of the province"),
+    responses(
+        (status = 204, description = "Province updated", headers(("ETag" = String, description = "New row version"))),
+        (status = 400, description = "Invalid JSON body or patch result", body = ErrorResponse),
+        (status = 404, description = "Province not found", body = ErrorResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 422, description = "Invalid fields, or code changed while children exist", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn patch_province(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    patch: web::Json<Value>,
+) -> impl Responder {
+    let id = path.into_inner();
+    patch_response(
+        &req,
+        "Province",
+        db.get_one_province(id),
+        patch.into_inner(),
+        |province, if_match| db.update_province(id, province, Some(if_match)),
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/regencies/{id}",
+    tag = "regencies",
+    params(
+        ("id" = i64, Path, description = "Regency ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on")
+    ),
+    request_body = Regency,
+    responses(
+        (status = 204, description = "Regency updated", headers(("ETag" = String, description = "New row version"))),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid fields or parent, or code changed while children exist", body = ValidationErrorResponse),
+        (status = 404, description = "Regency not found", body = ErrorResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn update_regency(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    regency: web::Json<Regency>,
+) -> impl Responder {
+    let if_match = if_match(&req);
+    update_response(
+        &req,
+        db.update_regency(
+            path.into_inner(),
+            &regency.into_inner(),
+            if_match.as_deref(),
+        ),
+        "Regency",
+    )
+}
+
+#[utoipa::path(
+    patch,
+    path = "/regencies/{id}",
+    tag = "regencies",
+    params(
+        ("id" = i64, Path, description = "Regency ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the patch is based on")
+    ),
+    request_body(content = Object, content_type = "application/merge-patch+json", description = "JSON Merge Patch of the regency"),
+    responses(
+        (status = 204, description = "Regency updated", headers(("ETag" = String, description = "New row version"))),
+        (status = 400, description = "Invalid JSON body or patch result", body = ErrorResponse),
+        (status = 404, description = "Regency not found", body = ErrorResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 422, description = "Invalid fields or parent, or code changed while children exist", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn patch_regency(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    patch: web::Json<Value>,
+) -> impl Responder {
+    let id = path.into_inner();
+    patch_response(
+        &req,
+        "Regency",
+        db.get_one_regency(id),
+        patch.into_inner(),
+        |regency, if_match| db.update_regency(id, regency, Some(if_match)),
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/districts/{id}",
+    tag = "districts",
+    params(
+        ("id" = i64, Path, description = "District ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on")
+    ),
+    request_body = District,
+    responses(
+        (status = 204, description = "District updated", headers(("ETag" = String, description = "New row version"))),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid fields or parent, or code changed while children exist", body = ValidationErrorResponse),
+        (status = 404, description = "District not found", body = ErrorResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn update_district(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    district: web::Json<District>,
+) -> impl Responder {
+    let if_match = if_match(&req);
+    update_response(
+        &req,
+        db.update_district(
+            path.into_inner(),
+            &district.into_inner(),
+            if_match.as_deref(),
+        ),
+        "District",
+    )
+}
+
+#[utoipa::path(
+    patch,
+    path = "/districts/{id}",
+    tag = "districts",
+    params(
+        ("id" = i64, Path, description = "District ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the patch is based on")
+    ),
+    request_body(content = Object, content_type = "application/merge-patch+json", description = "JSON Merge Patch of the district"),
+    responses(
+        (status = 204, description = "District updated", headers(("ETag" = String, description = "New row version"))),
+        (status = 400, description = "Invalid JSON body or patch result", body = ErrorResponse),
+        (status = 404, description = "District not found", body = ErrorResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 422, description = "Invalid fields or parent, or code changed while children exist", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn patch_district(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    patch: web::Json<Value>,
+) -> impl Responder {
+    let id = path.into_inner();
+    patch_response(
+        &req,
+        "District",
+        db.get_one_district(id),
+        patch.into_inner(),
+        |district, if_match| db.update_district(id, district, Some(if_match)),
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/villages/{id}",
+    tag = "villages",
+    params(
+        ("id" = i64, Path, description = "Village ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the update is based on")
+    ),
+    request_body = Village,
+    responses(
+        (status = 204, description = "Village updated", headers(("ETag" = String, description = "New row version"))),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid fields or parent", body = ValidationErrorResponse),
+        (status = 404, description = "Village not found", body = ErrorResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn update_village(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    village: web::Json<Village>,
+) -> impl Responder {
+    let if_match = if_match(&req);
+    update_response(
+        &req,
+        db.update_village(
+            path.into_inner(),
+            &village.into_inner(),
+            if_match.as_deref(),
+        ),
+        "Village",
+    )
+}
+
+#[utoipa::path(
+    patch,
+    path = "/villages/{id}",
+    tag = "villages",
+    params(
+        ("id" = i64, Path, description = "Village ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the patch is based on")
+    ),
+    request_body(content = Object, content_type = "application/merge-patch+json", description = "JSON Merge Patch of the village"),
+    responses(
+        (status = 204, description = "Village updated", headers(("ETag" = String, description = "New row version"))),
+        (status = 400, description = "Invalid JSON body or patch result", body = ErrorResponse),
+        (status = 404, description = "Village not found", body = ErrorResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 422, description = "Invalid fields or parent", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn patch_village(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    patch: web::Json<Value>,
+) -> impl Responder {
+    let id = path.into_inner();
+    patch_response(
+        &req,
+        "Village",
+        db.get_one_village(id),
+        patch.into_inner(),
+        |village, if_match| db.update_village(id, village, Some(if_match)),
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/provinces/{id}",
+    tag = "provinces",
+    params(
+        ("id" = i64, Path, description = "Province ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the delete is based on"),
+        DeleteOptions
+    ),
+    responses(
+        (status = 200, description = "Dry run: rows that would be deleted", body = DeletePlan),
+        (status = 204, description = "Province deleted"),
+        (status = 404, description = "Province not found", body = ErrorResponse),
+        (status = 409, description = "Province has children and cascade is not set", body = HasChildrenResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn delete_province(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    options: web::Query<DeleteOptions>,
+) -> impl Responder {
+    let if_match = if_match(&req);
+    delete_response(
+        &req,
+        db.delete_province(path.into_inner(), &options, if_match.as_deref()),
+        "Province",
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/regencies/{id}",
+    tag = "regencies",
+    params(
+        ("id" = i64, Path, description = "Regency ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the delete is based on"),
+        DeleteOptions
+    ),
+    responses(
+        (status = 200, description = "Dry run: rows that would be deleted", body = DeletePlan),
+        (status = 204, description = "Regency deleted"),
+        (status = 404, description = "Regency not found", body = ErrorResponse),
+        (status = 409, description = "Regency has children and cascade is not set", body = HasChildrenResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn delete_regency(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    options: web::Query<DeleteOptions>,
+) -> impl Responder {
+    let if_match = if_match(&req);
+    delete_response(
+        &req,
+        db.delete_regency(path.into_inner(), &options, if_match.as_deref()),
+        "Regency",
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/districts/{id}",
+    tag = "districts",
+    params(
+        ("id" = i64, Path, description = "District ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the delete is based on"),
+        DeleteOptions
+    ),
+    responses(
+        (status = 200, description = "Dry run: rows that would be deleted", body = DeletePlan),
+        (status = 204, description = "District deleted"),
+        (status = 404, description = "District not found", body = ErrorResponse),
+        (status = 409, description = "District has children and cascade is not set", body = HasChildrenResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn delete_district(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    options: web::Query<DeleteOptions>,
+) -> impl Responder {
+    let if_match = if_match(&req);
+    delete_response(
+        &req,
+        db.delete_district(path.into_inner(), &options, if_match.as_deref()),
+        "District",
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/villages/{id}",
+    tag = "villages",
+    params(
+        ("id" = i64, Path, description = "Village ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the delete is based on"),
+        DeleteOptions
+    ),
+    responses(
+        (status = 200, description = "Dry run: rows that would be deleted", body = DeletePlan),
+        (status = 204, description = "Village deleted"),
+        (status = 404, description = "Village not found", body = ErrorResponse),
+        (status = 412, description = "If-Match does not match the current version", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn delete_village(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    options: web::Query<DeleteOptions>,
+) -> impl Responder {
+    let if_match = if_match(&req);
+    delete_response(
+        &req,
+        db.delete_village(path.into_inner(), &options, if_match.as_deref()),
+        "Village",
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/provinces/{province_id}/regencies",
+    tag = "regencies",
+    params(
+        ("province_id" = i64, Path, description = "Province ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "Regencies of the province", body = Vec<Regency>, headers(("ETag" = String, description = "Dataset revision"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_regencies_by_province(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    province_id: web::Path<i64>,
+) -> impl Responder {
+    let province_id = province_id.into_inner();
+    let revision = db.revision();
+    let key = format!("provinces/{}/regencies", province_id);
+    let representation = Representation::new(&req, proto::Message::Regencies);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        Ok((None, db.get_regencies_by_province(province_id)?))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to update province: {}", e)
+            "error": format!("Failed to fetch regencies: {}", e)
         })),
     }
 }
 
-pub async fn update_regency(db: web::Data<Database>, path: web::Path<i64>, regency: web::Json<Regency>) -> impl Responder {
-    match db.update_regency(path.into_inner(), &regency.into_inner()) {
-        Ok(()) => HttpResponse::NoContent().finish(),
+// Get Districts by Regency ID
+#[utoipa::path(
+    get,
+    path = "/regencies/{city_id}/districts",
+    tag = "districts",
+    params(
+        ("city_id" = i64, Path, description = "Regency ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "Districts of the regency", body = Vec<District>, headers(("ETag" = String, description = "Dataset revision"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_districts_by_regency(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    regency_id: web::Path<i64>,
+) -> impl Responder {
+    let regency_id = regency_id.into_inner();
+    let revision = db.revision();
+    let key = format!("regencies/{}/districts", regency_id);
+    let representation = Representation::new(&req, proto::Message::Districts);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        Ok((None, db.get_districts_by_regency(regency_id)?))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to update regency: {}", e)
+            "error": format!("Failed to fetch districts: {}", e)
         })),
     }
 }
 
-pub async fn update_district(db: web::Data<Database>, path: web::Path<i64>, district: web::Json<District>) -> impl Responder {
-    match db.update_district(path.into_inner(), &district.into_inner()) {
-        Ok(()) => HttpResponse::NoContent().finish(),
+// Get Villages by District ID
+#[utoipa::path(
+    get,
+    path = "/districts/{region_id}/villages",
+    tag = "villages",
+    params(
+        ("region_id" = i64, Path, description = "District ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "Villages of the district", body = Vec<Village>, headers(("ETag" = String, description = "Dataset revision"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_villages_by_district(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    district_id: web::Path<i64>,
+) -> impl Responder {
+    let district_id = district_id.into_inner();
+    let revision = db.revision();
+    let key = format!("districts/{}/villages", district_id);
+    let representation = Representation::new(&req, proto::Message::Villages);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        Ok((None, db.get_villages_by_district(district_id)?))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to update district: {}", e)
+            "error": format!("Failed to fetch villages: {}", e)
         })),
     }
 }
 
-pub async fn update_village(db: web::Data<Database>, path: web::Path<i64>, village: web::Json<Village>) -> impl Responder {
-    match db.update_village(path.into_inner(), &village.into_inner()) {
-        Ok(()) => HttpResponse::NoContent().finish(),
+// Get Full Admin Data by Village ID
+#[utoipa::path(
+    get,
+    path = "/villages/{village_id}/details",
+    tag = "villages",
+    params(
+        ("village_id" = i64, Path, description = "Village ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "Full administrative hierarchy of the village", body = FullAdminData, headers(("ETag" = String, description = "Dataset revision"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 404, description = "Village not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_full_admin_data(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    village_id: web::Path<i64>,
+) -> impl Responder {
+    let village_id = village_id.into_inner();
+    let revision = db.revision();
+    let key = format!("villages/{}/details", village_id);
+    let representation = Representation::new(&req, proto::Message::FullAdminData);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        let full_data = db
+            .get_full_admin_data(village_id)?
+            .ok_or(DatabaseError::NotFound)?;
+        Ok((None, full_data))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
+            "error": "Village not found"
+        })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to update village: {}", e)
+            "error": format!("Failed to fetch admin data: {}", e)
         })),
     }
 }
 
-pub async fn delete_province(db: web::Data<Database>, path: web::Path<i64>) -> impl Responder {
-    match db.delete_province(path.into_inner()) {
-        Ok(()) => HttpResponse::NoContent().finish(),
+#[utoipa::path(
+    get,
+    path = "/provinces/code/{code}",
+    tag = "provinces",
+    params(
+        ("code" = String, Path, description = "Province code", example = "32"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "Province found", body = Province, headers(("ETag" = String, description = "Row version"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 404, description = "Province not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_province_by_code(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let code = path.into_inner();
+    let revision = db.revision();
+    let key = format!("provinces/code/{}", code);
+    let representation = Representation::new(&req, proto::Message::Province);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        let id = db
+            .find_id_by_code(Level::Province, &code)?
+            .ok_or(DatabaseError::NotFound)?;
+        let province = db.get_one_province(id)?.ok_or(DatabaseError::NotFound)?;
+        Ok((province.version, province))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
+            "error": "Province not found"
+        })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to delete province: {}", e)
+            "error": format!("Failed to fetch province: {}", e)
         })),
     }
 }
 
-pub async fn delete_regency(db: web::Data<Database>, path: web::Path<i64>) -> impl Responder {
-    match db.delete_regency(path.into_inner()) {
-        Ok(()) => HttpResponse::NoContent().finish(),
+// Idempotent create-or-update keyed on the Kemendagri code, for sync jobs
+#[utoipa::path(
+    put,
+    path = "/provinces/code/{code}",
+    tag = "provinces",
+    params(("code" = String, Path, description = "Province code", example = "32")),
+    request_body = UpsertRequest,
+    responses(
+        (status = 200, description = "Province updated or already up to date", body = UpsertResult),
+        (status = 201, description = "Province created", body = UpsertResult),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid code or name", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn upsert_province_by_code(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpsertRequest>,
+) -> impl Responder {
+    upsert_response(
+        &req,
+        db.upsert_by_code(Level::Province, &path.into_inner(), &body.name),
+        "Province",
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/regencies/code/{code}",
+    tag = "regencies",
+    params(
+        ("code" = String, Path, description = "Regency code", example = "32.04"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "Regency found", body = Regency, headers(("ETag" = String, description = "Row version"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 404, description = "Regency not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_regency_by_code(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let code = path.into_inner();
+    let revision = db.revision();
+    let key = format!("regencies/code/{}", code);
+    let representation = Representation::new(&req, proto::Message::Regency);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        let id = db
+            .find_id_by_code(Level::Regency, &code)?
+            .ok_or(DatabaseError::NotFound)?;
+        let regency = db.get_one_regency(id)?.ok_or(DatabaseError::NotFound)?;
+        Ok((regency.version, regency))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
+            "error": "Regency not found"
+        })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to delete regency: {}", e)
+            "error": format!("Failed to fetch regency: {}", e)
         })),
     }
 }
 
-pub async fn delete_district(db: web::Data<Database>, path: web::Path<i64>) -> impl Responder {
-    match db.delete_district(path.into_inner()) {
-        Ok(()) => HttpResponse::NoContent().finish(),
+// Idempotent create-or-update keyed on the Kemendagri code, for sync jobs
+#[utoipa::path(
+    put,
+    path = "/regencies/code/{code}",
+    tag = "regencies",
+    params(("code" = String, Path, description = "Regency code", example = "32.04")),
+    request_body = UpsertRequest,
+    responses(
+        (status = 200, description = "Regency updated or already up to date", body = UpsertResult),
+        (status = 201, description = "Regency created", body = UpsertResult),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid code or name, or its parent code does not exist", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn upsert_regency_by_code(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpsertRequest>,
+) -> impl Responder {
+    upsert_response(
+        &req,
+        db.upsert_by_code(Level::Regency, &path.into_inner(), &body.name),
+        "Regency",
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/districts/code/{code}",
+    tag = "districts",
+    params(
+        ("code" = String, Path, description = "District code", example = "32.04.08"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "District found", body = District, headers(("ETag" = String, description = "Row version"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 404, description = "District not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_district_by_code(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let code = path.into_inner();
+    let revision = db.revision();
+    let key = format!("districts/code/{}", code);
+    let representation = Representation::new(&req, proto::Message::District);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        let id = db
+            .find_id_by_code(Level::District, &code)?
+            .ok_or(DatabaseError::NotFound)?;
+        let district = db.get_one_district(id)?.ok_or(DatabaseError::NotFound)?;
+        Ok((district.version, district))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
+            "error": "District not found"
+        })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to delete district: {}", e)
+            "error": format!("Failed to fetch district: {}", e)
         })),
     }
 }
 
-pub async fn delete_village(db: web::Data<Database>, path: web::Path<i64>) -> impl Responder {
-    match db.delete_village(path.into_inner()) {
-        Ok(()) => HttpResponse::NoContent().finish(),
+// Idempotent create-or-update keyed on the Kemendagri code, for sync jobs
+#[utoipa::path(
+    put,
+    path = "/districts/code/{code}",
+    tag = "districts",
+    params(("code" = String, Path, description = "District code", example = "32.04.08")),
+    request_body = UpsertRequest,
+    responses(
+        (status = 200, description = "District updated or already up to date", body = UpsertResult),
+        (status = 201, description = "District created", body = UpsertResult),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid code or name, or its parent code does not exist", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn upsert_district_by_code(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpsertRequest>,
+) -> impl Responder {
+    upsert_response(
+        &req,
+        db.upsert_by_code(Level::District, &path.into_inner(), &body.name),
+        "District",
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/villages/code/{code}",
+    tag = "villages",
+    params(
+        ("code" = String, Path, description = "Village code", example = "32.04.08.2002"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy")
+    ),
+    responses(
+        (status = 200, description = "Village found", body = Village, headers(("ETag" = String, description = "Row version"))),
+        (status = 304, description = "Cached copy is current"),
+        (status = 404, description = "Village not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_village_by_code(
+    db: web::Data<dyn WilayahRepository>,
+    cache: web::Data<ResponseCache>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let code = path.into_inner();
+    let revision = db.revision();
+    let key = format!("villages/code/{}", code);
+    let representation = Representation::new(&req, proto::Message::Village);
+    let found = cache.get_or_load(key, representation, revision.seq, || {
+        let id = db
+            .find_id_by_code(Level::Village, &code)?
+            .ok_or(DatabaseError::NotFound)?;
+        let village = db.get_one_village(id)?.ok_or(DatabaseError::NotFound)?;
+        Ok((village.version, village))
+    });
+    match found {
+        Ok(response) => cached_response(&req, &cache, &revision, &response),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
+            "error": "Village not found"
+        })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to delete village: {}", e)
+            "error": format!("Failed to fetch village: {}", e)
         })),
     }
 }
 
-pub async fn get_regencies_by_province(
+// Idempotent create-or-update keyed on the Kemendagri code, for sync jobs
+#[utoipa::path(
+    put,
+    path = "/villages/code/{code}",
+    tag = "villages",
+    params(("code" = String, Path, description = "Village code", example = "32.04.08.2002")),
+    request_body = UpsertRequest,
+    responses(
+        (status = 200, description = "Village updated or already up to date", body = UpsertResult),
+        (status = 201, description = "Village created", body = UpsertResult),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid code or name, or its parent code does not exist", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn upsert_village_by_code(
+    db: web::Data<dyn WilayahRepository>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpsertRequest>,
+) -> impl Responder {
+    upsert_response(
+        &req,
+        db.upsert_by_code(Level::Village, &path.into_inner(), &body.name),
+        "Village",
+    )
+}
+
+// Upper bound of items accepted by one /batch/resolve request
+const MAX_BATCH_ITEMS: usize = 50_000;
+
+// Resolve many codes or ids to their hierarchies. Items are grouped per level
+// so the lookup costs a few set-based queries instead of one query per item.
+#[utoipa::path(
+    post,
+    path = "/batch/resolve",
+    tag = "batch",
+    request_body = BatchResolveRequest,
+    responses(
+        (status = 200, description = "Resolution of every item, keyed by input", body = BatchResolveResponse),
+        (status = 400, description = "Invalid JSON body or too many items", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn batch_resolve(
     db: web::Data<Database>,
-    province_id: web::Path<i64>,
+    request: web::Json<BatchResolveRequest>,
 ) -> impl Responder {
-    match db.get_regencies_by_province(province_id.into_inner()) {
-        Ok(regencies) => HttpResponse::Ok().json(regencies),
+    let items = request.into_inner().items;
+    if items.len() > MAX_BATCH_ITEMS {
+        return HttpResponse::BadRequest().json(json!({
+            "error": format!("At most {} items can be resolved per request", MAX_BATCH_ITEMS)
+        }));
+    }
+
+    // Up to MAX_BATCH_ITEMS lookups, off the async workers
+    match web::block(move || resolve_batch(&db, &items)).await {
+        Ok(Ok(results)) => HttpResponse::Ok().json(BatchResolveResponse { results }),
+        Ok(Err(e)) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to resolve batch: {}", e)
+        })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to fetch regencies: {}", e)
+            "error": format!("Failed to resolve batch: {}", e)
         })),
     }
 }
 
-// Get Districts by Regency ID
-pub async fn get_districts_by_regency(
+fn resolve_batch(
+    db: &Database,
+    items: &[BatchItem],
+) -> Result<BTreeMap<String, BatchResolution>, DatabaseError> {
+    let mut results = BTreeMap::new();
+    let mut codes: HashMap<Level, Vec<String>> = HashMap::new();
+    let mut ids: HashMap<Level, Vec<i64>> = HashMap::new();
+
+    for item in items {
+        results.insert(item.key(), BatchResolution::NotFound);
+        match item {
+            BatchItem::Code(code) => match Level::from_code(code) {
+                Some(level) => codes.entry(level).or_default().push(code.clone()),
+                None => {
+                    results.insert(item.key(), BatchResolution::Invalid);
+                }
+            },
+            BatchItem::Id { level, id } => ids.entry(*level).or_default().push(*id),
+        }
+    }
+
+    for (level, codes) in codes {
+        for hierarchy in db.resolve_codes(level, &codes)? {
+            results.insert(
+                hierarchy.code().to_string(),
+                BatchResolution::Found(hierarchy),
+            );
+        }
+    }
+
+    for (level, ids) in ids {
+        for (id, hierarchy) in db.resolve_ids(level, &ids)? {
+            let key = BatchItem::Id { level, id }.key();
+            results.insert(key, BatchResolution::Found(hierarchy));
+        }
+    }
+
+    Ok(results)
+}
+
+// Upper bound of lines accepted by one /batch/upsert request, above the
+// size of the whole dataset
+const MAX_UPSERT_LINES: usize = 200_000;
+
+// Apply an NDJSON stream of {"code", "name"} lines, e.g. a full upstream
+// export. Lines that fail to parse or validate are reported, not fatal.
+#[utoipa::path(
+    post,
+    path = "/batch/upsert",
+    tag = "batch",
+    request_body(content = BulkUpsertLine, content_type = "application/x-ndjson", description = "One JSON object per line, at most 200000 lines"),
+    responses(
+        (status = 200, description = "Outcome of every line", body = BulkUpsertResponse),
+        (status = 400, description = "Too many lines", body = ErrorResponse),
+        (status = 413, description = "Body too large", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn bulk_upsert(db: web::Data<Database>, body: web::Bytes) -> impl Responder {
+    let lines = body
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.trim_ascii().is_empty())
+        .count();
+    if lines > MAX_UPSERT_LINES {
+        return HttpResponse::BadRequest().json(json!({
+            "error": format!("At most {} lines can be upserted per request", MAX_UPSERT_LINES)
+        }));
+    }
+
+    // One transaction over the whole body, off the async workers
+    match web::block(move || upsert_lines(&db, &body)).await {
+        Ok(Ok(response)) => HttpResponse::Ok().json(response),
+        Ok(Err(e)) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to upsert: {}", e)
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to upsert: {}", e)
+        })),
+    }
+}
+
+fn upsert_lines(db: &Database, body: &[u8]) -> Result<BulkUpsertResponse, DatabaseError> {
+    let mut lines = Vec::new();
+    let mut results = Vec::new();
+    for (index, line) in body.split(|b| *b == b'\n').enumerate() {
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+        match serde_json::from_slice::<BulkUpsertLine>(line) {
+            Ok(parsed) => lines.push((index + 1, parsed)),
+            Err(e) => results.push(BulkUpsertResult {
+                line: index + 1,
+                code: None,
+                status: UpsertStatus::Invalid,
+                id: None,
+                version: None,
+                errors: vec![FieldError::new("line", e.to_string())],
+            }),
+        }
+    }
+
+    let rows: Vec<BulkUpsertLine> = lines.iter().map(|(_, line)| line.clone()).collect();
+    let outcomes = db.upsert_many(&rows)?;
+    for ((line, row), outcome) in lines.into_iter().zip(outcomes) {
+        results.push(match outcome {
+            Ok(result) => BulkUpsertResult {
+                line,
+                code: Some(result.code),
+                status: result.status,
+                id: Some(result.id),
+                version: Some(result.version),
+                errors: Vec::new(),
+            },
+            Err(errors) => BulkUpsertResult {
+                line,
+                code: Some(row.code),
+                status: UpsertStatus::Invalid,
+                id: None,
+                version: None,
+                errors,
+            },
+        });
+    }
+    results.sort_by_key(|result| result.line);
+
+    let mut counts = BTreeMap::new();
+    for result in &results {
+        *counts
+            .entry(result.status.as_str().to_string())
+            .or_insert(0) += 1;
+    }
+    Ok(BulkUpsertResponse { counts, results })
+}
+
+// Default and upper bound of changes per /changes page
+const DEFAULT_CHANGES_LIMIT: usize = 500;
+const MAX_CHANGES_LIMIT: usize = 5000;
+
+#[derive(Deserialize, IntoParams)]
+pub struct ChangesQuery {
+    /// `next` token of the previous page, `0` (the default) for the
+    /// beginning of the feed
+    since: Option<String>,
+    /// Changes per page, from 1 to 5000
+    limit: Option<usize>,
+}
+
+// Inserts, updates and deletes across all four levels, in order
+#[utoipa::path(
+    get,
+    path = "/changes",
+    tag = "changes",
+    params(ChangesQuery),
+    responses(
+        (status = 200, description = "Changes after `since`", body = ChangeFeed),
+        (status = 400, description = "Invalid since token", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_changes(
     db: web::Data<Database>,
-    regency_id: web::Path<i64>,
+    query: web::Query<ChangesQuery>,
 ) -> impl Responder {
-    match db.get_districts_by_regency(regency_id.into_inner()) {
-        Ok(districts) => HttpResponse::Ok().json(districts),
+    let since = match query.since.as_deref().unwrap_or("0").parse::<i64>() {
+        Ok(since) if since >= 0 => since,
+        _ => {
+            return HttpResponse::BadRequest().json(json!({
+                "error": "since must be a token returned as next or head"
+            }))
+        }
+    };
+    // An empty page with has_more would never advance the client
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_CHANGES_LIMIT)
+        .clamp(1, MAX_CHANGES_LIMIT);
+
+    match db.changes_since(since, limit) {
+        Ok(feed) => HttpResponse::Ok().json(feed),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to fetch districts: {}", e)
+            "error": format!("Failed to fetch changes: {}", e)
         })),
     }
 }
 
-// Get Villages by District ID
-pub async fn get_villages_by_district(
+#[derive(Deserialize, IntoParams)]
+pub struct EventsQuery {
+    /// Comma-separated levels to stream, e.g. `regency,district`. All
+    /// levels when absent.
+    level: Option<String>,
+    /// Only changes of codes starting with this prefix, e.g. `32.`
+    code_prefix: Option<String>,
+    /// Start after this change seq instead of now. The `Last-Event-ID`
+    /// header sent by reconnecting clients takes precedence.
+    since: Option<String>,
+}
+
+// Live stream of the change feed as Server-Sent Events. Each event has the
+// change seq as id, `<level>.<created|updated|deleted>` as name and the
+// change as JSON data.
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "changes",
+    params(
+        EventsQuery,
+        ("Last-Event-ID" = Option<String>, Header, description = "Resume after this event id")
+    ),
+    responses(
+        (status = 200, description = "Event stream", content_type = "text/event-stream", body = String),
+        (status = 400, description = "Invalid level or since", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn events(
     db: web::Data<Database>,
-    district_id: web::Path<i64>,
+    req: HttpRequest,
+    query: web::Query<EventsQuery>,
 ) -> impl Responder {
-    match db.get_villages_by_district(district_id.into_inner()) {
-        Ok(villages) => HttpResponse::Ok().json(villages),
+    let mut levels = Vec::new();
+    for name in query.level.iter().flat_map(|l| l.split(',')) {
+        match Level::parse(name.trim()) {
+            Some(level) => levels.push(level),
+            None => {
+                return HttpResponse::BadRequest().json(json!({
+                    "error": format!("Unknown level: {}", name)
+                }))
+            }
+        }
+    }
+
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok());
+    let since = match last_event_id.or(query.since.as_deref()) {
+        None => None,
+        Some(since) => match since.trim().parse::<i64>() {
+            Ok(since) if since >= 0 => Some(since),
+            _ => {
+                return HttpResponse::BadRequest().json(json!({
+                    "error": "since and Last-Event-ID must be an event id"
+                }))
+            }
+        },
+    };
+
+    let filter = ChangeFilter {
+        levels,
+        code_prefix: query.code_prefix.clone(),
+    };
+    match events::stream(db, filter, since) {
+        Ok(stream) => HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header((header::CACHE_CONTROL, "no-cache"))
+            // Compressors buffer, which would hold back events
+            .insert_header(header::ContentEncoding::Identity)
+            // Stop nginx from buffering the stream
+            .insert_header(("X-Accel-Buffering", "no"))
+            .streaming(stream),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to fetch villages: {}", e)
+            "error": format!("Failed to open event stream: {}", e)
         })),
     }
 }
 
-// Get Full Admin Data by Village ID
-pub async fn get_full_admin_data(
+// Query the hierarchy with GraphQL, e.g.
+// `{ village(code: "32.04.08.2002") { name district { name } } }`
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    tag = "graphql",
+    request_body(content = Object, description = "GraphQL request with `query`, `variables` and `operationName`"),
+    responses(
+        (status = 200, description = "GraphQL response with `data` and `errors`", body = Object)
+    )
+)]
+pub async fn graphql(
     db: web::Data<Database>,
-    village_id: web::Path<i64>,
+    schema: web::Data<WilayahSchema>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    graphql::execute(&schema, db, request.into_inner())
+        .await
+        .into()
+}
+
+#[utoipa::path(
+    get,
+    path = "/graphql",
+    tag = "graphql",
+    responses((status = 200, description = "GraphiQL IDE for /graphql", content_type = "text/html"))
+)]
+pub async fn graphiql() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/quality",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Data quality report; `ok` is false when errors were found", body = QualityReport),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn quality_report(db: web::Data<Database>) -> impl Responder {
+    match db.quality_report() {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to build quality report: {}", e)
+        })),
+    }
+}
+
+// Download of a point-in-time copy of the database, taken with the online
+// backup API while the server keeps serving. The copy includes the webhook
+// secrets, so it is only served behind the admin token of the /admin scope.
+#[utoipa::path(
+    get,
+    path = "/admin/backup",
+    tag = "admin",
+    params(BackupOptions),
+    responses(
+        (status = 200, description = "SQLite database file, webhook secrets included", content_type = "application/vnd.sqlite3", body = Vec<u8>),
+        (status = 500, description = "Backup failed", body = ErrorResponse)
+    )
+)]
+pub async fn download_backup(
+    db: web::Data<Database>,
+    options: web::Query<BackupOptions>,
+) -> impl Responder {
+    let vacuum = options.vacuum;
+    let name = backup::file_name(std::time::SystemTime::now());
+    let (file, len) = match web::block(move || backup::temp_backup(&db, vacuum)).await {
+        Ok(Ok(backup)) => backup,
+        Ok(Err(e)) => {
+            return HttpResponse::InternalServerError().json(json!({
+                "error": format!("Failed to back up the database: {}", e)
+            }))
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(json!({
+                "error": format!("Failed to back up the database: {}", e)
+            }))
+        }
+    };
+
+    // Read in chunks on the blocking pool as the client downloads
+    let chunks = futures_util::stream::try_unfold(file, |mut file| async move {
+        let (file, chunk) = web::block(move || {
+            use std::io::Read;
+            let mut chunk = vec![0; 64 * 1024];
+            let read = file.read(&mut chunk)?;
+            chunk.truncate(read);
+            Ok::<_, std::io::Error>((file, chunk))
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+        Ok::<_, std::io::Error>((!chunk.is_empty()).then(|| (web::Bytes::from(chunk), file)))
+    });
+    HttpResponse::Ok()
+        .content_type("application/vnd.sqlite3")
+        .insert_header(header::ContentDisposition::attachment(name))
+        .body(actix_web::body::SizedStream::new(len, chunks))
+}
+
+// Hit/miss counters of the response cache
+#[utoipa::path(
+    get,
+    path = "/admin/cache",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Response cache statistics", body = CacheStats)
+    )
+)]
+pub async fn cache_stats(
+    db: web::Data<Database>,
+    cache: web::Data<ResponseCache>,
+) -> impl Responder {
+    HttpResponse::Ok().json(cache.stats(db.revision().seq))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks",
+    tag = "admin",
+    responses(
+        (status = 200, description = "All webhook subscriptions", body = Vec<Webhook>),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_webhooks(db: web::Data<Database>) -> impl Responder {
+    match db.get_webhooks() {
+        Ok(webhooks) => HttpResponse::Ok().json(webhooks),
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to fetch webhooks: {}", e)
+        })),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/webhooks",
+    tag = "admin",
+    request_body = CreateWebhook,
+    responses(
+        (status = 201, description = "Webhook registered, receiving changes from now on", body = Webhook),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 422, description = "Invalid secret or URL, or a URL into a private network or outside WEBHOOK_ALLOWED_HOSTS", body = ValidationErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn create_webhook(
+    db: web::Data<Database>,
+    policy: Option<web::Data<UrlPolicy>>,
+    webhook: web::Json<CreateWebhook>,
 ) -> impl Responder {
-    match db.get_full_admin_data(village_id.into_inner()) {
-        Ok(Some(full_data)) => HttpResponse::Ok().json(full_data),
+    let policy = policy
+        .map(|policy| policy.get_ref().clone())
+        .unwrap_or_default();
+    // Where the URL points to is only checked once it is well-formed
+    let created = validation::validate_webhook(&webhook)
+        .and_then(|()| {
+            policy
+                .check(&webhook.url)
+                .map_err(|field| DatabaseError::Validation(vec![field]))
+        })
+        .and_then(|()| db.create_webhook(&webhook));
+    match created {
+        Ok(webhook) => HttpResponse::Created().json(webhook),
+        Err(DatabaseError::Validation(fields)) => validation_failed(fields),
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to create webhook: {}", e)
+        })),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks/{id}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Webhook ID")),
+    responses(
+        (status = 200, description = "Webhook found", body = Webhook),
+        (status = 404, description = "Webhook not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_one_webhook(db: web::Data<Database>, path: web::Path<i64>) -> impl Responder {
+    match db.get_one_webhook(path.into_inner()) {
+        Ok(Some(webhook)) => HttpResponse::Ok().json(webhook),
         Ok(None) => HttpResponse::NotFound().json(json!({
-            "error": "Village not found"
+            "error": "Webhook not found"
         })),
         Err(e) => HttpResponse::InternalServerError().json(json!({
-            "error": format!("Failed to fetch admin data: {}", e)
+            "error": format!("Failed to fetch webhook: {}", e)
+        })),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/webhooks/{id}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Webhook ID")),
+    responses(
+        (status = 204, description = "Webhook and its delivery log deleted"),
+        (status = 404, description = "Webhook not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn delete_webhook(db: web::Data<Database>, path: web::Path<i64>) -> impl Responder {
+    match db.delete_webhook(path.into_inner()) {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(DatabaseError::NotFound) => HttpResponse::NotFound().json(json!({
+            "error": "Webhook not found"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to delete webhook: {}", e)
+        })),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct DeliveriesQuery {
+    /// Only deliveries with this status
+    status: Option<DeliveryStatus>,
+    /// Number of deliveries, newest first (default 100, at most 1000)
+    limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks/{id}/deliveries",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Webhook ID"), DeliveriesQuery),
+    responses(
+        (status = 200, description = "Delivery log, newest first", body = Vec<WebhookDelivery>),
+        (status = 404, description = "Webhook not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_webhook_deliveries(
+    db: web::Data<Database>,
+    path: web::Path<i64>,
+    query: web::Query<DeliveriesQuery>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let limit = query.limit.unwrap_or(100).min(1000);
+    let deliveries = match db.get_one_webhook(id) {
+        Ok(Some(_)) => db.get_webhook_deliveries(id, query.status, limit),
+        Ok(None) => {
+            return HttpResponse::NotFound().json(json!({
+                "error": "Webhook not found"
+            }))
+        }
+        Err(e) => Err(e),
+    };
+    match deliveries {
+        Ok(deliveries) => HttpResponse::Ok().json(deliveries),
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to fetch deliveries: {}", e)
         })),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_patches() {
+        let mut target = json!({"code": "32", "name": "JAWA BARAT", "meta": {"a": 1, "b": 2}});
+        merge_patch(
+            &mut target,
+            json!({"name": "JABAR", "code": null, "meta": {"a": null, "c": [3]}}),
+        );
+        assert_eq!(target, json!({"name": "JABAR", "meta": {"b": 2, "c": [3]}}));
+
+        // Anything but an object replaces the target
+        merge_patch(&mut target, json!(["x"]));
+        assert_eq!(target, json!(["x"]));
+        merge_patch(&mut target, json!({"name": "X"}));
+        assert_eq!(target, json!({"name": "X"}));
+    }
+
+    #[test]
+    fn resolves_mixed_batches() {
+        let db = Database::new(":memory:").unwrap();
+        for (level, code, name) in [
+            (Level::Province, "32", "JAWA BARAT"),
+            (Level::Regency, "32.04", "KAB. BANDUNG"),
+            (Level::District, "32.04.08", "BOJONGSOANG"),
+            (Level::Village, "32.04.08.2002", "LENGKONG"),
+        ] {
+            db.upsert_by_code(level, code, name).unwrap();
+        }
+        let village = db
+            .find_id_by_code(Level::Village, "32.04.08.2002")
+            .unwrap()
+            .unwrap();
+
+        let items = vec![
+            BatchItem::Code("32.04".to_string()),
+            BatchItem::Id {
+                level: Level::Village,
+                id: village,
+            },
+            BatchItem::Code("32.04.08".to_string()),
+            BatchItem::Id {
+                level: Level::Province,
+                id: 99,
+            },
+            BatchItem::Code("32.04.09".to_string()),
+            BatchItem::Code("JB".to_string()),
+        ];
+        let results = resolve_batch(&db, &items).unwrap();
+
+        let status = |key: &str| serde_json::to_value(&results[key]).unwrap()["status"].clone();
+        assert_eq!(results.len(), items.len());
+        assert_eq!(status("32.04"), "found");
+        assert_eq!(status(&format!("village:{}", village)), "found");
+        assert_eq!(status("32.04.08"), "found");
+        assert_eq!(status("province:99"), "not_found");
+        assert_eq!(status("32.04.09"), "not_found");
+        assert_eq!(status("JB"), "invalid");
+
+        match &results[&format!("village:{}", village)] {
+            BatchResolution::Found(hierarchy) => {
+                assert_eq!(hierarchy.code(), "32.04.08.2002");
+                assert_eq!(hierarchy.region_name.as_deref(), Some("BOJONGSOANG"));
+            }
+            other => panic!("expected the village, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,306 @@
+// Handlers of the `search` feature: CSV enrichment and address parsing
+use actix_multipart::{Field, Multipart};
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use futures_util::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use std::io::{self, Read, Write};
+use tokio::sync::{mpsc, oneshot};
+use utoipa::IntoParams;
+
+use crate::address;
+use crate::db::Database;
+use crate::enrich::{EnrichError, Enrichment};
+use crate::models::{
+    EnrichUpload, ErrorResponse, FormatAddressRequest, FormattedAddress, ParseAddressRequest,
+    ParsedAddress,
+};
+
+// Chunks buffered between the request, the blocking worker and the response
+const ENRICH_QUEUE: usize = 16;
+
+#[derive(Deserialize, IntoParams)]
+pub struct EnrichQuery {
+    /// Header of the column holding village codes or names. Multipart
+    /// uploads may send it as a `column` form field before `file` instead.
+    column: Option<String>,
+    /// Field delimiter, defaults to `,`
+    delimiter: Option<char>,
+}
+
+// Append province/regency/district/village columns to an uploaded CSV. The
+// file is sent either as the raw request body or as the `file` field of a
+// multipart form. It is enriched on the blocking pool while it uploads and
+// the result is streamed back, so neither is held in memory whole. Errors
+// after the header abort the response.
+#[utoipa::path(
+    post,
+    path = "/enrich",
+    tag = "batch",
+    params(EnrichQuery),
+    request_body(content(
+        (String = "text/csv"),
+        (EnrichUpload = "multipart/form-data")
+    )),
+    responses(
+        (status = 200, description = "Input CSV with region and match_status columns appended", body = String, content_type = "text/csv"),
+        (status = 400, description = "Missing key column or malformed CSV header", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn enrich_csv(
+    db: web::Data<Database>,
+    req: HttpRequest,
+    payload: web::Payload,
+    query: web::Query<EnrichQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let is_multipart = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+    let (upload, form_column) = if is_multipart {
+        match open_enrich_form(Multipart::new(req.headers(), payload)).await {
+            Ok(upload) => upload,
+            Err(response) => return response,
+        }
+    } else {
+        (Upload::Body(payload), None)
+    };
+
+    let Some(column) = form_column.or(query.column) else {
+        return HttpResponse::BadRequest().json(json!({
+            "error": "Missing key column, pass it as ?column=<header>"
+        }));
+    };
+    let delimiter = query.delimiter.unwrap_or(',');
+    if !delimiter.is_ascii() {
+        return HttpResponse::BadRequest().json(json!({
+            "error": "Delimiter must be a single ASCII character"
+        }));
+    }
+
+    let (input, input_rx) = mpsc::channel(ENRICH_QUEUE);
+    let (output, mut output_rx) = mpsc::channel(ENRICH_QUEUE);
+    let (header, header_rx) = oneshot::channel();
+    let failed = output.clone();
+    let worker = web::block(move || {
+        let reader = ChannelReader {
+            chunks: input_rx,
+            current: web::Bytes::new(),
+        };
+        let enrichment = match Enrichment::new(reader, &column, delimiter as u8) {
+            Ok(enrichment) => {
+                let _ = header.send(Ok(()));
+                enrichment
+            }
+            Err(e) => {
+                let _ = header.send(Err(e));
+                return;
+            }
+        };
+        if let Err(e) = enrichment.write_to(&db, ChannelWriter(output.clone())) {
+            let _ = output.blocking_send(Err(io::Error::other(e.to_string())));
+        }
+    });
+    actix_web::rt::spawn(async move {
+        upload.forward(input).await;
+        if worker.await.is_err() {
+            let _ = failed
+                .send(Err(io::Error::other("enrichment stopped")))
+                .await;
+        }
+    });
+
+    match header_rx.await {
+        Ok(Ok(())) => {
+            let chunks = futures_util::stream::poll_fn(move |cx| output_rx.poll_recv(cx));
+            HttpResponse::Ok()
+                .content_type("text/csv; charset=utf-8")
+                .streaming(chunks)
+        }
+        Ok(Err(EnrichError::Database(e))) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to enrich CSV: {}", e)
+        })),
+        Ok(Err(e)) => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string()
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to enrich CSV: enrichment stopped"
+        })),
+    }
+}
+
+// Where the CSV of an /enrich request comes from
+enum Upload {
+    Body(web::Payload),
+    // The form is kept alive while its `file` field is read
+    Form(Box<(Multipart, Field)>),
+}
+
+impl Upload {
+    // Pass the chunks on to the worker until the upload ends, fails or the
+    // worker stops reading
+    async fn forward(self, input: mpsc::Sender<io::Result<web::Bytes>>) {
+        match self {
+            Upload::Body(payload) => forward(payload, input).await,
+            Upload::Form(form) => {
+                let (_form, field) = *form;
+                forward(field, input).await
+            }
+        }
+    }
+}
+
+async fn forward<S, E>(mut stream: S, input: mpsc::Sender<io::Result<web::Bytes>>)
+where
+    S: Stream<Item = Result<web::Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| io::Error::other(format!("Failed to read upload: {}", e)));
+        let failed = chunk.is_err();
+        if input.send(chunk).await.is_err() || failed {
+            break;
+        }
+    }
+}
+
+// Read the form up to its `file` field, taking the `column` field if it
+// comes first
+async fn open_enrich_form(mut form: Multipart) -> Result<(Upload, Option<String>), HttpResponse> {
+    let mut column = None;
+
+    while let Some(field) = form.next().await {
+        let field = field.map_err(|e| {
+            HttpResponse::BadRequest().json(json!({
+                "error": format!("Invalid multipart body: {}", e)
+            }))
+        })?;
+        match field.name() {
+            Some("file") => return Ok((Upload::Form(Box::new((form, field))), column)),
+            Some("column") => {
+                let value = read_limited(field, 1024).await?;
+                column = Some(String::from_utf8_lossy(&value).trim().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    Err(HttpResponse::BadRequest().json(json!({
+        "error": "Missing `file` field in multipart body"
+    })))
+}
+
+async fn read_limited<S, E>(mut stream: S, limit: usize) -> Result<Vec<u8>, HttpResponse>
+where
+    S: Stream<Item = Result<web::Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    let mut body = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| {
+            HttpResponse::BadRequest().json(json!({
+                "error": format!("Failed to read upload: {}", e)
+            }))
+        })?;
+        if body.len() + chunk.len() > limit {
+            return Err(HttpResponse::PayloadTooLarge().json(json!({
+                "error": format!("Upload exceeds {} bytes", limit)
+            })));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+// Blocking reader over the chunks forwarded from the request
+struct ChannelReader {
+    chunks: mpsc::Receiver<io::Result<web::Bytes>>,
+    current: web::Bytes,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.current.is_empty() {
+            match self.chunks.blocking_recv() {
+                Some(chunk) => self.current = chunk?,
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.current.len());
+        buf[..len].copy_from_slice(&self.current.split_to(len));
+        Ok(len)
+    }
+}
+
+// Blocking writer into the response body; fails once the client is gone
+struct ChannelWriter(mpsc::Sender<io::Result<web::Bytes>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(Ok(web::Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "response closed"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/address/parse",
+    tag = "address",
+    request_body = ParseAddressRequest,
+    responses(
+        (status = 200, description = "Recognized address components with their codes", body = ParsedAddress),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn parse_address(
+    db: web::Data<Database>,
+    request: web::Json<ParseAddressRequest>,
+) -> impl Responder {
+    match address::parse_address(&db, &request.address) {
+        Ok(parsed) => HttpResponse::Ok().json(parsed),
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to parse address: {}", e)
+        })),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/address/format",
+    tag = "address",
+    request_body = FormatAddressRequest,
+    responses(
+        (status = 200, description = "Postal address of the village in the requested style", body = FormattedAddress),
+        (status = 400, description = "Invalid JSON body"),
+        (status = 404, description = "Village not found", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn format_address(
+    db: web::Data<Database>,
+    request: web::Json<FormatAddressRequest>,
+) -> impl Responder {
+    match db.get_full_admin_data_by_code(request.village_code.trim()) {
+        Ok(Some(full_data)) => {
+            HttpResponse::Ok().json(address::format_address(&full_data, &request))
+        }
+        Ok(None) => HttpResponse::NotFound().json(json!({
+            "error": "Village not found"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(json!({
+            "error": format!("Failed to format address: {}", e)
+        })),
+    }
+}
@@ -0,0 +1,111 @@
+//! Indonesian administrative regions (provinsi, kabupaten/kota, kecamatan,
+//! desa/kelurahan): the SQLite data layer, code parsing and validation, and
+//! with the `http` feature the actix-web API of wilayah-service.
+//!
+//! Mounting the API in another actix app:
+//!
+//! ```no_run
+//! # #[cfg(feature = "http")]
+//! # async fn run() -> std::io::Result<()> {
+//! use actix_web::{web, App, HttpServer};
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use wilayah::auth::AdminToken;
+//! use wilayah::cache::ResponseCache;
+//! use wilayah::{Database, WilayahRepository};
+//!
+//! let db = web::Data::new(Database::new("data/wilayah_indonesia.db").unwrap());
+//! let repository: Arc<dyn WilayahRepository> = db.clone().into_inner();
+//! let repository = web::Data::from(repository);
+//! let cache = web::Data::new(ResponseCache::new(
+//!     10_000,
+//!     Duration::from_secs(300),
+//!     Duration::from_secs(60),
+//! ));
+//! // Without a token the /admin routes are disabled
+//! let admin_token = web::Data::new(AdminToken::new(std::env::var("ADMIN_TOKEN").ok()));
+//! HttpServer::new(move || {
+//!     App::new()
+//!         .app_data(db.clone())
+//!         .app_data(repository.clone())
+//!         .app_data(cache.clone())
+//!         .app_data(admin_token.clone())
+//!         .configure(wilayah::configure_all)
+//! })
+//! .bind("0.0.0.0:8080")?
+//! .run()
+//! .await
+//! # }
+//! ```
+//!
+//! Any other `WilayahRepository`, such as the `MemoryRepository` loaded from
+//! a snapshot, serves the per-level routes without SQLite:
+//!
+//! ```no_run
+//! # #[cfg(feature = "http")]
+//! # fn app() {
+//! use actix_web::{web, App};
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use wilayah::cache::ResponseCache;
+//! use wilayah::{MemoryRepository, WilayahRepository};
+//!
+//! let snapshot = std::fs::File::open("wilayah.json").unwrap();
+//! let repository: Arc<dyn WilayahRepository> =
+//!     Arc::new(MemoryRepository::from_json(snapshot).unwrap());
+//! let cache = ResponseCache::new(10_000, Duration::from_secs(300), Duration::from_secs(60));
+//! App::new()
+//!     .app_data(web::Data::from(repository))
+//!     .app_data(web::Data::new(cache))
+//!     .configure(wilayah::configure_region_routes);
+//! # }
+//! ```
+
+pub mod backup;
+pub mod db;
+#[cfg(feature = "embedded")]
+pub mod embedded;
+pub mod memory;
+pub mod models;
+pub mod repository;
+pub mod rules;
+pub mod validation;
+
+#[cfg(feature = "search")]
+pub mod address;
+#[cfg(feature = "search")]
+pub mod enrich;
+
+#[cfg(feature = "http")]
+pub mod auth;
+#[cfg(feature = "http")]
+pub mod cache;
+#[cfg(feature = "http")]
+pub mod events;
+#[cfg(feature = "http")]
+pub mod graphql;
+#[cfg(feature = "http")]
+pub mod handlers;
+#[cfg(feature = "http")]
+pub mod negotiate;
+#[cfg(feature = "http")]
+pub mod openapi;
+#[cfg(any(feature = "http", feature = "grpc"))]
+pub mod proto;
+#[cfg(feature = "cli")]
+pub mod query;
+#[cfg(feature = "http")]
+pub mod routes;
+#[cfg(feature = "cli")]
+pub mod static_api;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "http")]
+pub mod webhooks;
+
+pub use db::{Database, DatabaseError};
+pub use memory::MemoryRepository;
+pub use models::Level;
+pub use repository::WilayahRepository;
+#[cfg(feature = "http")]
+pub use routes::{configure_all, configure_region_routes};
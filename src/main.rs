@@ -1,36 +1,476 @@
+use actix_web::middleware::{from_fn, Compress};
 use actix_web::{web, App, HttpServer};
+use clap::{Parser, Subcommand};
 use env_logger::Env;
 use log::info;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use wilayah::query::{self, Format};
+use wilayah::{
+    auth, backup, cache, db, models, negotiate, static_api, webhooks, Level, WilayahRepository,
+};
 
-mod db;
-mod handlers;
-mod models;
-mod routes;
+#[derive(Parser)]
+#[command(version, about = "REST API wilayah administrasi Indonesia")]
+struct Cli {
+    /// Path of the SQLite database [default: data/wilayah_indonesia.db, or
+    /// the dataset embedded in the binary when built with `embedded`]
+    #[arg(long, env = "DATABASE_PATH", global = true)]
+    database: Option<String>,
+
+    /// Output of the query commands
+    #[arg(long, value_enum, default_value_t = Format::Table, global = true)]
+    format: Format,
+
+    /// Responses kept in the in-process cache
+    #[arg(long, env = "CACHE_CAPACITY", default_value_t = 10_000)]
+    cache_capacity: usize,
+
+    /// Seconds a cached response is served before it is rebuilt, even
+    /// without writes
+    #[arg(long, env = "CACHE_TTL", default_value_t = 300)]
+    cache_ttl: u64,
+
+    /// max-age in seconds advertised to clients and CDNs in Cache-Control
+    #[arg(long, env = "CACHE_MAX_AGE", default_value_t = 60)]
+    cache_max_age: u64,
+
+    /// Bearer token of the /admin routes, which are disabled without one
+    #[arg(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// Hosts webhooks may deliver to, comma-separated; without it any host
+    /// resolving to public addresses only
+    #[arg(long, env = "WEBHOOK_ALLOWED_HOSTS", value_delimiter = ',')]
+    webhook_allowed_hosts: Vec<String>,
+
+    /// Directory of the backups; with serve, also turns on scheduled backups
+    #[arg(long, env = "BACKUP_DIR", global = true)]
+    backup_dir: Option<PathBuf>,
+
+    /// Seconds between scheduled backups
+    #[arg(long, env = "BACKUP_INTERVAL", default_value_t = 86_400, global = true)]
+    backup_interval: u64,
+
+    /// Backups kept in the backup directory, the oldest are deleted; 0 keeps all
+    #[arg(long, env = "BACKUP_KEEP", default_value_t = 7, global = true)]
+    backup_keep: usize,
+
+    /// Compact backups with VACUUM INTO instead of copying the pages as they are
+    #[arg(long, env = "BACKUP_VACUUM", global = true)]
+    backup_vacuum: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start the HTTP server (default)
+    Serve,
+    /// Check the dataset for orphans, malformed or duplicated codes and SQLite corruption
+    Doctor {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+        /// Also fail when only warnings were found
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Show the region with this code
+    Get { code: String },
+    /// List the regions directly below this code, or the provinces
+    Children { code: Option<String> },
+    /// Find regions whose name contains the given text, ignoring case
+    Search {
+        name: String,
+        /// Only search this level: province, regency, district or village
+        #[arg(long, value_parser = parse_level)]
+        level: Option<Level>,
+        /// Maximum number of rows
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Show the region with this code and its ancestors, province first
+    Path { code: String },
+    /// Count the live and deleted regions of every level
+    Stats,
+    /// Write the read routes as static JSON files, with a checksum manifest
+    GenerateStatic {
+        /// Output directory, created when missing
+        #[arg(long)]
+        out: PathBuf,
+        /// Write compact JSON instead of pretty-printed
+        #[arg(long)]
+        minify: bool,
+    },
+    /// Write a consistent copy of the database into --backup-dir
+    Backup,
+    /// Replace the database file with a backup, after checking its schema version; stop the server first
+    Restore { backup: PathBuf },
+    /// Browse and rename regions interactively
+    #[cfg(feature = "tui")]
+    Tui {
+        /// Only browse, without renaming
+        #[arg(long)]
+        read_only: bool,
+    },
+}
+
+// Where the data comes from: the file given with --database or
+// DATABASE_PATH, else the dataset embedded at build time, else the default
+// file
+enum Source {
+    File(String),
+    #[cfg(feature = "embedded")]
+    Embedded,
+}
+
+impl Source {
+    fn of(database: Option<String>) -> Source {
+        match database {
+            Some(path) => Source::File(path),
+            #[cfg(feature = "embedded")]
+            None => Source::Embedded,
+            #[cfg(not(feature = "embedded"))]
+            None => Source::File("data/wilayah_indonesia.db".to_string()),
+        }
+    }
+
+    // The database file, None for the embedded dataset
+    fn file(&self) -> Option<&str> {
+        match self {
+            Source::File(path) => Some(path),
+            #[cfg(feature = "embedded")]
+            Source::Embedded => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Source::File(path) => f.write_str(path),
+            #[cfg(feature = "embedded")]
+            Source::Embedded => f.write_str("the embedded dataset"),
+        }
+    }
+}
+
+// The database of every command but serve, which never creates the file.
+// Only commands that write migrate it; the others open it read-only.
+// Failures are reported with exit code 2.
+fn open(source: &Source, writable: bool) -> Result<db::Database, ExitCode> {
+    let opened = match source {
+        Source::File(path) if !Path::new(path).exists() => {
+            eprintln!("Database {} does not exist", path);
+            return Err(ExitCode::from(2));
+        }
+        Source::File(path) if writable => db::Database::new(path),
+        Source::File(path) => db::Database::open_read_only(path),
+        #[cfg(feature = "embedded")]
+        Source::Embedded => wilayah::embedded::database(),
+    };
+    opened.map_err(|e| {
+        eprintln!("Failed to open {}: {}", source, e);
+        ExitCode::from(2)
+    })
+}
+
+fn parse_level(value: &str) -> Result<Level, String> {
+    Level::parse(value).ok_or_else(|| format!("unknown level {}", value))
+}
 
 #[actix_rt::main]
-async fn main() -> std::io::Result<()> {
+async fn main() -> std::io::Result<ExitCode> {
     // Setup logging
     env_logger::init_from_env(Env::default().default_filter_or("info"));
 
-    // Ensure the data directory exists
-    std::fs::create_dir_all("data").expect("Failed to create data directory");
+    let cli = Cli::parse();
+    let source = Source::of(cli.database);
+    let schedule = cli.backup_dir.map(|dir| backup::Schedule {
+        dir,
+        interval: Duration::from_secs(cli.backup_interval.max(1)),
+        keep: cli.backup_keep,
+        vacuum: cli.backup_vacuum,
+    });
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            let cache = cache::ResponseCache::new(
+                cli.cache_capacity,
+                Duration::from_secs(cli.cache_ttl),
+                Duration::from_secs(cli.cache_max_age),
+            );
+            let admin = Admin {
+                token: auth::AdminToken::new(cli.admin_token),
+                webhooks: webhooks::UrlPolicy::new(cli.webhook_allowed_hosts),
+            };
+            serve(&source, cache, admin, schedule)
+                .await
+                .map(|()| ExitCode::SUCCESS)
+        }
+        Command::Doctor { json, strict } => Ok(doctor(&source, json, strict)),
+        Command::GenerateStatic { out, minify } => Ok(generate_static(&source, &out, minify)),
+        Command::Backup => Ok(write_backup(&source, schedule)),
+        Command::Restore { backup } => Ok(restore(&source, &backup)),
+        #[cfg(feature = "tui")]
+        Command::Tui { read_only } => Ok(tui(&source, read_only)),
+        command => Ok(run_query(&source, cli.format, command)),
+    }
+}
+
+// Access to the /admin routes and where webhooks created there may deliver
+struct Admin {
+    token: auth::AdminToken,
+    webhooks: webhooks::UrlPolicy,
+}
 
+async fn serve(
+    source: &Source,
+    cache: cache::ResponseCache,
+    admin: Admin,
+    schedule: Option<backup::Schedule>,
+) -> std::io::Result<()> {
     // Initialize database
-    let db = web::Data::new(
-        db::Database::new("data/wilayah_indonesia.db").expect("Failed to create database connection"),
-    );
+    let db = match source {
+        Source::File(database) => {
+            // Ensure the data directory exists
+            if let Some(dir) = Path::new(database).parent() {
+                std::fs::create_dir_all(dir).expect("Failed to create data directory");
+            }
+            db::Database::new(database).expect("Failed to create database connection")
+        }
+        #[cfg(feature = "embedded")]
+        Source::Embedded => {
+            info!(
+                "Serving the embedded dataset ({} KiB compressed) from memory, writes are lost on restart",
+                wilayah::embedded::compressed_len() / 1024
+            );
+            wilayah::embedded::database().expect("Failed to load the embedded dataset")
+        }
+    };
+    let db = web::Data::new(db);
+
+    // The per-level routes only need the repository, the rest the database
+    let repository: Arc<dyn WilayahRepository> = db.clone().into_inner();
+    let repository = web::Data::from(repository);
+    let cache = web::Data::new(cache);
+    let admin_token = web::Data::new(admin.token);
+    let url_policy = web::Data::new(admin.webhooks.clone());
+
+    // Deliver webhooks in the background
+    webhooks::spawn_worker(db.clone(), admin.webhooks);
+    if let Some(schedule) = schedule {
+        backup::spawn_scheduler(db.clone(), schedule);
+    }
 
     // Start HTTP server
     info!("Starting server at http://localhost:8080");
     HttpServer::new(move || {
         App::new()
             .app_data(db.clone())
-            .configure(routes::configure_province_routes)
-            .configure(routes::configure_regency_routes)
-            .configure(routes::configure_district_routes)
-            .configure(routes::configure_village_routes)
+            .app_data(repository.clone())
+            .app_data(cache.clone())
+            .app_data(admin_token.clone())
+            .app_data(url_policy.clone())
+            // Compress runs last, on the negotiated body
+            .wrap(from_fn(negotiate::negotiate))
+            .wrap(Compress::default())
+            .configure(wilayah::configure_all)
     })
     .bind("0.0.0.0:8080")?
     .run()
     .await
 }
+
+// Exit code 0 when the dataset is healthy, 1 when issues were found and 2
+// when the check itself could not run, so CI can gate on it
+fn doctor(source: &Source, json: bool, strict: bool) -> ExitCode {
+    let db = match open(source, false) {
+        Ok(db) => db,
+        Err(code) => return code,
+    };
+    let report = match db.quality_report() {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Failed to check {}: {}", source, e);
+            return ExitCode::from(2);
+        }
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("serializable report")
+        );
+    } else {
+        println!("integrity_check: {}", report.integrity_check.join("; "));
+        println!(
+            "foreign_key_check: {} violations",
+            report.foreign_key_check.len()
+        );
+        for violation in &report.foreign_key_check {
+            println!(
+                "error    foreign_key  {} rowid {:?} -> {}",
+                violation.table, violation.rowid, violation.parent
+            );
+        }
+        for issue in &report.issues {
+            let severity = match issue.severity {
+                models::Severity::Error => "error",
+                models::Severity::Warning => "warning",
+            };
+            let id = issue.id.map(|id| format!(" #{}", id)).unwrap_or_default();
+            let code = issue
+                .code
+                .as_deref()
+                .map(|c| format!(" ({})", c))
+                .unwrap_or_default();
+            println!(
+                "{:<8} {:<22} {}{}{}: {}",
+                severity,
+                issue.check,
+                issue.level.as_str(),
+                id,
+                code,
+                issue.detail
+            );
+        }
+        println!("{} errors, {} warnings", report.errors, report.warnings);
+    }
+
+    if !report.ok || (strict && report.warnings > 0) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+// Same exit codes as doctor: 1 when the code does not exist, 2 when the
+// database cannot be read
+fn run_query(source: &Source, format: Format, command: Command) -> ExitCode {
+    let db = match open(source, false) {
+        Ok(db) => db,
+        Err(code) => return code,
+    };
+
+    let mut out = std::io::stdout().lock();
+    let result = match &command {
+        Command::Get { code } => query::get(&db, code)
+            .map(|record| record.map(|record| query::write_row(&mut out, format, &record))),
+        Command::Children { code } => query::children(&db, code.as_deref())
+            .map(|records| records.map(|records| query::write_rows(&mut out, format, &records))),
+        Command::Search { name, level, limit } => query::search(&db, name, *level, *limit)
+            .map(|records| Some(query::write_rows(&mut out, format, &records))),
+        Command::Path { code } => query::path(&db, code)
+            .map(|records| records.map(|records| query::write_rows(&mut out, format, &records))),
+        Command::Stats => {
+            query::stats(&db).map(|counts| Some(query::write_rows(&mut out, format, &counts)))
+        }
+        _ => unreachable!("not a query command"),
+    };
+
+    match result {
+        Ok(Some(Ok(()))) => ExitCode::SUCCESS,
+        Ok(Some(Err(e))) => {
+            eprintln!("Failed to write the output: {}", e);
+            ExitCode::from(2)
+        }
+        Ok(None) => {
+            eprintln!("Region not found");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Failed to query {}: {}", source, e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn generate_static(source: &Source, out: &Path, minify: bool) -> ExitCode {
+    let db = match open(source, false) {
+        Ok(db) => db,
+        Err(code) => return code,
+    };
+    match static_api::generate(&db, out, minify) {
+        Ok(manifest) => {
+            println!(
+                "Wrote {} files to {} (revision {})",
+                manifest.files.len(),
+                out.display(),
+                manifest.revision
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to generate {}: {}", out.display(), e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn write_backup(source: &Source, schedule: Option<backup::Schedule>) -> ExitCode {
+    let Some(schedule) = schedule else {
+        eprintln!("Set --backup-dir or BACKUP_DIR");
+        return ExitCode::from(2);
+    };
+    let db = match open(source, false) {
+        Ok(db) => db,
+        Err(code) => return code,
+    };
+    match backup::write_backup(&db, &schedule.dir, schedule.vacuum, schedule.keep) {
+        Ok(path) => {
+            println!("Wrote {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to back up {}: {}", source, e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+// Exit code 1 when the backup was refused, 2 when the restore failed
+fn restore(source: &Source, file: &Path) -> ExitCode {
+    let Some(database) = source.file() else {
+        eprintln!("Set --database or DATABASE_PATH to the file to restore");
+        return ExitCode::from(2);
+    };
+    match backup::restore(file, Path::new(database)) {
+        Ok(info) => {
+            println!(
+                "Restored {} from {} (schema version {}, revision {})",
+                database,
+                file.display(),
+                info.schema_version,
+                info.revision
+            );
+            ExitCode::SUCCESS
+        }
+        Err(backup::BackupError::Invalid(reason)) => {
+            eprintln!("Refusing to restore {}: {}", file.display(), reason);
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Failed to restore {}: {}", file.display(), e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+fn tui(source: &Source, read_only: bool) -> ExitCode {
+    let db = match open(source, !read_only) {
+        Ok(db) => db,
+        Err(code) => return code,
+    };
+    match wilayah::tui::run(&db, read_only) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Failed to browse {}: {}", source, e);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,818 @@
+// In-memory backend of WilayahRepository, loaded from a JSON or CSV snapshot.
+// Nothing is persisted and there is no change log, so only the per-level
+// operations are available. Writes follow the rules of the SQLite backend,
+// soft deletion included.
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::sync::RwLock;
+use thiserror::Error;
+
+use crate::db::{DatabaseError, Revision};
+use crate::models::{
+    BulkUpsertLine, DeleteOptions, DeletePlan, District, FieldError, FullAdminData, Level,
+    Province, Regency, UpsertResult, UpsertStatus, Village,
+};
+use crate::repository::WilayahRepository;
+use crate::rules::{self, RuleStore};
+use crate::validation::{self, Parent};
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid CSV: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("Line {0}: {1}")]
+    Line(usize, DatabaseError),
+}
+
+// The dataset as returned by the list endpoints, one array per level with
+// parents referenced by id
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Snapshot {
+    pub provinces: Vec<Province>,
+    pub regencies: Vec<Regency>,
+    pub districts: Vec<District>,
+    pub villages: Vec<Village>,
+}
+
+struct Row {
+    code: String,
+    name: String,
+    parent_id: Option<i64>,
+    version: i64,
+    // Soft-deleted, as deleted_at in SQLite: hidden from every read and kept
+    // until a hard delete purges it
+    deleted: bool,
+}
+
+struct State {
+    tables: BTreeMap<Level, BTreeMap<i64, Row>>,
+    // (level, code, id) and (level, parent id, id) of every row, deleted
+    // ones included, for the lookups by code and by parent
+    by_code: BTreeSet<(Level, String, i64)>,
+    by_parent: BTreeSet<(Level, i64, i64)>,
+    // Ids are never reused, as with AUTOINCREMENT
+    next_ids: BTreeMap<Level, i64>,
+    revision: Revision,
+}
+
+pub struct MemoryRepository {
+    state: RwLock<State>,
+}
+
+impl Default for MemoryRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        let tables = [
+            Level::Province,
+            Level::Regency,
+            Level::District,
+            Level::Village,
+        ]
+        .into_iter()
+        .map(|level| (level, BTreeMap::new()))
+        .collect();
+        MemoryRepository {
+            state: RwLock::new(State {
+                tables,
+                by_code: BTreeSet::new(),
+                by_parent: BTreeSet::new(),
+                next_ids: BTreeMap::new(),
+                revision: Revision::now(0),
+            }),
+        }
+    }
+
+    // Rows are taken as they are, like the rows of an existing database
+    // file. Rows without an id get the next free one.
+    pub fn from_snapshot(snapshot: Snapshot) -> Self {
+        let repo = Self::new();
+        {
+            let mut state = repo.state.write().unwrap();
+            for row in snapshot.provinces {
+                state.load(Level::Province, row.id, row.code, row.name, None);
+            }
+            for row in snapshot.regencies {
+                state.load(Level::Regency, row.id, row.code, row.name, row.province_id);
+            }
+            for row in snapshot.districts {
+                state.load(Level::District, row.id, row.code, row.name, row.regency_id);
+            }
+            for row in snapshot.villages {
+                state.load(Level::Village, row.id, row.code, row.name, row.district_id);
+            }
+        }
+        repo
+    }
+
+    pub fn from_json(reader: impl Read) -> Result<Self, SnapshotError> {
+        Ok(Self::from_snapshot(serde_json::from_reader(reader)?))
+    }
+
+    // CSV with `code` and `name` columns and a row per region of any level,
+    // validated like PUT /{level}/code/{code}. Parents are applied before
+    // their children whatever the order of the file.
+    pub fn from_csv(reader: impl Read) -> Result<Self, SnapshotError> {
+        let mut lines = Vec::new();
+        for (i, line) in csv::Reader::from_reader(reader)
+            .deserialize::<BulkUpsertLine>()
+            .enumerate()
+        {
+            // Line 1 is the header
+            lines.push((i + 2, line?));
+        }
+        lines.sort_by_key(|(_, line)| Level::from_code(&line.code));
+
+        let repo = Self::new();
+        {
+            let mut state = repo.state.write().unwrap();
+            for (number, line) in lines {
+                let Some(level) = Level::from_code(&line.code) else {
+                    let error = FieldError::new("code", "is not a region code");
+                    return Err(SnapshotError::Line(
+                        number,
+                        DatabaseError::Validation(vec![error]),
+                    ));
+                };
+                state
+                    .upsert(level, &line.code, &line.name)
+                    .map_err(|e| SnapshotError::Line(number, e))?;
+            }
+            state.revision = Revision::now(0);
+        }
+        Ok(repo)
+    }
+
+    fn get<T>(&self, level: Level, id: i64, convert: fn(i64, &Row) -> T) -> Option<T> {
+        let state = self.state.read().unwrap();
+        state.live(level, id).map(|row| convert(id, row))
+    }
+
+    fn list<T>(&self, level: Level, parent_id: i64, convert: fn(i64, &Row) -> T) -> Vec<T> {
+        let state = self.state.read().unwrap();
+        state
+            .children(level, &[parent_id], false)
+            .map(|(id, row)| convert(id, row))
+            .collect()
+    }
+}
+
+impl State {
+    fn table(&self, level: Level) -> &BTreeMap<i64, Row> {
+        &self.tables[&level]
+    }
+
+    // A row that is not soft-deleted
+    fn live(&self, level: Level, id: i64) -> Option<&Row> {
+        self.table(level).get(&id).filter(|row| !row.deleted)
+    }
+
+    // Insert or replace a row, keeping the indexes in step
+    fn put(&mut self, level: Level, id: i64, row: Row) {
+        self.remove(level, id);
+        self.by_code.insert((level, row.code.clone(), id));
+        if let Some(parent_id) = row.parent_id {
+            self.by_parent.insert((level, parent_id, id));
+        }
+        self.tables
+            .get_mut(&level)
+            .expect("table per level")
+            .insert(id, row);
+    }
+
+    fn remove(&mut self, level: Level, id: i64) {
+        let Some(row) = self
+            .tables
+            .get_mut(&level)
+            .and_then(|table| table.remove(&id))
+        else {
+            return;
+        };
+        self.by_code.remove(&(level, row.code, id));
+        if let Some(parent_id) = row.parent_id {
+            self.by_parent.remove(&(level, parent_id, id));
+        }
+    }
+
+    fn next_id(&mut self, level: Level) -> i64 {
+        let next = self.next_ids.entry(level).or_insert(1);
+        *next += 1;
+        *next - 1
+    }
+
+    // Every successful write moves the revision forward
+    fn touch(&mut self) {
+        self.revision = Revision::now(self.revision.seq + 1);
+    }
+
+    fn load(
+        &mut self,
+        level: Level,
+        id: Option<i64>,
+        code: String,
+        name: String,
+        parent_id: Option<i64>,
+    ) {
+        let id = id.unwrap_or_else(|| self.next_id(level));
+        let next = self.next_ids.entry(level).or_insert(1);
+        *next = (*next).max(id + 1);
+        self.put(
+            level,
+            id,
+            Row {
+                code,
+                name,
+                parent_id,
+                version: 1,
+                deleted: false,
+            },
+        );
+    }
+
+    // Lowest live id with this code, as ORDER BY id LIMIT 1
+    fn find_id(&self, level: Level, code: &str) -> Option<i64> {
+        self.by_code
+            .range((level, code.to_string(), i64::MIN)..=(level, code.to_string(), i64::MAX))
+            .map(|(_, _, id)| *id)
+            .find(|&id| self.live(level, id).is_some())
+    }
+
+    // Rows of `level` under any of `parent_ids`, by parent and then id; the
+    // soft-deleted ones only with `include_deleted`
+    fn children<'a>(
+        &'a self,
+        level: Level,
+        parent_ids: &'a [i64],
+        include_deleted: bool,
+    ) -> impl Iterator<Item = (i64, &'a Row)> + 'a {
+        parent_ids.iter().flat_map(move |&parent_id| {
+            self.by_parent
+                .range((level, parent_id, i64::MIN)..=(level, parent_id, i64::MAX))
+                .map(move |&(_, _, id)| (id, &self.table(level)[&id]))
+                .filter(move |(_, row)| include_deleted || !row.deleted)
+        })
+    }
+
+    fn validate(
+        &self,
+        level: Level,
+        code: &str,
+        name: &str,
+        parent_id: Option<i64>,
+    ) -> Result<(), DatabaseError> {
+        let parent = level.parent().map(|parent_level| Parent {
+            field: level.parent_column().expect("level with a parent"),
+            id: parent_id,
+            code: parent_id
+                .and_then(|id| self.live(parent_level, id))
+                .map(|row| row.code.as_str()),
+        });
+        validation::validate(level, code, name, parent)
+    }
+
+    fn create(
+        &mut self,
+        level: Level,
+        code: &str,
+        name: &str,
+        parent_id: Option<i64>,
+    ) -> Result<i64, DatabaseError> {
+        self.validate(level, code, name, parent_id)?;
+        let id = self.next_id(level);
+        self.put(
+            level,
+            id,
+            Row {
+                code: code.to_string(),
+                name: name.to_string(),
+                parent_id,
+                version: 1,
+                deleted: false,
+            },
+        );
+        self.touch();
+        Ok(id)
+    }
+
+    fn update(
+        &mut self,
+        level: Level,
+        id: i64,
+        code: &str,
+        name: &str,
+        parent_id: Option<i64>,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
+        let version = rules::check_version(self, level, id, if_match)?;
+        rules::check_code_change(self, level, id, code)?;
+        self.validate(level, code, name, parent_id)?;
+
+        self.put(
+            level,
+            id,
+            Row {
+                code: code.to_string(),
+                name: name.to_string(),
+                parent_id,
+                version: version + 1,
+                deleted: false,
+            },
+        );
+        self.touch();
+        Ok(version + 1)
+    }
+
+    // Soft delete (or with `hard`, remove) the rows of rules::delete_plan
+    fn delete(
+        &mut self,
+        level: Level,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        let plan = rules::delete_plan(self, level, id, options, if_match)?;
+        if options.dry_run {
+            return Ok(plan);
+        }
+
+        for row in &plan.rows {
+            if options.hard {
+                self.remove(row.level, row.id);
+            } else if let Some(deleted) = self
+                .tables
+                .get_mut(&row.level)
+                .and_then(|table| table.get_mut(&row.id))
+            {
+                deleted.deleted = true;
+                deleted.version += 1;
+            }
+        }
+        self.touch();
+        Ok(plan)
+    }
+
+    fn upsert(
+        &mut self,
+        level: Level,
+        code: &str,
+        name: &str,
+    ) -> Result<UpsertResult, DatabaseError> {
+        let parent_id = rules::upsert_parent(self, level, code, name)?;
+
+        let (id, status, version) = match self.find_id(level, code) {
+            Some(id) => {
+                let row = &self.table(level)[&id];
+                if row.name == name && row.parent_id == parent_id {
+                    (id, UpsertStatus::Unchanged, row.version)
+                } else {
+                    let version = row.version + 1;
+                    let row = Row {
+                        code: code.to_string(),
+                        name: name.to_string(),
+                        parent_id,
+                        version,
+                        deleted: false,
+                    };
+                    self.put(level, id, row);
+                    (id, UpsertStatus::Updated, version)
+                }
+            }
+            None => (
+                self.create(level, code, name, parent_id)?,
+                UpsertStatus::Created,
+                1,
+            ),
+        };
+        if status == UpsertStatus::Updated {
+            self.touch();
+        }
+
+        Ok(UpsertResult {
+            level,
+            id,
+            code: code.to_string(),
+            status,
+            version,
+        })
+    }
+
+    fn full_admin_data(&self, village_id: i64) -> Option<FullAdminData> {
+        let village = self.live(Level::Village, village_id)?;
+        let district = self.live(Level::District, village.parent_id?)?;
+        let regency = self.live(Level::Regency, district.parent_id?)?;
+        let province = self.live(Level::Province, regency.parent_id?)?;
+        Some(FullAdminData {
+            province_code: province.code.clone(),
+            province_name: province.name.clone(),
+            city_code: regency.code.clone(),
+            city_name: regency.name.clone(),
+            region_code: district.code.clone(),
+            region_name: district.name.clone(),
+            village_code: village.code.clone(),
+            village_name: village.name.clone(),
+        })
+    }
+}
+
+impl RuleStore for State {
+    fn live_row(&self, level: Level, id: i64) -> Result<Option<(i64, String)>, DatabaseError> {
+        Ok(self
+            .live(level, id)
+            .map(|row| (row.version, row.code.clone())))
+    }
+
+    fn find_id(&self, level: Level, code: &str) -> Result<Option<i64>, DatabaseError> {
+        Ok(State::find_id(self, level, code))
+    }
+
+    fn child_rows(
+        &self,
+        level: Level,
+        parent_ids: &[i64],
+        include_deleted: bool,
+    ) -> Result<Vec<(i64, String, bool)>, DatabaseError> {
+        Ok(self
+            .children(level, parent_ids, include_deleted)
+            .map(|(id, row)| (id, row.code.clone(), row.deleted))
+            .collect())
+    }
+}
+
+fn province(id: i64, row: &Row) -> Province {
+    Province {
+        id: Some(id),
+        version: Some(row.version),
+        code: row.code.clone(),
+        name: row.name.clone(),
+    }
+}
+
+fn regency(id: i64, row: &Row) -> Regency {
+    Regency {
+        id: Some(id),
+        version: Some(row.version),
+        code: row.code.clone(),
+        name: row.name.clone(),
+        province_id: row.parent_id,
+    }
+}
+
+fn district(id: i64, row: &Row) -> District {
+    District {
+        id: Some(id),
+        version: Some(row.version),
+        code: row.code.clone(),
+        name: row.name.clone(),
+        regency_id: row.parent_id,
+    }
+}
+
+fn village(id: i64, row: &Row) -> Village {
+    Village {
+        id: Some(id),
+        version: Some(row.version),
+        code: row.code.clone(),
+        name: row.name.clone(),
+        district_id: row.parent_id,
+    }
+}
+
+impl WilayahRepository for MemoryRepository {
+    fn revision(&self) -> Revision {
+        self.state.read().unwrap().revision
+    }
+
+    fn find_id_by_code(&self, level: Level, code: &str) -> Result<Option<i64>, DatabaseError> {
+        Ok(self.state.read().unwrap().find_id(level, code))
+    }
+
+    fn upsert_by_code(
+        &self,
+        level: Level,
+        code: &str,
+        name: &str,
+    ) -> Result<UpsertResult, DatabaseError> {
+        self.state.write().unwrap().upsert(level, code, name)
+    }
+
+    fn get_all_provinces(&self) -> Result<Vec<Province>, DatabaseError> {
+        let state = self.state.read().unwrap();
+        Ok(state
+            .table(Level::Province)
+            .iter()
+            .filter(|(_, row)| !row.deleted)
+            .map(|(id, row)| province(*id, row))
+            .collect())
+    }
+
+    fn get_one_province(&self, province_id: i64) -> Result<Option<Province>, DatabaseError> {
+        Ok(self.get(Level::Province, province_id, province))
+    }
+
+    fn create_province(&self, province: &Province) -> Result<i64, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.create(Level::Province, &province.code, &province.name, None)
+    }
+
+    fn update_province(
+        &self,
+        id: i64,
+        province: &Province,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.update(
+            Level::Province,
+            id,
+            &province.code,
+            &province.name,
+            None,
+            if_match,
+        )
+    }
+
+    fn delete_province(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.delete(Level::Province, id, options, if_match)
+    }
+
+    fn get_regencies_by_province(&self, province_id: i64) -> Result<Vec<Regency>, DatabaseError> {
+        Ok(self.list(Level::Regency, province_id, regency))
+    }
+
+    fn get_one_regency(&self, regency_id: i64) -> Result<Option<Regency>, DatabaseError> {
+        Ok(self.get(Level::Regency, regency_id, regency))
+    }
+
+    fn create_regency(&self, regency: &Regency) -> Result<i64, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.create(
+            Level::Regency,
+            &regency.code,
+            &regency.name,
+            regency.province_id,
+        )
+    }
+
+    fn update_regency(
+        &self,
+        id: i64,
+        regency: &Regency,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.update(
+            Level::Regency,
+            id,
+            &regency.code,
+            &regency.name,
+            regency.province_id,
+            if_match,
+        )
+    }
+
+    fn delete_regency(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.delete(Level::Regency, id, options, if_match)
+    }
+
+    fn get_districts_by_regency(&self, regency_id: i64) -> Result<Vec<District>, DatabaseError> {
+        Ok(self.list(Level::District, regency_id, district))
+    }
+
+    fn get_one_district(&self, district_id: i64) -> Result<Option<District>, DatabaseError> {
+        Ok(self.get(Level::District, district_id, district))
+    }
+
+    fn create_district(&self, district: &District) -> Result<i64, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.create(
+            Level::District,
+            &district.code,
+            &district.name,
+            district.regency_id,
+        )
+    }
+
+    fn update_district(
+        &self,
+        id: i64,
+        district: &District,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.update(
+            Level::District,
+            id,
+            &district.code,
+            &district.name,
+            district.regency_id,
+            if_match,
+        )
+    }
+
+    fn delete_district(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.delete(Level::District, id, options, if_match)
+    }
+
+    fn get_villages_by_district(&self, district_id: i64) -> Result<Vec<Village>, DatabaseError> {
+        Ok(self.list(Level::Village, district_id, village))
+    }
+
+    fn get_one_village(&self, village_id: i64) -> Result<Option<Village>, DatabaseError> {
+        Ok(self.get(Level::Village, village_id, village))
+    }
+
+    fn create_village(&self, village: &Village) -> Result<i64, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.create(
+            Level::Village,
+            &village.code,
+            &village.name,
+            village.district_id,
+        )
+    }
+
+    fn update_village(
+        &self,
+        id: i64,
+        village: &Village,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.update(
+            Level::Village,
+            id,
+            &village.code,
+            &village.name,
+            village.district_id,
+            if_match,
+        )
+    }
+
+    fn delete_village(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        let mut state = self.state.write().unwrap();
+        state.delete(Level::Village, id, options, if_match)
+    }
+
+    fn get_full_admin_data(&self, village_id: i64) -> Result<Option<FullAdminData>, DatabaseError> {
+        Ok(self.state.read().unwrap().full_admin_data(village_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regency_row(code: &str, province_id: i64) -> Regency {
+        Regency {
+            id: None,
+            version: None,
+            code: code.to_string(),
+            name: "KOTA BANDUNG".to_string(),
+            province_id: Some(province_id),
+        }
+    }
+
+    #[test]
+    fn writes_follow_the_sqlite_rules() {
+        let repo = MemoryRepository::new();
+        let province_id = repo
+            .create_province(&Province {
+                id: None,
+                version: None,
+                code: "32".to_string(),
+                name: "JAWA BARAT".to_string(),
+            })
+            .unwrap();
+
+        let wrong_prefix = repo.create_regency(&regency_row("33.73", province_id));
+        assert!(matches!(wrong_prefix, Err(DatabaseError::Validation(_))));
+        let regency_id = repo
+            .create_regency(&regency_row("32.73", province_id))
+            .unwrap();
+
+        let stale = repo.update_regency(regency_id, &regency_row("32.73", province_id), Some(&[7]));
+        assert!(matches!(stale, Err(DatabaseError::PreconditionFailed(1))));
+        let version = repo
+            .update_regency(regency_id, &regency_row("32.73", province_id), Some(&[1]))
+            .unwrap();
+        assert_eq!(version, 2);
+
+        let before = repo.revision().seq;
+        let refused = repo.delete_province(province_id, &DeleteOptions::default(), None);
+        assert!(matches!(refused, Err(DatabaseError::HasChildren(_))));
+        let options = DeleteOptions {
+            cascade: true,
+            ..Default::default()
+        };
+        let plan = repo.delete_province(province_id, &options, None).unwrap();
+        assert_eq!(plan.counts["regencies"], 1);
+        assert!(repo.get_one_regency(regency_id).unwrap().is_none());
+        assert!(repo.revision().seq > before);
+    }
+
+    #[test]
+    fn loads_csv_children_first() {
+        let csv = "code,name\n\
+            32.04.08.2002,Bojongsoang\n\
+            32.04.08,Bojongsoang\n\
+            32.04,KAB. BANDUNG\n\
+            32,JAWA BARAT\n";
+        let repo = MemoryRepository::from_csv(csv.as_bytes()).unwrap();
+        let village_id = repo
+            .find_id_by_code(Level::Village, "32.04.08.2002")
+            .unwrap()
+            .unwrap();
+        let data = repo.get_full_admin_data(village_id).unwrap().unwrap();
+        assert_eq!(data.province_name, "JAWA BARAT");
+        assert_eq!(data.city_code, "32.04");
+
+        let unchanged = repo
+            .upsert_by_code(Level::Regency, "32.04", "KAB. BANDUNG")
+            .unwrap();
+        assert_eq!(unchanged.status, UpsertStatus::Unchanged);
+        assert_eq!(repo.revision().seq, 0);
+
+        let orphan = MemoryRepository::from_csv("code,name\n32.04,KAB. BANDUNG\n".as_bytes());
+        assert!(matches!(orphan, Err(SnapshotError::Line(2, _))));
+    }
+
+    #[test]
+    fn deletes_softly_unless_hard() {
+        let csv = "code,name\n\
+            32,JAWA BARAT\n\
+            32.04,KAB. BANDUNG\n\
+            32.73,KOTA BANDUNG\n";
+        let repo = MemoryRepository::from_csv(csv.as_bytes()).unwrap();
+        let province_id = repo
+            .find_id_by_code(Level::Province, "32")
+            .unwrap()
+            .unwrap();
+        let regency_id = repo
+            .find_id_by_code(Level::Regency, "32.73")
+            .unwrap()
+            .unwrap();
+
+        let plan = repo
+            .delete_regency(regency_id, &DeleteOptions::default(), None)
+            .unwrap();
+        assert!(!plan.hard);
+        assert!(repo.get_one_regency(regency_id).unwrap().is_none());
+        assert_eq!(
+            repo.get_regencies_by_province(province_id).unwrap().len(),
+            1
+        );
+        assert_eq!(repo.find_id_by_code(Level::Regency, "32.73").unwrap(), None);
+        // Gone for writes too, as in SQLite
+        let again = repo.delete_regency(regency_id, &DeleteOptions::default(), None);
+        assert!(matches!(again, Err(DatabaseError::NotFound)));
+        let recreated = repo
+            .upsert_by_code(Level::Regency, "32.73", "KOTA BANDUNG")
+            .unwrap();
+        assert_eq!(recreated.status, UpsertStatus::Created);
+        assert_ne!(recreated.id, regency_id);
+
+        // A hard delete purges the soft-deleted descendants as well
+        let options = DeleteOptions {
+            cascade: true,
+            hard: true,
+            ..Default::default()
+        };
+        let plan = repo.delete_province(province_id, &options, None).unwrap();
+        assert!(plan.hard);
+        assert_eq!(plan.counts["regencies"], 3);
+        let state = repo.state.read().unwrap();
+        assert!(state.tables.values().all(BTreeMap::is_empty));
+        assert!(state.by_code.is_empty() && state.by_parent.is_empty());
+    }
+}
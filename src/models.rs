@@ -1,37 +1,63 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct Province {
     pub id: Option<i64>,
+    // Bumped on every write, sent as the ETag. Ignored in request bodies.
+    #[serde(default)]
+    #[schema(read_only, example = 1)]
+    pub version: Option<i64>,
+    #[schema(example = "32")]
     pub code: String,
+    #[schema(example = "JAWA BARAT")]
     pub name: String,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct Regency {
     pub id: Option<i64>,
+    // Bumped on every write, sent as the ETag. Ignored in request bodies.
+    #[serde(default)]
+    #[schema(read_only, example = 1)]
+    pub version: Option<i64>,
+    #[schema(example = "32.04")]
     pub code: String,
+    #[schema(example = "KAB. BANDUNG")]
     pub name: String,
     pub province_id: Option<i64>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct District {
     pub id: Option<i64>,
+    // Bumped on every write, sent as the ETag. Ignored in request bodies.
+    #[serde(default)]
+    #[schema(read_only, example = 1)]
+    pub version: Option<i64>,
+    #[schema(example = "32.04.08")]
     pub code: String,
+    #[schema(example = "Bojongsoang")]
     pub name: String,
     pub regency_id: Option<i64>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct Village {
     pub id: Option<i64>,
+    // Bumped on every write, sent as the ETag. Ignored in request bodies.
+    #[serde(default)]
+    #[schema(read_only, example = 1)]
+    pub version: Option<i64>,
+    #[schema(example = "32.04.08.2002")]
     pub code: String,
+    #[schema(example = "Bojongsoang")]
     pub name: String,
     pub district_id: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct FullAdminData {
     pub province_code: String,
     pub province_name: String,
@@ -42,3 +68,693 @@ pub struct FullAdminData {
     pub village_code: String,
     pub village_name: String,
 }
+
+// Response body returned by the create_* handlers
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct CreatedId {
+    pub id: i64,
+}
+
+// Error body returned by every handler on failure
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct ErrorResponse {
+    #[schema(example = "Province not found")]
+    pub error: String,
+}
+
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Province,
+    Regency,
+    District,
+    Village,
+}
+
+impl Level {
+    // Kemendagri codes have one dot-separated segment per level:
+    // 32 / 32.04 / 32.04.08 / 32.04.08.2002. Segments must be digits.
+    pub fn from_code(code: &str) -> Option<Level> {
+        let numeric = code
+            .split('.')
+            .all(|segment| !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()));
+        if !numeric {
+            return None;
+        }
+        match code.split('.').count() {
+            1 => Some(Level::Province),
+            2 => Some(Level::Regency),
+            3 => Some(Level::District),
+            4 => Some(Level::Village),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Province => "province",
+            Level::Regency => "regency",
+            Level::District => "district",
+            Level::Village => "village",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Level> {
+        match value {
+            "province" => Some(Level::Province),
+            "regency" => Some(Level::Regency),
+            "district" => Some(Level::District),
+            "village" => Some(Level::Village),
+            _ => None,
+        }
+    }
+
+    pub fn parent(&self) -> Option<Level> {
+        match self {
+            Level::Province => None,
+            Level::Regency => Some(Level::Province),
+            Level::District => Some(Level::Regency),
+            Level::Village => Some(Level::District),
+        }
+    }
+
+    pub fn child(&self) -> Option<Level> {
+        match self {
+            Level::Province => Some(Level::Regency),
+            Level::Regency => Some(Level::District),
+            Level::District => Some(Level::Village),
+            Level::Village => None,
+        }
+    }
+
+    // Column of this level's table referencing the parent row
+    pub fn parent_column(&self) -> Option<&'static str> {
+        match self {
+            Level::Province => None,
+            Level::Regency => Some("province_id"),
+            Level::District => Some("regency_id"),
+            Level::Village => Some("district_id"),
+        }
+    }
+
+    pub fn table(&self) -> &'static str {
+        match self {
+            Level::Province => "provinces",
+            Level::Regency => "regencies",
+            Level::District => "districts",
+            Level::Village => "villages",
+        }
+    }
+}
+
+// Like FullAdminData, but the levels below the resolved one are absent
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct AdminHierarchy {
+    pub level: Level,
+    pub province_code: String,
+    pub province_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub village_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub village_name: Option<String>,
+}
+
+impl AdminHierarchy {
+    // Code of the resolved entity itself, i.e. of the deepest level present
+    pub fn code(&self) -> &str {
+        match self.level {
+            Level::Province => &self.province_code,
+            Level::Regency => self.city_code.as_deref().unwrap_or_default(),
+            Level::District => self.region_code.as_deref().unwrap_or_default(),
+            Level::Village => self.village_code.as_deref().unwrap_or_default(),
+        }
+    }
+
+    // Name of the resolved entity itself, i.e. of the deepest level present
+    pub fn name(&self) -> &str {
+        match self.level {
+            Level::Province => &self.province_name,
+            Level::Regency => self.city_name.as_deref().unwrap_or_default(),
+            Level::District => self.region_name.as_deref().unwrap_or_default(),
+            Level::Village => self.village_name.as_deref().unwrap_or_default(),
+        }
+    }
+}
+
+// A batch item is either a Kemendagri code (its level is implied by the
+// number of segments) or a row id together with the level it belongs to
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[serde(untagged)]
+pub enum BatchItem {
+    #[schema(example = "32.04.08.2002")]
+    Code(String),
+    Id {
+        level: Level,
+        id: i64,
+    },
+}
+
+impl BatchItem {
+    // Key under which the item's result is reported
+    pub fn key(&self) -> String {
+        match self {
+            BatchItem::Code(code) => code.clone(),
+            BatchItem::Id { level, id } => format!("{}:{}", level.as_str(), id),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct BatchResolveRequest {
+    pub items: Vec<BatchItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BatchResolution {
+    Found(AdminHierarchy),
+    NotFound,
+    Invalid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct BatchResolveResponse {
+    // Keyed by the input code, or by "<level>:<id>" for id items
+    pub results: BTreeMap<String, BatchResolution>,
+}
+
+// Multipart form accepted by /enrich
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct EnrichUpload {
+    #[schema(format = Binary, value_type = String)]
+    pub file: Vec<u8>,
+    // Header of the key column, overrides the `column` query parameter
+    pub column: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ParseAddressRequest {
+    #[schema(
+        example = "Jl. Raya Bojongsoang No. 12, Ds. Bojongsoang, Kec. Bojongsoang, Kab. Bandung, Jawa Barat 40288"
+    )]
+    pub address: String,
+}
+
+// One administrative level of a parsed address
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct AddressComponent {
+    pub code: String,
+    pub name: String,
+    // Text of the address this level was recognized from; absent when the
+    // level was only inferred from a lower level
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct ParsedAddress {
+    pub street: Option<String>,
+    pub rt: Option<String>,
+    pub rw: Option<String>,
+    pub postal_code: Option<String>,
+    pub province: Option<AddressComponent>,
+    pub regency: Option<AddressComponent>,
+    pub district: Option<AddressComponent>,
+    pub village: Option<AddressComponent>,
+    // Segments that could not be attributed to any component
+    pub unmatched: Vec<String>,
+    // 0.0 - 1.0; how well the recognized regions agree with each other
+    #[schema(example = 0.95)]
+    pub confidence: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressStyle {
+    // Everything on one comma-separated line
+    #[default]
+    SingleLine,
+    // Shipping label: street, village/district, regency, province
+    MultiLine,
+    // Uppercase, one administrative level per line as recommended by Pos Indonesia
+    PosIndonesia,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct FormatAddressRequest {
+    #[schema(example = "32.04.08.2002")]
+    pub village_code: String,
+    #[schema(example = "Jl. Raya Bojongsoang No. 12")]
+    pub street: Option<String>,
+    pub rt: Option<String>,
+    pub rw: Option<String>,
+    #[schema(example = "40288")]
+    pub postal_code: Option<String>,
+    #[serde(default)]
+    pub style: AddressStyle,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct FormattedAddress {
+    pub style: AddressStyle,
+    // `lines` joined with the separator of the style
+    pub formatted: String,
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct FieldError {
+    #[schema(example = "province_id")]
+    pub field: String,
+    #[schema(example = "no row with id 99 exists")]
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+// Body of a 422 response
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct ValidationErrorResponse {
+    #[schema(example = "Validation failed")]
+    pub error: String,
+    pub fields: Vec<FieldError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct QualityIssue {
+    // orphan, code_prefix_mismatch, duplicate_code, duplicate_sibling_name,
+    // malformed_code or empty_level
+    #[schema(example = "orphan")]
+    pub check: String,
+    pub severity: Severity,
+    pub level: Level,
+    pub id: Option<i64>,
+    pub code: Option<String>,
+    #[schema(example = "district_id 991 does not exist")]
+    pub detail: String,
+}
+
+// One row of `PRAGMA foreign_key_check`
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    pub parent: String,
+    pub fkid: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct QualityReport {
+    // False when any error-severity issue was found
+    pub ok: bool,
+    pub errors: usize,
+    pub warnings: usize,
+    // Number of issues per check
+    pub counts: BTreeMap<String, usize>,
+    // Output of `PRAGMA integrity_check`, ["ok"] when healthy
+    pub integrity_check: Vec<String>,
+    pub foreign_key_check: Vec<ForeignKeyViolation>,
+    pub issues: Vec<QualityIssue>,
+}
+
+// What a backup file holds, checked before it is restored
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BackupInfo {
+    // PRAGMA user_version; older versions are migrated when opened
+    pub schema_version: i64,
+    // Seq of the latest change in the backup, 0 without change log
+    pub revision: i64,
+}
+
+// Query of GET /admin/backup
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default)]
+pub struct BackupOptions {
+    /// Compact the copy with VACUUM INTO instead of copying the pages as they are
+    pub vacuum: bool,
+}
+
+// Rows of one level, for `wilayah-service stats`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LevelCount {
+    pub level: Level,
+    pub live: i64,
+    pub deleted: i64,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default)]
+pub struct DeleteOptions {
+    /// Also delete every descendant instead of refusing with 409
+    pub cascade: bool,
+    /// Only report what would be deleted
+    pub dry_run: bool,
+    /// Remove the rows instead of setting deleted_at
+    pub hard: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct DeletedRow {
+    pub level: Level,
+    pub id: i64,
+    #[schema(example = "32.04")]
+    pub code: String,
+}
+
+// Rows removed (or, for a dry run, to be removed) by a delete
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct DeletePlan {
+    pub dry_run: bool,
+    pub hard: bool,
+    // Number of rows per table, the target included
+    pub counts: BTreeMap<String, usize>,
+    pub rows: Vec<DeletedRow>,
+}
+
+// Body of a 409 response to a delete of a row that still has children
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct HasChildrenResponse {
+    #[schema(example = "Regency still has children, retry with cascade=true")]
+    pub error: String,
+    // Number of live descendants per table
+    pub children: BTreeMap<String, usize>,
+}
+
+// Body of PUT /{level}/code/{code}. The parent is implied by the code.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct UpsertRequest {
+    #[schema(example = "KAB. BANDUNG")]
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UpsertStatus {
+    Created,
+    Updated,
+    Unchanged,
+    // Only in bulk results, for lines that could not be applied
+    Invalid,
+}
+
+impl UpsertStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpsertStatus::Created => "created",
+            UpsertStatus::Updated => "updated",
+            UpsertStatus::Unchanged => "unchanged",
+            UpsertStatus::Invalid => "invalid",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct UpsertResult {
+    pub level: Level,
+    pub id: i64,
+    #[schema(example = "32.04")]
+    pub code: String,
+    pub status: UpsertStatus,
+    pub version: i64,
+}
+
+// One line of the NDJSON body of POST /batch/upsert
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct BulkUpsertLine {
+    #[schema(example = "32.04.08")]
+    pub code: String,
+    #[schema(example = "Bojongsoang")]
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct BulkUpsertResult {
+    // 1-based line number in the request body
+    pub line: usize,
+    pub code: Option<String>,
+    pub status: UpsertStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct BulkUpsertResponse {
+    // Number of lines per status
+    pub counts: BTreeMap<String, usize>,
+    pub results: Vec<BulkUpsertResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl ChangeOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeOp::Insert => "insert",
+            ChangeOp::Update => "update",
+            ChangeOp::Delete => "delete",
+        }
+    }
+
+    // Suffix of event names, e.g. regency.updated
+    pub fn event_suffix(&self) -> &'static str {
+        match self {
+            ChangeOp::Insert => "created",
+            ChangeOp::Update => "updated",
+            ChangeOp::Delete => "deleted",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ChangeOp> {
+        match value {
+            "insert" => Some(ChangeOp::Insert),
+            "update" => Some(ChangeOp::Update),
+            "delete" => Some(ChangeOp::Delete),
+            _ => None,
+        }
+    }
+}
+
+// A row of any level; parent_id is the province_id, regency_id or
+// district_id column, null for provinces
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct RegionRow {
+    pub id: i64,
+    #[schema(example = "32.04")]
+    pub code: String,
+    #[schema(example = "KAB. BANDUNG")]
+    pub name: String,
+    pub parent_id: Option<i64>,
+    pub version: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Change {
+    pub seq: i64,
+    pub op: ChangeOp,
+    pub level: Level,
+    pub id: i64,
+    #[schema(example = "32.04")]
+    pub code: String,
+    #[schema(example = "2024-01-31T08:15:00.000Z")]
+    pub changed_at: String,
+    // Current state of the row for inserts and updates. Absent for deletes
+    // (tombstones) and for rows that were purged since.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<RegionRow>,
+}
+
+impl Change {
+    // Event name used by webhooks and /events, e.g. regency.updated
+    pub fn event(&self) -> String {
+        format!("{}.{}", self.level.as_str(), self.op.event_suffix())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ChangeFeed {
+    pub changes: Vec<Change>,
+    // Token to pass as `since` for the next request
+    #[schema(example = "1024")]
+    pub next: String,
+    // Token of the latest change; a client starting from a full download
+    // takes it before downloading
+    #[schema(example = "2048")]
+    pub head: String,
+    pub has_more: bool,
+}
+
+// Body of POST /admin/webhooks
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CreateWebhook {
+    #[schema(example = "https://cache.internal/hooks/wilayah")]
+    pub url: String,
+    // Key of the HMAC-SHA256 signature sent with every delivery
+    pub secret: String,
+    #[serde(flatten)]
+    pub filter: ChangeFilter,
+}
+
+// A subscription as returned by the admin API; the secret is never echoed
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    #[serde(flatten)]
+    pub filter: ChangeFilter,
+    pub created_at: String,
+    // Last change already queued for delivery
+    pub last_seq: i64,
+}
+
+// Selects changes for webhooks and the /events stream
+#[derive(Debug, Default, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ChangeFilter {
+    // Only changes of these levels, all levels when empty
+    #[serde(default)]
+    pub levels: Vec<Level>,
+    // Only changes of codes starting with this prefix
+    #[schema(example = "32.")]
+    pub code_prefix: Option<String>,
+}
+
+impl ChangeFilter {
+    pub fn matches(&self, change: &Change) -> bool {
+        (self.levels.is_empty() || self.levels.contains(&change.level))
+            && self
+                .code_prefix
+                .as_deref()
+                .is_none_or(|prefix| change.code.starts_with(prefix))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    // Waiting for its first attempt or for a retry
+    Pending,
+    Delivered,
+    // Gave up after the last retry
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<DeliveryStatus> {
+        match value {
+            "pending" => Some(DeliveryStatus::Pending),
+            "delivered" => Some(DeliveryStatus::Delivered),
+            "failed" => Some(DeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+// One entry of the delivery log
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub seq: i64,
+    #[schema(example = "regency.updated")]
+    pub event: String,
+    pub status: DeliveryStatus,
+    pub attempts: i64,
+    pub next_attempt_at: Option<String>,
+    // HTTP status of the last attempt, absent when the request failed
+    pub response_status: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+}
+
+// Body POSTed to a webhook
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct WebhookEvent {
+    pub delivery_id: i64,
+    #[schema(example = "regency.updated")]
+    pub event: String,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+// A delivery due for an attempt, with what is needed to send it
+#[derive(Debug, Clone)]
+pub struct PendingDelivery {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    pub attempts: i64,
+    pub event: WebhookEvent,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+    pub ttl_secs: u64,
+    pub max_age_secs: u64,
+    // Dataset revision the cached responses are checked against
+    #[schema(example = "1024")]
+    pub revision: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_only_numeric_codes() {
+        assert_eq!(Level::from_code("32"), Some(Level::Province));
+        assert_eq!(Level::from_code("32.04"), Some(Level::Regency));
+        assert_eq!(Level::from_code("32.04.08"), Some(Level::District));
+        assert_eq!(Level::from_code("32.04.08.2002"), Some(Level::Village));
+
+        for code in ["JB", "3A", "", "32.", ".04", "32..08", "32.04.08.2002.1"] {
+            assert_eq!(Level::from_code(code), None, "{:?}", code);
+        }
+    }
+}
@@ -0,0 +1,377 @@
+// Accept-based content negotiation for every JSON response. Cached reads are
+// encoded by the response cache, see Representation; for the rest handlers
+// keep answering JSON and this middleware re-encodes the body when the client
+// prefers MessagePack or CBOR, or answers 406 when it accepts no format the
+// response comes in.
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, Header, HeaderValue};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpRequest, HttpResponse};
+use log::warn;
+use serde_json::json;
+
+use crate::proto;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    MsgPack,
+    Cbor,
+    #[cfg(feature = "grpc")]
+    Protobuf,
+}
+
+impl Format {
+    // The format a media type of Accept names, if any
+    fn from_mime(essence: &str) -> Option<Format> {
+        match essence {
+            "application/json" | "application/*" | "*/*" => Some(Format::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Format::MsgPack)
+            }
+            "application/cbor" => Some(Format::Cbor),
+            #[cfg(feature = "grpc")]
+            "application/x-protobuf" | "application/protobuf" => Some(Format::Protobuf),
+            _ => None,
+        }
+    }
+
+    // Media types the client takes, most preferred first, with the format
+    // each names. None without a valid Accept header: anything goes.
+    fn accepted(req: &HttpRequest) -> Option<Vec<Option<Format>>> {
+        let accept = header::Accept::parse(req).ok()?;
+        if accept.is_empty() {
+            return None;
+        }
+        // ranked() keeps the types refused with q=0, last
+        let refused: Vec<_> = accept
+            .iter()
+            .filter(|item| item.quality == header::Quality::ZERO)
+            .map(|item| &item.item)
+            .collect();
+        Some(
+            accept
+                .ranked()
+                .iter()
+                .filter(|mime| !refused.contains(mime))
+                .map(|mime| Format::from_mime(mime.essence_str()))
+                .collect(),
+        )
+    }
+
+    // The client's most preferred format the response can be encoded in;
+    // protobuf needs the message of the response. None when the client
+    // accepts none of them.
+    fn negotiate(req: &HttpRequest, has_message: bool) -> Option<Format> {
+        let Some(accepted) = Format::accepted(req) else {
+            return Some(Format::Json);
+        };
+        accepted
+            .into_iter()
+            .flatten()
+            .find(|format| has_message || !format.needs_message())
+    }
+
+    fn needs_message(self) -> bool {
+        match self {
+            #[cfg(feature = "grpc")]
+            Format::Protobuf => true,
+            _ => false,
+        }
+    }
+
+    // Tells the ETags of the encodings apart, empty for JSON
+    fn etag_suffix(self) -> &'static str {
+        match self {
+            Format::Json => "",
+            Format::MsgPack => "-msgpack",
+            Format::Cbor => "-cbor",
+            #[cfg(feature = "grpc")]
+            Format::Protobuf => "-protobuf",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::MsgPack => "application/msgpack",
+            Format::Cbor => "application/cbor",
+            #[cfg(feature = "grpc")]
+            Format::Protobuf => "application/x-protobuf",
+        }
+    }
+
+    // None when the body has no representation in this format, e.g. protobuf
+    // for responses without a message; those stay JSON
+    #[cfg_attr(not(feature = "grpc"), allow(unused_variables))]
+    fn encode(self, json: &[u8], message: Option<proto::Message>) -> Option<Vec<u8>> {
+        let encoded = match self {
+            Format::Json => return None,
+            Format::MsgPack => serde_json::from_slice::<serde_json::Value>(json)
+                .map_err(|e| e.to_string())
+                .and_then(|value| rmp_serde::to_vec_named(&value).map_err(|e| e.to_string())),
+            Format::Cbor => serde_json::from_slice::<serde_json::Value>(json)
+                .map_err(|e| e.to_string())
+                .and_then(|value| {
+                    let mut buf = Vec::new();
+                    ciborium::into_writer(&value, &mut buf).map_err(|e| e.to_string())?;
+                    Ok(buf)
+                }),
+            #[cfg(feature = "grpc")]
+            Format::Protobuf => message?.encode(json).map_err(|e| e.to_string()),
+        };
+        match encoded {
+            Ok(encoded) => Some(encoded),
+            Err(e) => {
+                warn!(
+                    "Failed to encode response as {}: {}",
+                    self.content_type(),
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+// Appended to the ETags of a response without a protobuf message, so a copy
+// in one encoding is never confirmed by a 304 for another
+pub fn etag_suffix(req: &HttpRequest) -> &'static str {
+    Format::negotiate(req, false)
+        .unwrap_or(Format::Json)
+        .etag_suffix()
+}
+
+// Encoding of a cached read: the format the client negotiated and the
+// protobuf message its JSON maps to. Reads are cached per representation,
+// already encoded, so the middleware leaves them alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Representation {
+    format: Format,
+    message: proto::Message,
+}
+
+impl Representation {
+    // JSON when the client accepts no format; the middleware answers 406
+    pub fn new(req: &HttpRequest, message: proto::Message) -> Representation {
+        Representation {
+            format: Format::negotiate(req, true).unwrap_or(Format::Json),
+            message,
+        }
+    }
+
+    // Tells the cache entries and ETags of the encodings apart
+    pub fn suffix(&self) -> &'static str {
+        self.format.etag_suffix()
+    }
+
+    // The body with its content type and ETag suffix; JSON when it could not
+    // be encoded in the negotiated format
+    pub fn encode(&self, json: Vec<u8>) -> (Vec<u8>, &'static str, &'static str) {
+        match self.format.encode(&json, Some(self.message)) {
+            Some(encoded) => (
+                encoded,
+                self.format.content_type(),
+                self.format.etag_suffix(),
+            ),
+            None => (json, Format::Json.content_type(), ""),
+        }
+    }
+}
+
+// 406 with the formats a response without a protobuf message comes in
+fn not_acceptable() -> HttpResponse {
+    HttpResponse::NotAcceptable().json(json!({
+        "error": "This response is only available as application/json, application/msgpack or application/cbor"
+    }))
+}
+
+fn vary_on_accept<B>(mut res: ServiceResponse<B>) -> ServiceResponse<B> {
+    res.headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+    res
+}
+
+pub async fn negotiate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let format = Format::negotiate(req.request(), false);
+    // Only reads have a protobuf message. Refuse a write up front when the
+    // client takes nothing but formats its response cannot come in, before
+    // it changes anything; types unknown here are left to the handler.
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD);
+    let only_known_types = Format::accepted(req.request())
+        .is_some_and(|accepted| accepted.iter().all(Option::is_some));
+    if format.is_none() && !safe && only_known_types {
+        return Ok(vary_on_accept(req.into_response(not_acceptable())));
+    }
+
+    // Every response depends on Accept, 304s and errors included, so shared
+    // caches must key them on it
+    let res = vary_on_accept(next.call(req).await?);
+
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|essence| essence == "application/json" || essence.ends_with("+json"));
+    let format = match format {
+        _ if !is_json => return Ok(res.map_into_boxed_body()),
+        Some(Format::Json) => return Ok(res.map_into_boxed_body()),
+        Some(format) => format,
+        // Errors have no other form, they stay JSON
+        None if !res.status().is_success() => return Ok(res.map_into_boxed_body()),
+        None => {
+            let (req, _) = res.into_parts();
+            return Ok(vary_on_accept(ServiceResponse::new(req, not_acceptable())));
+        }
+    };
+
+    let (req, res) = res.into_parts();
+    let (mut res, body) = res.into_parts();
+    let json = body::to_bytes(body)
+        .await
+        .map_err(|_| actix_web::error::ErrorInternalServerError("Failed to read response body"))?;
+
+    let body = match format.encode(&json, None) {
+        Some(encoded) => {
+            res.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(format.content_type()),
+            );
+            BoxBody::new(encoded)
+        }
+        None => BoxBody::new(json),
+    };
+    Ok(ServiceResponse::new(req, res.set_body(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{web, App};
+
+    fn request(accept: &str) -> HttpRequest {
+        TestRequest::default()
+            .insert_header((header::ACCEPT, accept))
+            .to_http_request()
+    }
+
+    fn format(accept: &str) -> Option<Format> {
+        Format::negotiate(&request(accept), false)
+    }
+
+    #[test]
+    fn picks_the_preferred_supported_type() {
+        assert_eq!(
+            Format::negotiate(&TestRequest::default().to_http_request(), false),
+            Some(Format::Json)
+        );
+        assert_eq!(format("not a media type"), Some(Format::Json));
+        assert_eq!(format("text/html"), None);
+        assert_eq!(
+            format("application/cbor;q=0.5, application/msgpack"),
+            Some(Format::MsgPack)
+        );
+        assert_eq!(
+            format("text/html, application/vnd.msgpack;q=0.9, */*;q=0.1"),
+            Some(Format::MsgPack)
+        );
+        assert_eq!(
+            format("application/json;q=0.1, application/cbor"),
+            Some(Format::Cbor)
+        );
+        // Refused types are not a fallback
+        assert_eq!(format("application/msgpack;q=0, text/html"), None);
+        assert_eq!(format("*/*, application/json;q=0"), Some(Format::Json));
+
+        let suffixes: Vec<_> = [
+            "application/json",
+            "application/msgpack",
+            "application/cbor",
+        ]
+        .map(|accept| format(accept).unwrap().etag_suffix())
+        .into();
+        assert_eq!(suffixes, ["", "-msgpack", "-cbor"]);
+    }
+
+    #[cfg(feature = "grpc")]
+    #[test]
+    fn protobuf_only_with_a_message() {
+        let accept = "application/x-protobuf, application/msgpack;q=0.9";
+        assert_eq!(
+            Format::negotiate(&request(accept), true),
+            Some(Format::Protobuf)
+        );
+        assert_eq!(format(accept), Some(Format::MsgPack));
+        assert_eq!(etag_suffix(&request(accept)), "-msgpack");
+        assert_eq!(format("application/protobuf"), None);
+        assert_eq!(
+            Format::negotiate(&request("application/protobuf"), true),
+            Some(Format::Protobuf)
+        );
+    }
+
+    #[actix_web::test]
+    async fn varies_every_response_on_accept() {
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(negotiate))
+                .route(
+                    "/json",
+                    web::get().to(|| async { HttpResponse::Ok().json(json!({"name": "BALI"})) }),
+                )
+                .route("/not-modified", web::get().to(HttpResponse::NotModified))
+                .route(
+                    "/text",
+                    web::get().to(|| async { HttpResponse::Ok().body("BALI") }),
+                ),
+        )
+        .await;
+        let get = |uri: &str, accept: &str| {
+            TestRequest::get()
+                .uri(uri)
+                .insert_header((header::ACCEPT, accept))
+                .to_request()
+        };
+
+        for uri in ["/json", "/not-modified", "/text"] {
+            for accept in ["application/json", "application/msgpack"] {
+                let res = call_service(&app, get(uri, accept)).await;
+                assert_eq!(
+                    res.headers().get(header::VARY).unwrap(),
+                    "accept",
+                    "{} {}",
+                    uri,
+                    accept
+                );
+            }
+        }
+
+        let res = call_service(&app, get("/json", "application/msgpack")).await;
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/msgpack"
+        );
+        let body: serde_json::Value = rmp_serde::from_slice(&read_body(res).await).unwrap();
+        assert_eq!(body, json!({"name": "BALI"}));
+
+        // Only JSON is re-encoded
+        let res = call_service(&app, get("/text", "application/cbor")).await;
+        assert_eq!(read_body(res).await, "BALI");
+
+        // JSON nobody accepts is refused, other bodies are left alone
+        let res = call_service(&app, get("/json", "text/html")).await;
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "accept");
+        let res = call_service(&app, get("/text", "text/html")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
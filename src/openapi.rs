@@ -0,0 +1,359 @@
+use actix_web::{HttpResponse, Responder};
+use utoipa::OpenApi;
+
+use crate::handlers;
+#[cfg(feature = "search")]
+use crate::models::{
+    AddressComponent, AddressStyle, EnrichUpload, FormatAddressRequest, FormattedAddress,
+    ParseAddressRequest, ParsedAddress,
+};
+use crate::models::{
+    AdminHierarchy, BatchItem, BatchResolution, BatchResolveRequest, BatchResolveResponse,
+    BulkUpsertLine, BulkUpsertResponse, BulkUpsertResult, CacheStats, Change, ChangeFeed,
+    ChangeFilter, ChangeOp, CreateWebhook, CreatedId, DeletePlan, DeletedRow, DeliveryStatus,
+    District, ErrorResponse, FieldError, ForeignKeyViolation, FullAdminData, HasChildrenResponse,
+    Level, Province, QualityIssue, QualityReport, Regency, RegionRow, Severity, UpsertRequest,
+    UpsertResult, UpsertStatus, ValidationErrorResponse, Village, Webhook, WebhookDelivery,
+    WebhookEvent,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "API Wilayah Indonesia",
+        description = "Data wilayah administrasi Indonesia: provinsi, kabupaten/kota, kecamatan dan desa/kelurahan."
+    ),
+    paths(
+        handlers::get_all_provinces,
+        handlers::create_province,
+        handlers::get_one_province,
+        handlers::update_province,
+        handlers::patch_province,
+        handlers::delete_province,
+        handlers::get_province_by_code,
+        handlers::upsert_province_by_code,
+        handlers::get_regencies_by_province,
+        handlers::get_districts_by_regency,
+        handlers::create_regency,
+        handlers::get_one_regency,
+        handlers::update_regency,
+        handlers::patch_regency,
+        handlers::delete_regency,
+        handlers::get_regency_by_code,
+        handlers::upsert_regency_by_code,
+        handlers::get_villages_by_district,
+        handlers::create_district,
+        handlers::get_one_district,
+        handlers::update_district,
+        handlers::patch_district,
+        handlers::delete_district,
+        handlers::get_district_by_code,
+        handlers::upsert_district_by_code,
+        handlers::get_full_admin_data,
+        handlers::create_village,
+        handlers::get_one_village,
+        handlers::update_village,
+        handlers::patch_village,
+        handlers::delete_village,
+        handlers::get_village_by_code,
+        handlers::upsert_village_by_code,
+        handlers::batch_resolve,
+        handlers::bulk_upsert,
+        handlers::get_changes,
+        handlers::events,
+        handlers::graphql,
+        handlers::graphiql,
+        handlers::quality_report,
+        handlers::download_backup,
+        handlers::cache_stats,
+        handlers::get_webhooks,
+        handlers::create_webhook,
+        handlers::get_one_webhook,
+        handlers::delete_webhook,
+        handlers::get_webhook_deliveries,
+        openapi_json,
+        docs,
+    ),
+    components(schemas(
+        Province,
+        Regency,
+        District,
+        Village,
+        FullAdminData,
+        Level,
+        AdminHierarchy,
+        BatchItem,
+        BatchResolveRequest,
+        BatchResolution,
+        BatchResolveResponse,
+        UpsertRequest,
+        UpsertStatus,
+        UpsertResult,
+        BulkUpsertLine,
+        BulkUpsertResult,
+        BulkUpsertResponse,
+        ChangeOp,
+        RegionRow,
+        Change,
+        ChangeFeed,
+        Severity,
+        QualityIssue,
+        ForeignKeyViolation,
+        QualityReport,
+        CacheStats,
+        ChangeFilter,
+        CreateWebhook,
+        Webhook,
+        DeliveryStatus,
+        WebhookDelivery,
+        WebhookEvent,
+        CreatedId,
+        ErrorResponse,
+        DeletedRow,
+        DeletePlan,
+        HasChildrenResponse,
+        FieldError,
+        ValidationErrorResponse
+    )),
+    tags(
+        (name = "provinces", description = "Provinsi"),
+        (name = "regencies", description = "Kabupaten/kota"),
+        (name = "districts", description = "Kecamatan"),
+        (name = "villages", description = "Desa/kelurahan"),
+        (name = "batch", description = "Bulk lookups"),
+        (name = "changes", description = "Change feed for incremental sync"),
+        (name = "graphql", description = "GraphQL over the administrative hierarchy"),
+        (name = "admin", description = "Maintenance of the dataset"),
+        (name = "docs", description = "API documentation")
+    )
+)]
+pub struct ApiDoc;
+
+#[cfg(feature = "search")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        handlers::enrich_csv,
+        handlers::parse_address,
+        handlers::format_address,
+    ),
+    components(schemas(
+        EnrichUpload,
+        ParseAddressRequest,
+        AddressComponent,
+        ParsedAddress,
+        AddressStyle,
+        FormatAddressRequest,
+        FormattedAddress
+    )),
+    tags((name = "address", description = "Address parsing and formatting"))
+)]
+struct SearchApiDoc;
+
+// The document of every route compiled in
+pub fn api_doc() -> utoipa::openapi::OpenApi {
+    #[allow(unused_mut)]
+    let mut doc = ApiDoc::openapi();
+    #[cfg(feature = "search")]
+    doc.merge(SearchApiDoc::openapi());
+    secure_admin_paths(&mut doc);
+    doc
+}
+
+// Every /admin route needs the bearer token checked by auth::require_admin
+fn secure_admin_paths(doc: &mut utoipa::openapi::OpenApi) {
+    use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityRequirement, SecurityScheme};
+    use utoipa::openapi::{RefOr, Response};
+
+    doc.components
+        .get_or_insert_with(Default::default)
+        .add_security_scheme(
+            "admin_token",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    for (_, item) in doc
+        .paths
+        .paths
+        .iter_mut()
+        .filter(|(path, _)| path.starts_with("/admin/"))
+    {
+        let operations = [
+            &mut item.get,
+            &mut item.post,
+            &mut item.put,
+            &mut item.patch,
+            &mut item.delete,
+        ];
+        for operation in operations.into_iter().flatten() {
+            operation.security = Some(vec![SecurityRequirement::new(
+                "admin_token",
+                Vec::<String>::new(),
+            )]);
+            let responses = &mut operation.responses.responses;
+            responses.insert(
+                "401".to_string(),
+                RefOr::T(Response::new("Missing or wrong admin token")),
+            );
+            responses.insert(
+                "403".to_string(),
+                RefOr::T(Response::new("Admin API disabled, ADMIN_TOKEN is not set")),
+            );
+        }
+    }
+}
+
+// Pinned, so a new Redoc release cannot change or break the page
+const REDOC_HTML: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>API Wilayah Indonesia</title>
+    <meta charset="utf-8"/>
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+  </head>
+  <body>
+    <redoc spec-url="/openapi.json"></redoc>
+    <script src="https://cdn.jsdelivr.net/npm/redoc@2.1.5/bundles/redoc.standalone.js"></script>
+  </body>
+</html>
+"#;
+
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    tag = "docs",
+    responses((status = 200, description = "OpenAPI 3.1 document of this API", content_type = "application/json"))
+)]
+pub async fn openapi_json() -> impl Responder {
+    HttpResponse::Ok().json(api_doc())
+}
+
+#[utoipa::path(
+    get,
+    path = "/docs",
+    tag = "docs",
+    responses((status = 200, description = "Redoc UI rendering /openapi.json", content_type = "text/html"))
+)]
+pub async fn docs() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(REDOC_HTML)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AdminToken;
+    use crate::routes::ROUTES;
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App};
+    use std::collections::BTreeSet;
+
+    const METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
+
+    // Send every method to every path of ROUTES through the real app and
+    // collect the (METHOD, pattern) pairs a route took. Handlers fail without
+    // app data; only requests no route took reach the 501 default service.
+    // The admin token lets them into the /admin scope.
+    async fn registered_routes() -> BTreeSet<(String, String)> {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(AdminToken::new(Some("t".to_string()))))
+                .configure(crate::configure_all)
+                .default_service(web::to(HttpResponse::NotImplemented)),
+        )
+        .await;
+        let paths: BTreeSet<_> = ROUTES.iter().map(|def| def.path).collect();
+        let mut routes = BTreeSet::new();
+        for path in paths {
+            let uri = sample_uri(path);
+            for method in METHODS {
+                let req = TestRequest::default()
+                    .method(Method::from_bytes(method.as_bytes()).unwrap())
+                    .uri(&uri)
+                    .insert_header(("Authorization", "Bearer t"))
+                    .to_request();
+                let res = call_service(&app, req).await;
+                if res.status() != StatusCode::NOT_IMPLEMENTED {
+                    let pattern = res.request().match_pattern().unwrap();
+                    routes.insert((method.to_string(), pattern));
+                }
+            }
+        }
+        routes
+    }
+
+    // The path with every {parameter} set to 1
+    fn sample_uri(path: &str) -> String {
+        let mut uri = String::new();
+        let mut rest = path;
+        while let Some(start) = rest.find('{') {
+            uri.push_str(&rest[..start]);
+            uri.push('1');
+            rest = &rest[rest[start..].find('}').unwrap() + start + 1..];
+        }
+        uri.push_str(rest);
+        uri
+    }
+
+    fn documented_routes() -> BTreeSet<(String, String)> {
+        let doc = serde_json::to_value(api_doc()).unwrap();
+        let mut routes = BTreeSet::new();
+        for (path, item) in doc["paths"].as_object().unwrap() {
+            for method in item.as_object().unwrap().keys() {
+                if method != "parameters" {
+                    routes.insert((method.to_uppercase(), path.clone()));
+                }
+            }
+        }
+        routes
+    }
+
+    #[actix_web::test]
+    async fn every_route_is_documented() {
+        let table: BTreeSet<_> = ROUTES
+            .iter()
+            .map(|def| (def.method.to_string(), def.path.to_string()))
+            .collect();
+        let registered = registered_routes().await;
+        assert_eq!(registered, table, "routes registered outside ROUTES");
+
+        let documented = documented_routes();
+        let undocumented: Vec<_> = table.difference(&documented).collect();
+        assert!(
+            undocumented.is_empty(),
+            "routes missing from OpenAPI: {:?}",
+            undocumented
+        );
+        let unrouted: Vec<_> = documented.difference(&table).collect();
+        assert!(
+            unrouted.is_empty(),
+            "OpenAPI paths without a route: {:?}",
+            unrouted
+        );
+    }
+
+    #[test]
+    fn document_is_openapi_3_1() {
+        let doc = serde_json::to_value(api_doc()).unwrap();
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3.1"));
+        for schema in [
+            "Province",
+            "Regency",
+            "District",
+            "Village",
+            "FullAdminData",
+            "ErrorResponse",
+        ] {
+            assert!(
+                doc["components"]["schemas"][schema].is_object(),
+                "missing schema {}",
+                schema
+            );
+        }
+        let backup = &doc["paths"]["/admin/backup"]["get"];
+        assert_eq!(backup["security"], serde_json::json!([{"admin_token": []}]));
+        assert!(backup["responses"]["401"].is_object());
+        assert!(doc["paths"]["/provinces"]["get"]["security"].is_null());
+    }
+}
@@ -0,0 +1,21 @@
+// Protobuf encoding of JSON responses. Handlers name the message their
+// body maps to; the messages themselves need `grpc`.
+#[cfg(feature = "grpc")]
+mod messages;
+#[cfg(feature = "grpc")]
+pub use messages::*;
+
+// Message a handler's JSON response maps to, part of the representation
+// the response cache encodes a read in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Message {
+    Province,
+    Provinces,
+    Regency,
+    Regencies,
+    District,
+    Districts,
+    Village,
+    Villages,
+    FullAdminData,
+}
@@ -0,0 +1,259 @@
+// Protobuf messages of proto/wilayah.proto, kept in step with it by the test
+// below. Responses are encoded from their JSON body, so the structs
+// deserialize the same field names.
+use prost::Message as _;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::Message;
+
+#[derive(Clone, PartialEq, prost::Message, Deserialize)]
+pub struct Province {
+    #[prost(int64, optional, tag = "1")]
+    pub id: Option<i64>,
+    #[prost(int64, optional, tag = "2")]
+    pub version: Option<i64>,
+    #[prost(string, tag = "3")]
+    pub code: String,
+    #[prost(string, tag = "4")]
+    pub name: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message, Deserialize)]
+pub struct Regency {
+    #[prost(int64, optional, tag = "1")]
+    pub id: Option<i64>,
+    #[prost(int64, optional, tag = "2")]
+    pub version: Option<i64>,
+    #[prost(string, tag = "3")]
+    pub code: String,
+    #[prost(string, tag = "4")]
+    pub name: String,
+    #[prost(int64, optional, tag = "5")]
+    pub province_id: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message, Deserialize)]
+pub struct District {
+    #[prost(int64, optional, tag = "1")]
+    pub id: Option<i64>,
+    #[prost(int64, optional, tag = "2")]
+    pub version: Option<i64>,
+    #[prost(string, tag = "3")]
+    pub code: String,
+    #[prost(string, tag = "4")]
+    pub name: String,
+    #[prost(int64, optional, tag = "5")]
+    pub regency_id: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message, Deserialize)]
+pub struct Village {
+    #[prost(int64, optional, tag = "1")]
+    pub id: Option<i64>,
+    #[prost(int64, optional, tag = "2")]
+    pub version: Option<i64>,
+    #[prost(string, tag = "3")]
+    pub code: String,
+    #[prost(string, tag = "4")]
+    pub name: String,
+    #[prost(int64, optional, tag = "5")]
+    pub district_id: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message, Deserialize)]
+pub struct FullAdminData {
+    #[prost(string, tag = "1")]
+    pub province_code: String,
+    #[prost(string, tag = "2")]
+    pub province_name: String,
+    #[prost(string, tag = "3")]
+    pub city_code: String,
+    #[prost(string, tag = "4")]
+    pub city_name: String,
+    #[prost(string, tag = "5")]
+    pub region_code: String,
+    #[prost(string, tag = "6")]
+    pub region_name: String,
+    #[prost(string, tag = "7")]
+    pub village_code: String,
+    #[prost(string, tag = "8")]
+    pub village_name: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProvinceList {
+    #[prost(message, repeated, tag = "1")]
+    pub items: Vec<Province>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RegencyList {
+    #[prost(message, repeated, tag = "1")]
+    pub items: Vec<Regency>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DistrictList {
+    #[prost(message, repeated, tag = "1")]
+    pub items: Vec<District>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VillageList {
+    #[prost(message, repeated, tag = "1")]
+    pub items: Vec<Village>,
+}
+
+impl Message {
+    pub fn encode(self, json: &[u8]) -> serde_json::Result<Vec<u8>> {
+        Ok(match self {
+            Message::Province => one::<Province>(json)?,
+            Message::Provinces => ProvinceList { items: list(json)? }.encode_to_vec(),
+            Message::Regency => one::<Regency>(json)?,
+            Message::Regencies => RegencyList { items: list(json)? }.encode_to_vec(),
+            Message::District => one::<District>(json)?,
+            Message::Districts => DistrictList { items: list(json)? }.encode_to_vec(),
+            Message::Village => one::<Village>(json)?,
+            Message::Villages => VillageList { items: list(json)? }.encode_to_vec(),
+            Message::FullAdminData => one::<FullAdminData>(json)?,
+        })
+    }
+}
+
+fn one<M: prost::Message + DeserializeOwned>(json: &[u8]) -> serde_json::Result<Vec<u8>> {
+    Ok(serde_json::from_slice::<M>(json)?.encode_to_vec())
+}
+
+fn list<M: DeserializeOwned>(json: &[u8]) -> serde_json::Result<Vec<M>> {
+    serde_json::from_slice(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Map, Value};
+    use std::collections::BTreeMap;
+
+    // A field of proto/wilayah.proto: type, name and tag
+    struct Field {
+        kind: String,
+        name: String,
+        tag: u32,
+    }
+
+    // The messages of proto/wilayah.proto. Enough of the syntax for this file:
+    // one field per line, no nesting.
+    fn descriptor() -> BTreeMap<String, Vec<Field>> {
+        let mut messages = BTreeMap::new();
+        let mut current: Option<(String, Vec<Field>)> = None;
+        for line in include_str!("../../proto/wilayah.proto").lines() {
+            let line = line.split("//").next().unwrap().trim();
+            if let Some(name) = line.strip_prefix("message ") {
+                let name = name.trim_end_matches('{').trim().to_string();
+                current = Some((name, Vec::new()));
+            } else if line == "}" {
+                let (name, fields) = current.take().unwrap();
+                messages.insert(name, fields);
+            } else if let Some((_, fields)) = current.as_mut() {
+                let (decl, tag) = line.trim_end_matches(';').split_once('=').unwrap();
+                let words: Vec<_> = decl.split_whitespace().collect();
+                let [.., kind, name] = words[..] else {
+                    panic!("unexpected field {:?}", line);
+                };
+                fields.push(Field {
+                    kind: kind.to_string(),
+                    name: name.to_string(),
+                    tag: tag.trim().parse().unwrap(),
+                });
+            }
+        }
+        messages
+    }
+
+    // A JSON body with every field of the message set, each to a value of
+    // its own
+    fn sample(messages: &BTreeMap<String, Vec<Field>>, message: &str) -> Value {
+        let mut object = Map::new();
+        for field in &messages[message] {
+            let value = match field.kind.as_str() {
+                "int64" => json!(100 + field.tag),
+                "string" => json!(format!("{}-{}", message, field.name)),
+                nested => json!([sample(messages, nested)]),
+            };
+            object.insert(field.name.clone(), value);
+        }
+        Value::Object(object)
+    }
+
+    fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    // The wire encoding the descriptor gives the sample, fields in tag order
+    fn wire(messages: &BTreeMap<String, Vec<Field>>, message: &str, value: &Value) -> Vec<u8> {
+        let mut fields: Vec<_> = messages[message].iter().collect();
+        fields.sort_by_key(|field| field.tag);
+        let mut out = Vec::new();
+        for field in fields {
+            let value = &value[&field.name];
+            match field.kind.as_str() {
+                "int64" => {
+                    varint(u64::from(field.tag) << 3, &mut out);
+                    varint(value.as_u64().unwrap(), &mut out);
+                }
+                "string" => {
+                    let text = value.as_str().unwrap();
+                    varint(u64::from(field.tag) << 3 | 2, &mut out);
+                    varint(text.len() as u64, &mut out);
+                    out.extend_from_slice(text.as_bytes());
+                }
+                nested => {
+                    for item in value.as_array().unwrap() {
+                        let bytes = wire(messages, nested, item);
+                        varint(u64::from(field.tag) << 3 | 2, &mut out);
+                        varint(bytes.len() as u64, &mut out);
+                        out.extend(bytes);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    // The structs are written by hand; they must encode exactly what the
+    // .proto describes, field names, tags and types alike
+    #[test]
+    fn structs_match_the_proto_file() {
+        let messages = descriptor();
+        let cases = [
+            (Message::Province, "Province"),
+            (Message::Provinces, "ProvinceList"),
+            (Message::Regency, "Regency"),
+            (Message::Regencies, "RegencyList"),
+            (Message::District, "District"),
+            (Message::Districts, "DistrictList"),
+            (Message::Village, "Village"),
+            (Message::Villages, "VillageList"),
+            (Message::FullAdminData, "FullAdminData"),
+        ];
+        for (message, name) in cases {
+            let value = sample(&messages, name);
+            let expected = wire(&messages, name, &value);
+            // Lists are sent as bare JSON arrays
+            let json = match name.strip_suffix("List") {
+                Some(_) => value["items"].clone(),
+                None => value,
+            };
+            let encoded = message.encode(&serde_json::to_vec(&json).unwrap()).unwrap();
+            assert_eq!(encoded, expected, "{}", name);
+        }
+        let mut covered = cases.map(|(_, name)| name);
+        covered.sort_unstable();
+        assert_eq!(messages.keys().collect::<Vec<_>>(), covered);
+    }
+}
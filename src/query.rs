@@ -0,0 +1,308 @@
+// Read-only lookups of the command-line tool, run directly against the
+// SQLite file, and their table/JSON/CSV output
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+
+use crate::db::{Database, DatabaseError};
+use crate::models::{AdminHierarchy, Level, LevelCount};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+// One region per output line. `path` holds the names of its ancestors,
+// province first, so a row can be read without the four-way join.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Record {
+    pub level: Level,
+    pub code: String,
+    pub name: String,
+    pub path: String,
+}
+
+impl From<&AdminHierarchy> for Record {
+    fn from(hierarchy: &AdminHierarchy) -> Self {
+        let mut ancestors = breadcrumb(hierarchy);
+        let own = ancestors.pop().expect("the resolved level");
+        Record {
+            path: join_names(&ancestors),
+            ..own
+        }
+    }
+}
+
+// The hierarchy as one record per level, province first
+fn breadcrumb(hierarchy: &AdminHierarchy) -> Vec<Record> {
+    let levels = [
+        (
+            Level::Province,
+            Some(&hierarchy.province_code),
+            Some(&hierarchy.province_name),
+        ),
+        (
+            Level::Regency,
+            hierarchy.city_code.as_ref(),
+            hierarchy.city_name.as_ref(),
+        ),
+        (
+            Level::District,
+            hierarchy.region_code.as_ref(),
+            hierarchy.region_name.as_ref(),
+        ),
+        (
+            Level::Village,
+            hierarchy.village_code.as_ref(),
+            hierarchy.village_name.as_ref(),
+        ),
+    ];
+    let mut records: Vec<Record> = Vec::new();
+    for (level, code, name) in levels {
+        let (Some(code), Some(name)) = (code, name) else {
+            break;
+        };
+        records.push(Record {
+            level,
+            code: code.clone(),
+            name: name.clone(),
+            path: join_names(&records),
+        });
+    }
+    records
+}
+
+fn join_names(records: &[Record]) -> String {
+    records
+        .iter()
+        .map(|record| record.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
+
+fn resolve(db: &Database, code: &str) -> Result<Option<AdminHierarchy>, DatabaseError> {
+    let Some(level) = Level::from_code(code) else {
+        return Ok(None);
+    };
+    Ok(db.resolve_codes(level, &[code.to_string()])?.pop())
+}
+
+pub fn get(db: &Database, code: &str) -> Result<Option<Record>, DatabaseError> {
+    Ok(resolve(db, code)?.as_ref().map(Record::from))
+}
+
+// Provinces without a code; villages have no children
+pub fn children(db: &Database, code: Option<&str>) -> Result<Option<Vec<Record>>, DatabaseError> {
+    let Some(code) = code else {
+        let provinces = db.get_all_provinces()?;
+        return Ok(Some(
+            provinces
+                .into_iter()
+                .map(|province| Record {
+                    level: Level::Province,
+                    code: province.code,
+                    name: province.name,
+                    path: String::new(),
+                })
+                .collect(),
+        ));
+    };
+    let Some(parent) = resolve(db, code)? else {
+        return Ok(None);
+    };
+    let Some(level) = parent.level.child() else {
+        return Ok(Some(Vec::new()));
+    };
+    let Some(parent_id) = db.find_id_by_code(parent.level, code)? else {
+        return Ok(None);
+    };
+    let path = join_names(&breadcrumb(&parent));
+    Ok(Some(
+        db.regions_by_parent(level, &[parent_id])?
+            .into_iter()
+            .map(|row| Record {
+                level,
+                code: row.code,
+                name: row.name,
+                path: path.clone(),
+            })
+            .collect(),
+    ))
+}
+
+// Case-insensitive substring match on the name, at one level or at all of
+// them from provinces down, at most `limit` rows in total
+pub fn search(
+    db: &Database,
+    name: &str,
+    level: Option<Level>,
+    limit: usize,
+) -> Result<Vec<Record>, DatabaseError> {
+    let levels = match level {
+        Some(level) => vec![level],
+        None => vec![
+            Level::Province,
+            Level::Regency,
+            Level::District,
+            Level::Village,
+        ],
+    };
+    let mut records = Vec::new();
+    for level in levels {
+        if records.len() >= limit {
+            break;
+        }
+        let found = db.search_names(level, name, limit - records.len())?;
+        records.extend(found.iter().map(Record::from));
+    }
+    Ok(records)
+}
+
+// Like get_full_admin_data, but for a code of any level
+pub fn path(db: &Database, code: &str) -> Result<Option<Vec<Record>>, DatabaseError> {
+    Ok(resolve(db, code)?.as_ref().map(breadcrumb))
+}
+
+pub fn stats(db: &Database) -> Result<Vec<LevelCount>, DatabaseError> {
+    db.level_counts()
+}
+
+// A list of rows. Tables and CSV get a header line; nothing is written for
+// an empty list except `[]` in JSON.
+pub fn write_rows<W: Write, T: Serialize>(
+    out: &mut W,
+    format: Format,
+    rows: &[T],
+) -> io::Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, rows)?;
+            writeln!(out)
+        }
+        Format::Csv => out.write_all(&to_csv(rows)?),
+        Format::Table => write_table(out, &to_csv(rows)?),
+    }
+}
+
+// A single row, as an object rather than a one-element array in JSON
+pub fn write_row<W: Write, T: Serialize>(out: &mut W, format: Format, row: &T) -> io::Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, row)?;
+            writeln!(out)
+        }
+        _ => write_rows(out, format, std::slice::from_ref(row)),
+    }
+}
+
+fn to_csv<T: Serialize>(rows: &[T]) -> io::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row).map_err(io::Error::other)?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| io::Error::other(e.to_string()))
+}
+
+// Columns padded to their widest cell, the table being the CSV read back
+fn write_table<W: Write>(out: &mut W, csv: &[u8]) -> io::Result<()> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(csv);
+    let mut lines = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(io::Error::other)?;
+        lines.push(record.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+    let mut widths = Vec::new();
+    for line in &lines {
+        widths.resize(widths.len().max(line.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for line in &lines {
+        let mut text = String::new();
+        for (i, cell) in line.iter().enumerate() {
+            if i + 1 < line.len() {
+                text.push_str(&format!("{:<width$}  ", cell, width = widths[i]));
+            } else {
+                text.push_str(cell);
+            }
+        }
+        writeln!(out, "{}", text)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Database {
+        let db = Database::new(":memory:").unwrap();
+        for (code, name) in [
+            ("32", "JAWA BARAT"),
+            ("32.04", "KAB. BANDUNG"),
+            ("32.73", "KOTA BANDUNG"),
+            ("32.04.08", "Bojongsoang"),
+            ("32.04.08.2001", "Lengkong"),
+        ] {
+            db.upsert_by_code(Level::from_code(code).unwrap(), code, name)
+                .unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn looks_up_regions() {
+        let db = fixture();
+
+        let village = get(&db, "32.04.08.2001").unwrap().unwrap();
+        assert_eq!(village.name, "Lengkong");
+        assert_eq!(village.path, "JAWA BARAT > KAB. BANDUNG > Bojongsoang");
+        assert!(get(&db, "32.04.08.9999").unwrap().is_none());
+
+        let regencies = children(&db, Some("32")).unwrap().unwrap();
+        assert_eq!(regencies.len(), 2);
+        assert_eq!(regencies[1].path, "JAWA BARAT");
+        assert_eq!(children(&db, None).unwrap().unwrap().len(), 1);
+
+        let found = search(&db, "bandung", None, 50).unwrap();
+        let codes: Vec<_> = found.iter().map(|record| record.code.as_str()).collect();
+        assert_eq!(codes, ["32.04", "32.73"]);
+        assert_eq!(search(&db, "_", None, 50).unwrap().len(), 0);
+
+        let crumbs = path(&db, "32.04.08").unwrap().unwrap();
+        assert_eq!(crumbs.len(), 3);
+        assert_eq!(crumbs[2].path, "JAWA BARAT > KAB. BANDUNG");
+
+        let counts = stats(&db).unwrap();
+        assert_eq!(counts[1].live, 2);
+    }
+
+    #[test]
+    fn aligns_table_columns() {
+        let db = fixture();
+        let rows = children(&db, Some("32")).unwrap().unwrap();
+
+        let mut out = Vec::new();
+        write_rows(&mut out, Format::Table, &rows).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "level    code   name          path\n\
+             regency  32.04  KAB. BANDUNG  JAWA BARAT\n\
+             regency  32.73  KOTA BANDUNG  JAWA BARAT\n"
+        );
+
+        let mut out = Vec::new();
+        write_row(&mut out, Format::Csv, &rows[0]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "level,code,name,path\nregency,32.04,KAB. BANDUNG,JAWA BARAT\n"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+// Storage of the administrative regions as seen by the per-level handlers.
+// `Database` is the SQLite backend, `MemoryRepository` keeps everything in
+// memory. Apps register the backend as `web::Data<dyn WilayahRepository>`.
+use crate::db::{Database, DatabaseError, Revision};
+use crate::models::{
+    DeleteOptions, DeletePlan, District, FullAdminData, Level, Province, Regency, UpsertResult,
+    Village,
+};
+
+pub trait WilayahRepository: Send + Sync {
+    // Latest change of the data, every write moves it forward
+    fn revision(&self) -> Revision;
+
+    fn find_id_by_code(&self, level: Level, code: &str) -> Result<Option<i64>, DatabaseError>;
+    // Insert or update the row of `level` with this code, under the parent
+    // with the code minus its last segment
+    fn upsert_by_code(
+        &self,
+        level: Level,
+        code: &str,
+        name: &str,
+    ) -> Result<UpsertResult, DatabaseError>;
+
+    fn get_all_provinces(&self) -> Result<Vec<Province>, DatabaseError>;
+    fn get_one_province(&self, province_id: i64) -> Result<Option<Province>, DatabaseError>;
+    fn create_province(&self, province: &Province) -> Result<i64, DatabaseError>;
+    // Returns the new version of the row
+    fn update_province(
+        &self,
+        id: i64,
+        province: &Province,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError>;
+    fn delete_province(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError>;
+
+    fn get_regencies_by_province(&self, province_id: i64) -> Result<Vec<Regency>, DatabaseError>;
+    fn get_one_regency(&self, regency_id: i64) -> Result<Option<Regency>, DatabaseError>;
+    fn create_regency(&self, regency: &Regency) -> Result<i64, DatabaseError>;
+    fn update_regency(
+        &self,
+        id: i64,
+        regency: &Regency,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError>;
+    fn delete_regency(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError>;
+
+    fn get_districts_by_regency(&self, regency_id: i64) -> Result<Vec<District>, DatabaseError>;
+    fn get_one_district(&self, district_id: i64) -> Result<Option<District>, DatabaseError>;
+    fn create_district(&self, district: &District) -> Result<i64, DatabaseError>;
+    fn update_district(
+        &self,
+        id: i64,
+        district: &District,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError>;
+    fn delete_district(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError>;
+
+    fn get_villages_by_district(&self, district_id: i64) -> Result<Vec<Village>, DatabaseError>;
+    fn get_one_village(&self, village_id: i64) -> Result<Option<Village>, DatabaseError>;
+    fn create_village(&self, village: &Village) -> Result<i64, DatabaseError>;
+    fn update_village(
+        &self,
+        id: i64,
+        village: &Village,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError>;
+    fn delete_village(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError>;
+
+    fn get_full_admin_data(&self, village_id: i64) -> Result<Option<FullAdminData>, DatabaseError>;
+}
+
+impl WilayahRepository for Database {
+    fn revision(&self) -> Revision {
+        Database::revision(self)
+    }
+
+    fn find_id_by_code(&self, level: Level, code: &str) -> Result<Option<i64>, DatabaseError> {
+        Database::find_id_by_code(self, level, code)
+    }
+
+    fn upsert_by_code(
+        &self,
+        level: Level,
+        code: &str,
+        name: &str,
+    ) -> Result<UpsertResult, DatabaseError> {
+        Database::upsert_by_code(self, level, code, name)
+    }
+
+    fn get_all_provinces(&self) -> Result<Vec<Province>, DatabaseError> {
+        Database::get_all_provinces(self)
+    }
+
+    fn get_one_province(&self, province_id: i64) -> Result<Option<Province>, DatabaseError> {
+        Database::get_one_province(self, province_id)
+    }
+
+    fn create_province(&self, province: &Province) -> Result<i64, DatabaseError> {
+        Database::create_province(self, province)
+    }
+
+    fn update_province(
+        &self,
+        id: i64,
+        province: &Province,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
+        Database::update_province(self, id, province, if_match)
+    }
+
+    fn delete_province(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        Database::delete_province(self, id, options, if_match)
+    }
+
+    fn get_regencies_by_province(&self, province_id: i64) -> Result<Vec<Regency>, DatabaseError> {
+        Database::get_regencies_by_province(self, province_id)
+    }
+
+    fn get_one_regency(&self, regency_id: i64) -> Result<Option<Regency>, DatabaseError> {
+        Database::get_one_regency(self, regency_id)
+    }
+
+    fn create_regency(&self, regency: &Regency) -> Result<i64, DatabaseError> {
+        Database::create_regency(self, regency)
+    }
+
+    fn update_regency(
+        &self,
+        id: i64,
+        regency: &Regency,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
+        Database::update_regency(self, id, regency, if_match)
+    }
+
+    fn delete_regency(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        Database::delete_regency(self, id, options, if_match)
+    }
+
+    fn get_districts_by_regency(&self, regency_id: i64) -> Result<Vec<District>, DatabaseError> {
+        Database::get_districts_by_regency(self, regency_id)
+    }
+
+    fn get_one_district(&self, district_id: i64) -> Result<Option<District>, DatabaseError> {
+        Database::get_one_district(self, district_id)
+    }
+
+    fn create_district(&self, district: &District) -> Result<i64, DatabaseError> {
+        Database::create_district(self, district)
+    }
+
+    fn update_district(
+        &self,
+        id: i64,
+        district: &District,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
+        Database::update_district(self, id, district, if_match)
+    }
+
+    fn delete_district(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        Database::delete_district(self, id, options, if_match)
+    }
+
+    fn get_villages_by_district(&self, district_id: i64) -> Result<Vec<Village>, DatabaseError> {
+        Database::get_villages_by_district(self, district_id)
+    }
+
+    fn get_one_village(&self, village_id: i64) -> Result<Option<Village>, DatabaseError> {
+        Database::get_one_village(self, village_id)
+    }
+
+    fn create_village(&self, village: &Village) -> Result<i64, DatabaseError> {
+        Database::create_village(self, village)
+    }
+
+    fn update_village(
+        &self,
+        id: i64,
+        village: &Village,
+        if_match: Option<&[i64]>,
+    ) -> Result<i64, DatabaseError> {
+        Database::update_village(self, id, village, if_match)
+    }
+
+    fn delete_village(
+        &self,
+        id: i64,
+        options: &DeleteOptions,
+        if_match: Option<&[i64]>,
+    ) -> Result<DeletePlan, DatabaseError> {
+        Database::delete_village(self, id, options, if_match)
+    }
+
+    fn get_full_admin_data(&self, village_id: i64) -> Result<Option<FullAdminData>, DatabaseError> {
+        Database::get_full_admin_data(self, village_id)
+    }
+}
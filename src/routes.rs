@@ -1,59 +1,278 @@
-use crate::handlers;
-use actix_web::web;
+use crate::{auth, graphql, handlers, openapi};
+use actix_web::http::Method;
+use actix_web::middleware::from_fn;
+use actix_web::{web, Route, Scope};
+
+// One route of the service. Routes are only registered from ROUTES, so the
+// OpenAPI test sees every one of them.
+pub struct RouteDef {
+    pub method: Method,
+    // Full path pattern, as documented in OpenAPI
+    pub path: &'static str,
+    handler: fn(Route) -> Route,
+}
+
+const fn route(method: Method, path: &'static str, handler: fn(Route) -> Route) -> RouteDef {
+    RouteDef {
+        method,
+        path,
+        handler,
+    }
+}
+
+// Every route of the service. Within a scope the first match wins, so
+// /code/{code} comes before /{id}.
+pub static ROUTES: &[RouteDef] = &[
+    route(Method::GET, "/provinces/code/{code}", |r| {
+        r.to(handlers::get_province_by_code)
+    }),
+    route(Method::PUT, "/provinces/code/{code}", |r| {
+        r.to(handlers::upsert_province_by_code)
+    }),
+    route(Method::GET, "/provinces", |r| {
+        r.to(handlers::get_all_provinces)
+    }),
+    route(Method::POST, "/provinces", |r| {
+        r.to(handlers::create_province)
+    }),
+    route(Method::GET, "/provinces/{id}", |r| {
+        r.to(handlers::get_one_province)
+    }),
+    route(Method::PUT, "/provinces/{id}", |r| {
+        r.to(handlers::update_province)
+    }),
+    route(Method::PATCH, "/provinces/{id}", |r| {
+        r.to(handlers::patch_province)
+    }),
+    route(Method::DELETE, "/provinces/{id}", |r| {
+        r.to(handlers::delete_province)
+    }),
+    route(Method::GET, "/provinces/{province_id}/regencies", |r| {
+        r.to(handlers::get_regencies_by_province)
+    }),
+    route(Method::GET, "/regencies/code/{code}", |r| {
+        r.to(handlers::get_regency_by_code)
+    }),
+    route(Method::PUT, "/regencies/code/{code}", |r| {
+        r.to(handlers::upsert_regency_by_code)
+    }),
+    route(Method::GET, "/regencies/{city_id}/districts", |r| {
+        r.to(handlers::get_districts_by_regency)
+    }),
+    route(Method::POST, "/regencies", |r| {
+        r.to(handlers::create_regency)
+    }),
+    route(Method::GET, "/regencies/{id}", |r| {
+        r.to(handlers::get_one_regency)
+    }),
+    route(Method::PUT, "/regencies/{id}", |r| {
+        r.to(handlers::update_regency)
+    }),
+    route(Method::PATCH, "/regencies/{id}", |r| {
+        r.to(handlers::patch_regency)
+    }),
+    route(Method::DELETE, "/regencies/{id}", |r| {
+        r.to(handlers::delete_regency)
+    }),
+    route(Method::GET, "/districts/code/{code}", |r| {
+        r.to(handlers::get_district_by_code)
+    }),
+    route(Method::PUT, "/districts/code/{code}", |r| {
+        r.to(handlers::upsert_district_by_code)
+    }),
+    route(Method::GET, "/districts/{region_id}/villages", |r| {
+        r.to(handlers::get_villages_by_district)
+    }),
+    route(Method::POST, "/districts", |r| {
+        r.to(handlers::create_district)
+    }),
+    route(Method::GET, "/districts/{id}", |r| {
+        r.to(handlers::get_one_district)
+    }),
+    route(Method::PUT, "/districts/{id}", |r| {
+        r.to(handlers::update_district)
+    }),
+    route(Method::PATCH, "/districts/{id}", |r| {
+        r.to(handlers::patch_district)
+    }),
+    route(Method::DELETE, "/districts/{id}", |r| {
+        r.to(handlers::delete_district)
+    }),
+    route(Method::GET, "/villages/code/{code}", |r| {
+        r.to(handlers::get_village_by_code)
+    }),
+    route(Method::PUT, "/villages/code/{code}", |r| {
+        r.to(handlers::upsert_village_by_code)
+    }),
+    route(Method::GET, "/villages/{village_id}/details", |r| {
+        r.to(handlers::get_full_admin_data)
+    }),
+    route(Method::POST, "/villages", |r| {
+        r.to(handlers::create_village)
+    }),
+    route(Method::GET, "/villages/{id}", |r| {
+        r.to(handlers::get_one_village)
+    }),
+    route(Method::PUT, "/villages/{id}", |r| {
+        r.to(handlers::update_village)
+    }),
+    route(Method::PATCH, "/villages/{id}", |r| {
+        r.to(handlers::patch_village)
+    }),
+    route(Method::DELETE, "/villages/{id}", |r| {
+        r.to(handlers::delete_village)
+    }),
+    route(Method::POST, "/batch/resolve", |r| {
+        r.to(handlers::batch_resolve)
+    }),
+    route(Method::POST, "/batch/upsert", |r| {
+        r.to(handlers::bulk_upsert)
+    }),
+    #[cfg(feature = "search")]
+    route(Method::POST, "/enrich", |r| r.to(handlers::enrich_csv)),
+    route(Method::GET, "/changes", |r| r.to(handlers::get_changes)),
+    route(Method::GET, "/events", |r| r.to(handlers::events)),
+    route(Method::POST, "/graphql", |r| r.to(handlers::graphql)),
+    route(Method::GET, "/graphql", |r| r.to(handlers::graphiql)),
+    #[cfg(feature = "search")]
+    route(Method::POST, "/address/parse", |r| {
+        r.to(handlers::parse_address)
+    }),
+    #[cfg(feature = "search")]
+    route(Method::POST, "/address/format", |r| {
+        r.to(handlers::format_address)
+    }),
+    route(Method::GET, "/admin/quality", |r| {
+        r.to(handlers::quality_report)
+    }),
+    route(Method::GET, "/admin/cache", |r| r.to(handlers::cache_stats)),
+    route(Method::GET, "/admin/backup", |r| {
+        r.to(handlers::download_backup)
+    }),
+    route(Method::GET, "/admin/webhooks", |r| {
+        r.to(handlers::get_webhooks)
+    }),
+    route(Method::POST, "/admin/webhooks", |r| {
+        r.to(handlers::create_webhook)
+    }),
+    route(Method::GET, "/admin/webhooks/{id}", |r| {
+        r.to(handlers::get_one_webhook)
+    }),
+    route(Method::DELETE, "/admin/webhooks/{id}", |r| {
+        r.to(handlers::delete_webhook)
+    }),
+    route(Method::GET, "/admin/webhooks/{id}/deliveries", |r| {
+        r.to(handlers::get_webhook_deliveries)
+    }),
+    route(Method::GET, "/openapi.json", |r| {
+        r.to(openapi::openapi_json)
+    }),
+    route(Method::GET, "/docs", |r| r.to(openapi::docs)),
+];
+
+// The routes of ROUTES below `prefix`, in a scope of their own
+fn scope(prefix: &'static str) -> Scope {
+    ROUTES
+        .iter()
+        .filter(|def| {
+            def.path
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .fold(web::scope(prefix), |scope, def| {
+            let route = (def.handler)(web::method(def.method.clone()));
+            scope.route(&def.path[prefix.len()..], route)
+        })
+}
+
+// The routes of ROUTES at exactly `path`
+fn register(cfg: &mut web::ServiceConfig, path: &str) {
+    for def in ROUTES.iter().filter(|def| def.path == path) {
+        cfg.route(def.path, (def.handler)(web::method(def.method.clone())));
+    }
+}
+
+// Every route of the service. The app must provide `web::Data<Database>`,
+// the same data as `web::Data<dyn WilayahRepository>` and
+// `web::Data<ResponseCache>`, and for the /admin routes
+// `web::Data<AdminToken>` and optionally `web::Data<UrlPolicy>`; webhook
+// delivery and the negotiation and compression middleware are left to the
+// app.
+pub fn configure_all(cfg: &mut web::ServiceConfig) {
+    cfg.configure(configure_region_routes)
+        .configure(configure_batch_routes);
+    #[cfg(feature = "search")]
+    cfg.configure(configure_enrich_routes);
+    cfg.configure(configure_change_routes)
+        .configure(configure_graphql_routes);
+    #[cfg(feature = "search")]
+    cfg.configure(configure_address_routes);
+    cfg.configure(configure_admin_routes)
+        .configure(configure_docs_routes);
+}
+
+// The per-level routes. They only need `web::Data<dyn WilayahRepository>`
+// and `web::Data<ResponseCache>`, so any backend can serve them, including
+// MemoryRepository; the other routes need the SQLite `Database`.
+pub fn configure_region_routes(cfg: &mut web::ServiceConfig) {
+    cfg.configure(configure_province_routes)
+        .configure(configure_regency_routes)
+        .configure(configure_district_routes)
+        .configure(configure_village_routes);
+}
 
 pub fn configure_province_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::scope("/provinces")
-            .route("", web::get().to(handlers::get_all_provinces))
-            .route("", web::post().to(handlers::create_province))
-            .route("/{id}", web::get().to(handlers::get_one_province))
-            .route("/{id}", web::put().to(handlers::update_province))
-            .route("/{id}", web::delete().to(handlers::delete_province))
-            .route(
-                "/{province_id}/regencies",
-                web::get().to(handlers::get_regencies_by_province),
-            ),
-    );
+    cfg.service(scope("/provinces"));
 }
 
 pub fn configure_regency_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::scope("/regencies")
-            .route(
-                "/{city_id}/districts",
-                web::get().to(handlers::get_districts_by_regency),
-            )
-            .route("", web::post().to(handlers::create_regency))
-            .route("/{id}", web::get().to(handlers::get_one_regency))
-            .route("/{id}", web::put().to(handlers::update_regency))
-            .route("/{id}", web::delete().to(handlers::delete_regency)),
-    );
+    cfg.service(scope("/regencies"));
 }
 
 pub fn configure_district_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::scope("/districts")
-            .route(
-                "/{region_id}/villages",
-                web::get().to(handlers::get_villages_by_district),
-            )
-            .route("", web::post().to(handlers::create_district))
-            .route("/{id}", web::get().to(handlers::get_one_district))
-            .route("/{id}", web::put().to(handlers::update_district))
-            .route("/{id}", web::delete().to(handlers::delete_district)),
-    );
+    cfg.service(scope("/districts"));
 }
 
 pub fn configure_village_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(scope("/villages"));
+}
+
+pub fn configure_batch_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
-        web::scope("/villages")
-            .route(
-                "/{village_id}/details",
-                web::get().to(handlers::get_full_admin_data),
-            )
-            .route("", web::post().to(handlers::create_village))
-            .route("/{id}", web::get().to(handlers::get_one_village))
-            .route("/{id}", web::put().to(handlers::update_village))
-            .route("/{id}", web::delete().to(handlers::delete_village)),
+        scope("/batch")
+            // ETL jobs send far more than the default 32 KiB of JSON
+            .app_data(web::JsonConfig::default().limit(16 * 1024 * 1024))
+            // NDJSON exports of the whole dataset run to tens of MiB
+            .app_data(web::PayloadConfig::new(64 * 1024 * 1024)),
     );
 }
+
+#[cfg(feature = "search")]
+pub fn configure_enrich_routes(cfg: &mut web::ServiceConfig) {
+    register(cfg, "/enrich");
+}
+
+pub fn configure_change_routes(cfg: &mut web::ServiceConfig) {
+    register(cfg, "/changes");
+    register(cfg, "/events");
+}
+
+pub fn configure_graphql_routes(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::Data::new(graphql::schema()));
+    register(cfg, "/graphql");
+}
+
+#[cfg(feature = "search")]
+pub fn configure_address_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(scope("/address"));
+}
+
+// Behind auth::require_admin, which needs `web::Data<AdminToken>`
+pub fn configure_admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(scope("/admin").wrap(from_fn(auth::require_admin)));
+}
+
+pub fn configure_docs_routes(cfg: &mut web::ServiceConfig) {
+    register(cfg, "/openapi.json");
+    register(cfg, "/docs");
+}
@@ -0,0 +1,154 @@
+// Write rules shared by the SQLite and in-memory backends: If-Match versions,
+// code changes under children, the parent of an upsert by code and the plan
+// of a delete. Each backend answers RuleStore from its own rows and applies
+// the outcome itself.
+use std::collections::BTreeMap;
+
+use crate::db::DatabaseError;
+use crate::models::{DeleteOptions, DeletePlan, DeletedRow, FieldError, Level};
+use crate::validation;
+
+// Lookups the rules need from a backend
+pub trait RuleStore {
+    // Version and code of a row that is not soft-deleted
+    fn live_row(&self, level: Level, id: i64) -> Result<Option<(i64, String)>, DatabaseError>;
+    // Lowest id of a live row with this code
+    fn find_id(&self, level: Level, code: &str) -> Result<Option<i64>, DatabaseError>;
+    // Rows of `level` under any of `parent_ids` as (id, code, deleted), the
+    // soft-deleted ones only with `include_deleted`
+    fn child_rows(
+        &self,
+        level: Level,
+        parent_ids: &[i64],
+        include_deleted: bool,
+    ) -> Result<Vec<(i64, String, bool)>, DatabaseError>;
+}
+
+// Version and code of a live row. With `if_match`, the row must be at one of
+// the listed versions.
+fn matching_row(
+    store: &impl RuleStore,
+    level: Level,
+    id: i64,
+    if_match: Option<&[i64]>,
+) -> Result<(i64, String), DatabaseError> {
+    let (version, code) = store.live_row(level, id)?.ok_or(DatabaseError::NotFound)?;
+    match if_match {
+        Some(versions) if !versions.contains(&version) => {
+            Err(DatabaseError::PreconditionFailed(version))
+        }
+        _ => Ok((version, code)),
+    }
+}
+
+// Current version of a live row, checked against `if_match`
+pub fn check_version(
+    store: &impl RuleStore,
+    level: Level,
+    id: i64,
+    if_match: Option<&[i64]>,
+) -> Result<i64, DatabaseError> {
+    matching_row(store, level, id, if_match).map(|(version, _)| version)
+}
+
+// Children carry their parent's code as prefix, so a row with children
+// cannot be recoded without breaking them
+pub fn check_code_change(
+    store: &impl RuleStore,
+    level: Level,
+    id: i64,
+    new_code: &str,
+) -> Result<(), DatabaseError> {
+    let (_, current) = store.live_row(level, id)?.ok_or(DatabaseError::NotFound)?;
+    let Some(child) = level.child() else {
+        return Ok(());
+    };
+    if current == new_code {
+        return Ok(());
+    }
+
+    let children = store.child_rows(child, &[id], false)?.len();
+    if children > 0 {
+        return Err(DatabaseError::Validation(vec![FieldError::new(
+            "code",
+            format!(
+                "cannot change while {} {} rows belong to this {}",
+                children,
+                child.as_str(),
+                level.as_str()
+            ),
+        )]));
+    }
+    Ok(())
+}
+
+// Validates an upsert by code and finds its parent: the live row with the
+// code minus its last segment, which must exist
+pub fn upsert_parent(
+    store: &impl RuleStore,
+    level: Level,
+    code: &str,
+    name: &str,
+) -> Result<Option<i64>, DatabaseError> {
+    validation::validate(level, code, name, None)?;
+
+    match (level.parent(), code.rsplit_once('.')) {
+        (Some(parent), Some((parent_code, _))) => {
+            let id = store.find_id(parent, parent_code)?.ok_or_else(|| {
+                DatabaseError::Validation(vec![FieldError::new(
+                    "code",
+                    format!("no {} with code {} exists", parent.as_str(), parent_code),
+                )])
+            })?;
+            Ok(Some(id))
+        }
+        _ => Ok(None),
+    }
+}
+
+// The rows a delete takes: the row and its descendants on every level below.
+// Rows with live children are refused unless `cascade` is set; with `hard`,
+// soft-deleted descendants are collected too, to be purged.
+pub fn delete_plan(
+    store: &impl RuleStore,
+    level: Level,
+    id: i64,
+    options: &DeleteOptions,
+    if_match: Option<&[i64]>,
+) -> Result<DeletePlan, DatabaseError> {
+    let (_, code) = matching_row(store, level, id, if_match)?;
+
+    let mut rows = vec![DeletedRow { level, id, code }];
+    let mut live_children = BTreeMap::new();
+    let mut parent_ids = vec![id];
+    let mut child_level = level.child();
+    while let Some(child) = child_level {
+        let children = store.child_rows(child, &parent_ids, options.hard)?;
+        let live = children.iter().filter(|(_, _, deleted)| !deleted).count();
+        if live > 0 {
+            live_children.insert(child.table().to_string(), live);
+        }
+        parent_ids = children.iter().map(|(id, _, _)| *id).collect();
+        rows.extend(children.into_iter().map(|(id, code, _)| DeletedRow {
+            level: child,
+            id,
+            code,
+        }));
+        child_level = child.child();
+    }
+
+    if !live_children.is_empty() && !options.cascade {
+        return Err(DatabaseError::HasChildren(live_children));
+    }
+
+    let mut counts = BTreeMap::new();
+    for row in &rows {
+        *counts.entry(row.level.table().to_string()).or_insert(0) += 1;
+    }
+    Ok(DeletePlan {
+        dry_run: options.dry_run,
+        hard: options.hard,
+        counts,
+        rows,
+    })
+}
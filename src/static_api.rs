@@ -0,0 +1,190 @@
+// Read side of the API as a tree of JSON files for `wilayah-service
+// generate-static`, to be served from a CDN. Every file has the body of the
+// matching GET route, keyed by code instead of id:
+//
+//   provinces.json                   GET /provinces
+//   regencies/{province_code}.json   GET /provinces/{id}/regencies
+//   districts/{regency_code}.json    GET /regencies/{id}/districts
+//   villages/{district_code}.json    GET /districts/{id}/villages
+//   details/{village_code}.json      GET /villages/{id}/details
+//
+// manifest.json lists every file with its SHA-256 so a sync job only uploads
+// what changed.
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::models::FullAdminData;
+use crate::repository::WilayahRepository;
+
+pub const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub sha256: String,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Manifest {
+    // Seq of the latest change included
+    pub revision: i64,
+    // By path relative to the output directory, with `/` separators
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+struct Writer<'a> {
+    out: &'a Path,
+    minify: bool,
+    files: BTreeMap<String, ManifestEntry>,
+}
+
+impl Writer<'_> {
+    fn write<T: Serialize>(&mut self, path: String, body: &T) -> io::Result<()> {
+        let bytes = if self.minify {
+            serde_json::to_vec(body)?
+        } else {
+            let mut bytes = serde_json::to_vec_pretty(body)?;
+            bytes.push(b'\n');
+            bytes
+        };
+        let file = self.out.join(&path);
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&file, &bytes)?;
+        self.files.insert(
+            path,
+            ManifestEntry {
+                sha256: hex::encode(Sha256::digest(&bytes)),
+                bytes: bytes.len(),
+            },
+        );
+        Ok(())
+    }
+}
+
+// Writes the whole tree into `out` and returns its manifest. Files listed in
+// the manifest of a previous run that are not part of this one (regions
+// deleted since) are removed; nothing else in `out` is touched.
+pub fn generate(
+    repository: &dyn WilayahRepository,
+    out: &Path,
+    minify: bool,
+) -> io::Result<Manifest> {
+    let previous: Option<Manifest> = match fs::read(out.join(MANIFEST)) {
+        Ok(bytes) => Some(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    fs::create_dir_all(out)?;
+
+    let revision = repository.revision().seq;
+    let mut writer = Writer {
+        out,
+        minify,
+        files: BTreeMap::new(),
+    };
+    let provinces = repository.get_all_provinces().map_err(io::Error::other)?;
+    writer.write("provinces.json".to_string(), &provinces)?;
+    for province in &provinces {
+        let regencies = repository
+            .get_regencies_by_province(province.id.unwrap_or_default())
+            .map_err(io::Error::other)?;
+        writer.write(format!("regencies/{}.json", province.code), &regencies)?;
+        for regency in &regencies {
+            let districts = repository
+                .get_districts_by_regency(regency.id.unwrap_or_default())
+                .map_err(io::Error::other)?;
+            writer.write(format!("districts/{}.json", regency.code), &districts)?;
+            for district in &districts {
+                let villages = repository
+                    .get_villages_by_district(district.id.unwrap_or_default())
+                    .map_err(io::Error::other)?;
+                writer.write(format!("villages/{}.json", district.code), &villages)?;
+                for village in &villages {
+                    let details = FullAdminData {
+                        province_code: province.code.clone(),
+                        province_name: province.name.clone(),
+                        city_code: regency.code.clone(),
+                        city_name: regency.name.clone(),
+                        region_code: district.code.clone(),
+                        region_name: district.name.clone(),
+                        village_code: village.code.clone(),
+                        village_name: village.name.clone(),
+                    };
+                    writer.write(format!("details/{}.json", village.code), &details)?;
+                }
+            }
+        }
+    }
+
+    for path in previous.iter().flat_map(|manifest| manifest.files.keys()) {
+        if !writer.files.contains_key(path) {
+            match fs::remove_file(out.join(path)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+    }
+
+    let manifest = Manifest {
+        revision,
+        files: writer.files,
+    };
+    fs::write(out.join(MANIFEST), serde_json::to_vec_pretty(&manifest)?)?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DeleteOptions, Level, Province, Regency};
+    use crate::Database;
+    use tempfile::TempDir;
+
+    #[test]
+    fn mirrors_the_read_routes() {
+        let db = Database::new(":memory:").unwrap();
+        for (code, name) in [
+            ("32", "JAWA BARAT"),
+            ("32.04", "KAB. BANDUNG"),
+            ("32.04.08", "Bojongsoang"),
+            ("32.04.08.2001", "Lengkong"),
+            ("32.04.08.2002", "Bojongsoang"),
+        ] {
+            db.upsert_by_code(Level::from_code(code).unwrap(), code, name)
+                .unwrap();
+        }
+        let dir = TempDir::new().unwrap();
+
+        let manifest = generate(&db, dir.path(), false).unwrap();
+        assert_eq!(manifest.revision, 5);
+        assert_eq!(manifest.files.len(), 6);
+        let provinces: Vec<Province> =
+            serde_json::from_slice(&fs::read(dir.path().join("provinces.json")).unwrap()).unwrap();
+        assert_eq!(provinces[0].version, Some(1));
+        let regencies: Vec<Regency> =
+            serde_json::from_slice(&fs::read(dir.path().join("regencies/32.json")).unwrap())
+                .unwrap();
+        assert_eq!(regencies[0].province_id, provinces[0].id);
+        let details = fs::read(dir.path().join("details/32.04.08.2002.json")).unwrap();
+        let details: FullAdminData = serde_json::from_slice(&details).unwrap();
+        assert_eq!(details.region_name, "Bojongsoang");
+
+        // Stale files of the previous run go, the checksums follow the bytes
+        let id = db.find_id_by_code(Level::Village, "32.04.08.2001").unwrap();
+        db.delete_village(id.unwrap(), &DeleteOptions::default(), None)
+            .unwrap();
+        let manifest = generate(&db, dir.path(), true).unwrap();
+        assert!(!dir.path().join("details/32.04.08.2001.json").exists());
+        let villages = fs::read(dir.path().join("villages/32.04.08.json")).unwrap();
+        assert!(!villages.contains(&b'\n'));
+        let entry = &manifest.files["villages/32.04.08.json"];
+        assert_eq!(entry.sha256, hex::encode(Sha256::digest(&villages)));
+        assert_eq!(entry.bytes, villages.len());
+    }
+}
@@ -0,0 +1,516 @@
+// Interactive browser of the hierarchy for `wilayah-service tui`. One list
+// per level, entered with Enter and left with Backspace; renames go through
+// the update_* methods of the repository, so validation and If-Match apply.
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+use std::io;
+
+use crate::db::DatabaseError;
+use crate::models::{District, Level, Province, Regency, Village};
+use crate::repository::WilayahRepository;
+
+const PAGE: usize = 10;
+
+#[derive(Debug, Clone)]
+struct Row {
+    id: i64,
+    code: String,
+    name: String,
+    version: Option<i64>,
+}
+
+// The rows of one level below one parent, and where the cursor is
+struct Column {
+    level: Level,
+    parent_id: Option<i64>,
+    rows: Vec<Row>,
+    filter: String,
+    // Index into the filtered rows
+    selected: usize,
+}
+
+impl Column {
+    fn load(
+        repository: &dyn WilayahRepository,
+        level: Level,
+        parent_id: Option<i64>,
+    ) -> Result<Column, DatabaseError> {
+        Ok(Column {
+            level,
+            parent_id,
+            rows: load_rows(repository, level, parent_id)?,
+            filter: String::new(),
+            selected: 0,
+        })
+    }
+
+    // Rows whose name or code contains the filter, ignoring case
+    fn visible(&self) -> Vec<&Row> {
+        let filter = self.filter.to_uppercase();
+        self.rows
+            .iter()
+            .filter(|row| row.name.to_uppercase().contains(&filter) || row.code.contains(&filter))
+            .collect()
+    }
+
+    fn current(&self) -> Option<&Row> {
+        self.visible().get(self.selected).copied()
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let last = self.visible().len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+}
+
+fn load_rows(
+    repository: &dyn WilayahRepository,
+    level: Level,
+    parent_id: Option<i64>,
+) -> Result<Vec<Row>, DatabaseError> {
+    let parent_id = || parent_id.expect("level with a parent");
+    let rows = match level {
+        Level::Province => repository
+            .get_all_provinces()?
+            .into_iter()
+            .map(|p| (p.id, p.code, p.name, p.version))
+            .collect::<Vec<_>>(),
+        Level::Regency => repository
+            .get_regencies_by_province(parent_id())?
+            .into_iter()
+            .map(|r| (r.id, r.code, r.name, r.version))
+            .collect(),
+        Level::District => repository
+            .get_districts_by_regency(parent_id())?
+            .into_iter()
+            .map(|d| (d.id, d.code, d.name, d.version))
+            .collect(),
+        Level::Village => repository
+            .get_villages_by_district(parent_id())?
+            .into_iter()
+            .map(|v| (v.id, v.code, v.name, v.version))
+            .collect(),
+    };
+    Ok(rows
+        .into_iter()
+        .map(|(id, code, name, version)| Row {
+            id: id.unwrap_or_default(),
+            code,
+            name,
+            version,
+        })
+        .collect())
+}
+
+// Rename through update_*, based on the version the row was loaded with
+fn rename(
+    repository: &dyn WilayahRepository,
+    column: &Column,
+    row: &Row,
+    name: &str,
+) -> Result<i64, DatabaseError> {
+    let version = row.version.map(|version| [version]);
+    let if_match = version.as_ref().map(|version| &version[..]);
+    let (id, code, name) = (Some(row.id), row.code.clone(), name.to_string());
+    match column.level {
+        Level::Province => repository.update_province(
+            row.id,
+            &Province {
+                id,
+                version: None,
+                code,
+                name,
+            },
+            if_match,
+        ),
+        Level::Regency => repository.update_regency(
+            row.id,
+            &Regency {
+                id,
+                version: None,
+                code,
+                name,
+                province_id: column.parent_id,
+            },
+            if_match,
+        ),
+        Level::District => repository.update_district(
+            row.id,
+            &District {
+                id,
+                version: None,
+                code,
+                name,
+                regency_id: column.parent_id,
+            },
+            if_match,
+        ),
+        Level::Village => repository.update_village(
+            row.id,
+            &Village {
+                id,
+                version: None,
+                code,
+                name,
+                district_id: column.parent_id,
+            },
+            if_match,
+        ),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+    Browse,
+    Filter,
+    // The new name being typed
+    Edit(String),
+}
+
+pub struct Browser {
+    // Provinces first, the level being browsed last
+    columns: Vec<Column>,
+    mode: Mode,
+    read_only: bool,
+    status: String,
+}
+
+impl Browser {
+    pub fn new(
+        repository: &dyn WilayahRepository,
+        read_only: bool,
+    ) -> Result<Browser, DatabaseError> {
+        Ok(Browser {
+            columns: vec![Column::load(repository, Level::Province, None)?],
+            mode: Mode::Browse,
+            read_only,
+            status: String::new(),
+        })
+    }
+
+    fn column(&self) -> &Column {
+        self.columns.last().expect("the province column")
+    }
+
+    fn column_mut(&mut self) -> &mut Column {
+        self.columns.last_mut().expect("the province column")
+    }
+
+    // Returns false once the user asked to quit
+    pub fn handle_key(&mut self, repository: &dyn WilayahRepository, key: KeyEvent) -> bool {
+        if key.kind != KeyEventKind::Press {
+            return true;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+        match self.mode.clone() {
+            Mode::Browse => return self.browse(repository, key.code),
+            Mode::Filter => match key.code {
+                KeyCode::Char(c) => self.set_filter(|filter| filter.push(c)),
+                KeyCode::Backspace => self.set_filter(|filter| {
+                    filter.pop();
+                }),
+                KeyCode::Esc => {
+                    self.set_filter(String::clear);
+                    self.mode = Mode::Browse;
+                }
+                KeyCode::Enter | KeyCode::Up | KeyCode::Down => self.mode = Mode::Browse,
+                _ => {}
+            },
+            Mode::Edit(mut name) => match key.code {
+                KeyCode::Char(c) => {
+                    name.push(c);
+                    self.mode = Mode::Edit(name);
+                }
+                KeyCode::Backspace => {
+                    name.pop();
+                    self.mode = Mode::Edit(name);
+                }
+                KeyCode::Esc => {
+                    self.mode = Mode::Browse;
+                    self.status.clear();
+                }
+                KeyCode::Enter => self.save(repository, &name),
+                _ => {}
+            },
+        }
+        true
+    }
+
+    fn browse(&mut self, repository: &dyn WilayahRepository, key: KeyCode) -> bool {
+        self.status.clear();
+        match key {
+            KeyCode::Char('q') => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.column_mut().move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.column_mut().move_by(-1),
+            KeyCode::PageDown => self.column_mut().move_by(PAGE as isize),
+            KeyCode::PageUp => self.column_mut().move_by(-(PAGE as isize)),
+            KeyCode::Home => self.column_mut().selected = 0,
+            KeyCode::End => self.column_mut().move_by(isize::MAX),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => self.enter(repository),
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') if self.columns.len() > 1 => {
+                self.columns.pop();
+            }
+            KeyCode::Esc => self.set_filter(String::clear),
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Char('e') if self.read_only => self.status = "Read-only".to_string(),
+            KeyCode::Char('e') => {
+                if let Some(row) = self.column().current() {
+                    self.mode = Mode::Edit(row.name.clone());
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn set_filter(&mut self, change: impl FnOnce(&mut String)) {
+        let column = self.column_mut();
+        change(&mut column.filter);
+        column.selected = 0;
+    }
+
+    fn enter(&mut self, repository: &dyn WilayahRepository) {
+        let column = self.column();
+        let (Some(level), Some(row)) = (column.level.child(), column.current()) else {
+            return;
+        };
+        match Column::load(repository, level, Some(row.id)) {
+            Ok(child) => self.columns.push(child),
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn save(&mut self, repository: &dyn WilayahRepository, name: &str) {
+        let column = self.column();
+        let Some(row) = column.current().cloned() else {
+            self.mode = Mode::Browse;
+            return;
+        };
+        match rename(repository, column, &row, name) {
+            Ok(version) => {
+                let saved = self
+                    .column_mut()
+                    .rows
+                    .iter_mut()
+                    .find(|r| r.id == row.id)
+                    .expect("the edited row");
+                saved.name = name.to_string();
+                saved.version = Some(version);
+                self.status = format!("Saved {}", row.code);
+                self.mode = Mode::Browse;
+            }
+            // Stay in the editor so the name can be fixed
+            Err(DatabaseError::Validation(fields)) => {
+                self.status = fields
+                    .iter()
+                    .map(|field| format!("{}: {}", field.field, field.message))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+            }
+            Err(DatabaseError::PreconditionFailed(version)) => {
+                self.reload(repository);
+                self.status = format!(
+                    "{} was changed elsewhere (now version {}), reloaded",
+                    row.code, version
+                );
+                self.mode = Mode::Browse;
+            }
+            Err(e) => {
+                self.status = e.to_string();
+                self.mode = Mode::Browse;
+            }
+        }
+    }
+
+    // Load the current column again, keeping the filter and the cursor
+    fn reload(&mut self, repository: &dyn WilayahRepository) {
+        let column = self.column();
+        match load_rows(repository, column.level, column.parent_id) {
+            Ok(rows) => {
+                let column = self.column_mut();
+                column.rows = rows;
+                column.move_by(0);
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    // The selected row of every level, province first, with the full codes
+    fn detail(&self) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        let mut current = None;
+        for column in &self.columns {
+            let Some(row) = column.current() else {
+                break;
+            };
+            lines.push(Line::from(format!(
+                "{:<9} {:<14} {}",
+                column.level.as_str(),
+                row.code,
+                row.name
+            )));
+            current = Some(row);
+        }
+        if let Some(row) = current {
+            lines.push(Line::from(""));
+            lines.push(Line::from(format!("code      {}", row.code)));
+            lines.push(Line::from(format!(
+                "digits    {}",
+                row.code.replace('.', "")
+            )));
+            lines.push(Line::from(format!("id        {}", row.id)));
+            if let Some(version) = row.version {
+                lines.push(Line::from(format!("version   {}", version)));
+            }
+        }
+        lines
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [list, detail] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main);
+
+        let column = self.column();
+        let visible = column.visible();
+        let mut title = format!(
+            " {} ({}/{})",
+            column.level.table(),
+            visible.len(),
+            column.rows.len()
+        );
+        if !column.filter.is_empty() {
+            title.push_str(&format!(" /{}", column.filter));
+        }
+        title.push(' ');
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|row| ListItem::new(format!("{:<14} {}", row.code, row.name)))
+            .collect();
+        let mut state = ListState::default().with_selected(Some(column.selected));
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(title))
+                .highlight_style(Style::new().reversed()),
+            list,
+            &mut state,
+        );
+        frame.render_widget(
+            Paragraph::new(self.detail()).block(Block::bordered().title(" detail ")),
+            detail,
+        );
+
+        let text = match &self.mode {
+            Mode::Filter => format!("/{}", column.filter),
+            Mode::Edit(name) if self.status.is_empty() => format!("name: {}", name),
+            Mode::Edit(name) => format!("name: {}  ({})", name, self.status),
+            Mode::Browse if !self.status.is_empty() => self.status.clone(),
+            Mode::Browse if self.read_only => {
+                "↑↓ move  ⏎ open  ⌫ back  / filter  q quit  (read-only)".to_string()
+            }
+            Mode::Browse => "↑↓ move  ⏎ open  ⌫ back  / filter  e rename  q quit".to_string(),
+        };
+        frame.render_widget(Paragraph::new(text), footer);
+    }
+}
+
+pub fn run(repository: &dyn WilayahRepository, read_only: bool) -> io::Result<()> {
+    let mut browser = Browser::new(repository, read_only).map_err(io::Error::other)?;
+    let mut terminal = ratatui::init();
+    let result = (|| loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if !browser.handle_key(repository, key) {
+                return Ok(());
+            }
+        }
+    })();
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    fn press(browser: &mut Browser, db: &Database, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\x08' => KeyCode::Backspace,
+                '\x1b' => KeyCode::Esc,
+                c => KeyCode::Char(c),
+            };
+            browser.handle_key(db, KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    fn fixture() -> Database {
+        let db = Database::new(":memory:").unwrap();
+        for (code, name) in [
+            ("32", "JAWA BARAT"),
+            ("32.04", "KAB. BANDUNG"),
+            ("32.73", "KOTA BANDUNG"),
+            ("32.79", "KOTA BANJAR"),
+        ] {
+            db.upsert_by_code(Level::from_code(code).unwrap(), code, name)
+                .unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn filters_and_renames() {
+        let db = fixture();
+        let mut browser = Browser::new(&db, false).unwrap();
+
+        press(&mut browser, &db, "\n/banj\n");
+        assert_eq!(browser.column().visible().len(), 1);
+        assert_eq!(browser.column().current().unwrap().code, "32.79");
+        assert_eq!(
+            browser.detail()[0].to_string().trim_end(),
+            "province  32             JAWA BARAT"
+        );
+
+        // Rejected by validation: the editor stays open with the errors
+        press(
+            &mut browser,
+            &db,
+            "e\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\n",
+        );
+        assert!(matches!(browser.mode, Mode::Edit(_)));
+        assert!(browser.status.starts_with("name:"));
+
+        press(&mut browser, &db, "KOTA BANJAR\n");
+        assert_eq!(browser.mode, Mode::Browse);
+        assert_eq!(db.get_one_regency(3).unwrap().unwrap().version, Some(2));
+
+        // Changed behind the browser's back
+        db.upsert_by_code(Level::Regency, "32.79", "BANJAR")
+            .unwrap();
+        press(&mut browser, &db, "e!\n");
+        assert!(browser.status.contains("changed elsewhere"));
+        assert_eq!(browser.column().current().unwrap().name, "BANJAR");
+
+        press(&mut browser, &db, "\x1b\x08");
+        assert_eq!(browser.columns.len(), 1);
+    }
+
+    #[test]
+    fn refuses_edits_when_read_only() {
+        let db = fixture();
+        let mut browser = Browser::new(&db, true).unwrap();
+        press(&mut browser, &db, "e");
+        assert_eq!(browser.mode, Mode::Browse);
+        assert_eq!(browser.status, "Read-only");
+    }
+}
@@ -0,0 +1,226 @@
+use crate::db::DatabaseError;
+use crate::models::{CreateWebhook, FieldError, Level};
+
+// Parent row referenced by a regency, district or village, as looked up in
+// the database
+pub struct Parent<'a> {
+    pub field: &'static str,
+    pub id: Option<i64>,
+    // None when no row with `id` exists
+    pub code: Option<&'a str>,
+}
+
+// Digits per code segment of each level: 32 / 32.04 / 32.04.08 / 32.04.08.2002
+pub fn segment_lengths(level: Level) -> &'static [usize] {
+    match level {
+        Level::Province => &[2],
+        Level::Regency => &[2, 2],
+        Level::District => &[2, 2, 2],
+        Level::Village => &[2, 2, 2, 4],
+    }
+}
+
+pub fn is_valid_code(level: Level, code: &str) -> bool {
+    let segments: Vec<&str> = code.split('.').collect();
+    let lengths = segment_lengths(level);
+    segments.len() == lengths.len()
+        && segments.iter().zip(lengths).all(|(segment, len)| {
+            segment.len() == *len && segment.bytes().all(|b| b.is_ascii_digit())
+        })
+}
+
+// Check the fields of a row of `level` and its place in the hierarchy.
+// Every problem is reported, not only the first one.
+pub fn validate(
+    level: Level,
+    code: &str,
+    name: &str,
+    parent: Option<Parent>,
+) -> Result<(), DatabaseError> {
+    let mut errors = Vec::new();
+
+    if !is_valid_code(level, code) {
+        let format = segment_lengths(level)
+            .iter()
+            .map(|len| "9".repeat(*len))
+            .collect::<Vec<_>>()
+            .join(".");
+        errors.push(FieldError::new(
+            "code",
+            format!("must be a {} code formatted as {}", level.as_str(), format),
+        ));
+    }
+
+    if name.trim().is_empty() {
+        errors.push(FieldError::new("name", "must not be empty"));
+    }
+
+    if let Some(parent) = parent {
+        match (parent.id, parent.code) {
+            (None, _) => errors.push(FieldError::new(parent.field, "is required")),
+            (Some(id), None) => errors.push(FieldError::new(
+                parent.field,
+                format!("no row with id {} exists", id),
+            )),
+            (Some(_), Some(parent_code)) => {
+                let prefix = format!("{}.", parent_code);
+                if !code.starts_with(&prefix) {
+                    errors.push(FieldError::new(
+                        "code",
+                        format!("must start with the parent code {}", prefix),
+                    ));
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(DatabaseError::Validation(errors))
+    }
+}
+
+// Fields of a new webhook subscription. Where the URL may point to is up to
+// the server's delivery policy.
+pub fn validate_webhook(webhook: &CreateWebhook) -> Result<(), DatabaseError> {
+    let mut errors = Vec::new();
+    if !(webhook.url.starts_with("http://") || webhook.url.starts_with("https://")) {
+        errors.push(FieldError::new("url", "must be an http:// or https:// URL"));
+    }
+    if webhook.secret.len() < 16 {
+        errors.push(FieldError::new("secret", "must be at least 16 characters"));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(DatabaseError::Validation(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ChangeFilter;
+
+    fn errors(result: Result<(), DatabaseError>) -> Vec<(String, String)> {
+        match result {
+            Err(DatabaseError::Validation(errors)) => errors
+                .into_iter()
+                .map(|error| (error.field, error.message))
+                .collect(),
+            other => panic!("expected validation errors, got {:?}", other),
+        }
+    }
+
+    fn parent(id: Option<i64>, code: Option<&str>) -> Option<Parent<'_>> {
+        Some(Parent {
+            field: "regency_id",
+            id,
+            code,
+        })
+    }
+
+    #[test]
+    fn checks_code_shape_per_level() {
+        assert!(is_valid_code(Level::Province, "32"));
+        assert!(is_valid_code(Level::Regency, "32.04"));
+        assert!(is_valid_code(Level::District, "32.04.08"));
+        assert!(is_valid_code(Level::Village, "32.04.08.2002"));
+
+        for code in ["3", "320", "3A", "JB", "", "32.", "٣٢"] {
+            assert!(!is_valid_code(Level::Province, code), "{:?}", code);
+        }
+        assert!(!is_valid_code(Level::Regency, "32"));
+        assert!(!is_valid_code(Level::Regency, "32.4"));
+        assert!(!is_valid_code(Level::District, "32.04.8A"));
+        assert!(!is_valid_code(Level::Village, "32.04.08.202"));
+        assert!(!is_valid_code(Level::Village, "32.04.08.2002.1"));
+    }
+
+    #[test]
+    fn accepts_a_well_formed_row() {
+        assert!(validate(Level::Province, "32", "Jawa Barat", None).is_ok());
+        assert!(validate(
+            Level::District,
+            "32.04.08",
+            "Bojongsoang",
+            parent(Some(1), Some("32.04"))
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let found = errors(validate(Level::Province, "3A", "  ", None));
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "code".to_string(),
+                    "must be a province code formatted as 99".to_string()
+                ),
+                ("name".to_string(), "must not be empty".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_the_parent() {
+        let missing = errors(validate(
+            Level::District,
+            "32.04.08",
+            "X",
+            parent(None, None),
+        ));
+        assert_eq!(
+            missing,
+            vec![("regency_id".to_string(), "is required".to_string())]
+        );
+
+        let unknown = errors(validate(
+            Level::District,
+            "32.04.08",
+            "X",
+            parent(Some(99), None),
+        ));
+        assert_eq!(
+            unknown,
+            vec![(
+                "regency_id".to_string(),
+                "no row with id 99 exists".to_string()
+            )]
+        );
+
+        let elsewhere = errors(validate(
+            Level::District,
+            "32.04.08",
+            "X",
+            parent(Some(1), Some("32.73")),
+        ));
+        assert_eq!(
+            elsewhere,
+            vec![(
+                "code".to_string(),
+                "must start with the parent code 32.73.".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn checks_webhook_fields() {
+        let webhook = |url: &str, secret: &str| CreateWebhook {
+            url: url.to_string(),
+            secret: secret.to_string(),
+            filter: ChangeFilter::default(),
+        };
+
+        assert!(validate_webhook(&webhook("https://example.com/hook", "0123456789abcdef")).is_ok());
+        let fields: Vec<String> = errors(validate_webhook(&webhook("ftp://example.com", "short")))
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect();
+        assert_eq!(fields, vec!["url", "secret"]);
+    }
+}
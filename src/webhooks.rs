@@ -0,0 +1,433 @@
+use actix_web::web;
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use sha2::Sha256;
+use std::error::Error as _;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::db::{Database, DatabaseError};
+use crate::models::{FieldError, PendingDelivery};
+
+pub const SIGNATURE_HEADER: &str = "X-Wilayah-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Wilayah-Timestamp";
+pub const EVENT_HEADER: &str = "X-Wilayah-Event";
+pub const DELIVERY_HEADER: &str = "X-Wilayah-Delivery";
+
+// How often the worker looks for new changes and due retries
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BATCH_SIZE: usize = 100;
+
+// Attempts before a delivery is marked failed. Retries wait 10s, 20s, 40s, ...
+// up to MAX_BACKOFF_SECS.
+const MAX_ATTEMPTS: i64 = 8;
+const BASE_BACKOFF_SECS: u64 = 10;
+const MAX_BACKOFF_SECS: u64 = 3600;
+
+// `sha256=<hex>` of HMAC-SHA256 over "<timestamp>.<body>". Receivers
+// recompute it with their secret and reject stale timestamps.
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// Delay before the retry following `attempts` failed attempts, None once
+// the delivery should be given up
+fn backoff(attempts: i64) -> Option<u64> {
+    if attempts >= MAX_ATTEMPTS {
+        return None;
+    }
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    Some((BASE_BACKOFF_SECS << exponent).min(MAX_BACKOFF_SECS))
+}
+
+// Where deliveries may go. With an allowlist only its hosts are accepted,
+// at any address. Without one any host is, as long as it resolves to public
+// addresses only, so a webhook cannot make the server post into its own
+// network.
+#[derive(Debug, Clone, Default)]
+pub struct UrlPolicy {
+    allowed_hosts: Vec<String>,
+}
+
+impl UrlPolicy {
+    pub fn new(allowed_hosts: Vec<String>) -> UrlPolicy {
+        let allowed_hosts = allowed_hosts
+            .into_iter()
+            .map(|host| host.trim().to_ascii_lowercase())
+            .filter(|host| !host.is_empty())
+            .collect();
+        UrlPolicy { allowed_hosts }
+    }
+
+    fn allows(&self, host: &str) -> bool {
+        self.allowed_hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }
+
+    // Checked when a webhook is created and before every delivery. Names
+    // are resolved only when delivering, by the client of `client`.
+    pub fn check(&self, url: &str) -> Result<(), FieldError> {
+        let refuse = |message: &str| Err(FieldError::new("url", message));
+        let Ok(url) = reqwest::Url::parse(url) else {
+            return refuse("must be an http:// or https:// URL");
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            return refuse("must be an http:// or https:// URL");
+        }
+        let Some(host) = url.host_str() else {
+            return refuse("must have a host");
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if self.allows(host) {
+            return Ok(());
+        }
+        if !self.allowed_hosts.is_empty() {
+            return refuse("host is not in WEBHOOK_ALLOWED_HOSTS");
+        }
+        let local = host.eq_ignore_ascii_case("localhost")
+            || host.to_ascii_lowercase().ends_with(".localhost");
+        match host.parse::<IpAddr>() {
+            Ok(ip) if !is_public(ip) => {
+                refuse("must not point to a loopback, private or link-local address")
+            }
+            _ if local => refuse("must not point to a loopback, private or link-local address"),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Not loopback, private, link-local, shared, documentation, multicast or
+// unspecified
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // 100.64.0.0/10, carrier-grade NAT
+                || (a == 100 && b & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // fc00::/7 unique local, fe80::/10 link-local
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+// Resolves like the system, but fails for names outside the allowlist that
+// resolve to a non-public address
+struct PublicResolver {
+    policy: UrlPolicy,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let checked = !self.policy.allows(&host);
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if let Some(addr) = addrs.iter().find(|addr| checked && !is_public(addr.ip())) {
+                return Err(
+                    format!("{} resolves to non-public address {}", host, addr.ip()).into(),
+                );
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+// HTTP client of the worker. Redirects are not followed, they could lead
+// anywhere.
+pub fn client(policy: &UrlPolicy) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver {
+            policy: policy.clone(),
+        }))
+        .build()
+        .expect("Failed to build webhook HTTP client")
+}
+
+pub fn spawn_worker(db: web::Data<Database>, policy: UrlPolicy) {
+    actix_rt::spawn(async move {
+        let client = client(&policy);
+        info!("Webhook worker started");
+        loop {
+            if let Err(e) = run_once(&db, &client, &policy).await {
+                warn!("Webhook worker failed: {}", e);
+            }
+            actix_rt::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+// Queue deliveries for new changes, then attempt every due delivery once
+pub async fn run_once(
+    db: &Database,
+    client: &reqwest::Client,
+    policy: &UrlPolicy,
+) -> Result<(), DatabaseError> {
+    while db.enqueue_webhook_deliveries(BATCH_SIZE)? {}
+
+    loop {
+        let due = db.due_webhook_deliveries(BATCH_SIZE)?;
+        if due.is_empty() {
+            return Ok(());
+        }
+        for delivery in &due {
+            let attempts = delivery.attempts + 1;
+            match send(client, policy, delivery).await {
+                Ok(status) if (200..300).contains(&status) => {
+                    db.mark_webhook_delivered(delivery.id, status)?
+                }
+                Ok(status) => db.mark_webhook_failed(
+                    delivery.id,
+                    Some(status),
+                    &format!("receiver answered {}", status),
+                    backoff(attempts),
+                )?,
+                Err(e) => db.mark_webhook_failed(delivery.id, None, &e, backoff(attempts))?,
+            }
+        }
+        // A short batch means every due delivery was attempted
+        if due.len() < BATCH_SIZE {
+            return Ok(());
+        }
+    }
+}
+
+async fn send(
+    client: &reqwest::Client,
+    policy: &UrlPolicy,
+    delivery: &PendingDelivery,
+) -> Result<u16, String> {
+    // Again, in case the policy changed since the webhook was created
+    if let Err(refused) = policy.check(&delivery.url) {
+        return Err(format!("refused: url {}", refused.message));
+    }
+    let body = serde_json::to_vec(&delivery.event).unwrap_or_default();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let response = client
+        .post(&delivery.url)
+        .header("Content-Type", "application/json")
+        .header(EVENT_HEADER, &delivery.event.event)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, sign(&delivery.secret, timestamp, &body))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| {
+            // The cause, such as a refused address, is in the sources
+            let mut message = e.to_string();
+            let mut source = e.source();
+            while let Some(cause) = source {
+                message = format!("{}: {}", message, cause);
+                source = cause.source();
+            }
+            message
+        })?;
+    Ok(response.status().as_u16())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ChangeFilter, CreateWebhook, DeliveryStatus, Province};
+    use actix_web::http::header::HeaderMap;
+    use actix_web::{App, HttpRequest, HttpResponse, HttpServer};
+    use std::sync::{Arc, Mutex};
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, web::Bytes)>>>;
+
+    // Local receiver answering every POST with `status`
+    fn receiver(status: u16) -> (String, Received) {
+        let received: Received = Arc::default();
+        let log = received.clone();
+        let server = HttpServer::new(move || {
+            let log = log.clone();
+            App::new().default_service(web::to(move |req: HttpRequest, body: web::Bytes| {
+                log.lock().unwrap().push((req.headers().clone(), body));
+                let status = actix_web::http::StatusCode::from_u16(status).unwrap();
+                async move { HttpResponse::build(status).finish() }
+            }))
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let url = format!("http://{}/hook", server.addrs()[0]);
+        actix_rt::spawn(server.run());
+        (url, received)
+    }
+
+    fn subscribe(db: &Database, url: &str) -> i64 {
+        db.create_webhook(&CreateWebhook {
+            url: url.to_string(),
+            secret: "0123456789abcdef".to_string(),
+            filter: ChangeFilter {
+                levels: Vec::new(),
+                code_prefix: Some("32".to_string()),
+            },
+        })
+        .unwrap()
+        .id
+    }
+
+    // The receivers listen on loopback
+    fn local() -> UrlPolicy {
+        UrlPolicy::new(vec!["127.0.0.1".to_string()])
+    }
+
+    fn create_province(db: &Database, code: &str, name: &str) {
+        db.create_province(&Province {
+            id: None,
+            version: None,
+            code: code.to_string(),
+            name: name.to_string(),
+        })
+        .unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn delivers_signed_events() {
+        let (url, received) = receiver(204);
+        let db = Database::new(":memory:").unwrap();
+        create_province(&db, "31", "DKI JAKARTA");
+        let webhook_id = subscribe(&db, &url);
+        create_province(&db, "32", "JAWA BARAT");
+        create_province(&db, "51", "BALI");
+
+        run_once(&db, &reqwest::Client::new(), &local())
+            .await
+            .unwrap();
+
+        // Only 32 is both newer than the subscription and under its prefix
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (headers, body) = &received[0];
+        let header = |name: &str| headers.get(name).unwrap().to_str().unwrap();
+        assert_eq!(header(EVENT_HEADER), "province.created");
+        let timestamp: u64 = header(TIMESTAMP_HEADER).parse().unwrap();
+        assert_eq!(
+            header(SIGNATURE_HEADER),
+            sign("0123456789abcdef", timestamp, body)
+        );
+        let event: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(event["code"], "32");
+        assert_eq!(event["data"]["name"], "JAWA BARAT");
+
+        let log = db.get_webhook_deliveries(webhook_id, None, 10).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].status, DeliveryStatus::Delivered);
+        assert_eq!(log[0].response_status, Some(204));
+    }
+
+    #[actix_rt::test]
+    async fn failed_deliveries_are_retried_later() {
+        let (url, received) = receiver(500);
+        let db = Database::new(":memory:").unwrap();
+        let webhook_id = subscribe(&db, &url);
+        create_province(&db, "32", "JAWA BARAT");
+
+        let client = reqwest::Client::new();
+        run_once(&db, &client, &local()).await.unwrap();
+        // The retry is not due yet
+        run_once(&db, &client, &local()).await.unwrap();
+
+        assert_eq!(received.lock().unwrap().len(), 1);
+        let log = db.get_webhook_deliveries(webhook_id, None, 10).unwrap();
+        assert_eq!(log[0].status, DeliveryStatus::Pending);
+        assert_eq!(log[0].attempts, 1);
+        assert_eq!(log[0].response_status, Some(500));
+        assert!(log[0].next_attempt_at.is_some());
+    }
+
+    #[actix_rt::test]
+    async fn refuses_private_addresses() {
+        let (url, received) = receiver(204);
+        let db = Database::new(":memory:").unwrap();
+        let webhook_id = subscribe(&db, &url);
+        create_province(&db, "32", "JAWA BARAT");
+
+        let policy = UrlPolicy::default();
+        run_once(&db, &client(&policy), &policy).await.unwrap();
+
+        assert!(received.lock().unwrap().is_empty());
+        let log = db.get_webhook_deliveries(webhook_id, None, 10).unwrap();
+        assert_eq!(log[0].status, DeliveryStatus::Pending);
+        let error = log[0].last_error.as_deref().unwrap();
+        assert!(
+            error.starts_with("refused: url must not point"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn checks_urls_against_the_policy() {
+        let open = UrlPolicy::default();
+        assert!(open.check("https://hooks.example.com/wilayah").is_ok());
+        assert!(open.check("http://93.184.216.34:8080/").is_ok());
+        for url in [
+            "ftp://hooks.example.com/",
+            "not a url",
+            "http://localhost:9000/",
+            "http://api.localhost/",
+            "http://127.0.0.1/",
+            "http://10.1.2.3/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://100.64.0.1/",
+            "http://0.0.0.0/",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:192.168.0.1]/",
+        ] {
+            assert!(open.check(url).is_err(), "{}", url);
+        }
+
+        let allowlist =
+            UrlPolicy::new(vec![" Hooks.Internal ".to_string(), "10.1.2.3".to_string()]);
+        assert!(allowlist.check("https://hooks.internal/wilayah").is_ok());
+        assert!(allowlist.check("http://10.1.2.3/").is_ok());
+        let refused = allowlist.check("https://hooks.example.com/").unwrap_err();
+        assert_eq!(refused.message, "host is not in WEBHOOK_ALLOWED_HOSTS");
+    }
+
+    #[test]
+    fn backoff_doubles_until_given_up() {
+        assert_eq!(backoff(1), Some(10));
+        assert_eq!(backoff(2), Some(20));
+        assert_eq!(backoff(3), Some(40));
+        assert_eq!(backoff(7), Some(640));
+        assert_eq!(backoff(MAX_ATTEMPTS), None);
+    }
+}
@@ -0,0 +1,201 @@
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use serde_json::json;
+
+use crate::common::{admin, admin_get, get, send, Fixture, MISSING};
+
+#[actix_web::test]
+async fn reports_quality() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = admin_get(&app, "/admin/quality").await;
+    assert_eq!(reply.status, StatusCode::OK);
+    let report = reply.json();
+    assert_eq!(report["ok"], true);
+    assert_eq!(report["errors"], 0);
+    assert_eq!(report["integrity_check"], json!(["ok"]));
+
+    fixture.execute(
+        "PRAGMA foreign_keys = OFF;
+        UPDATE districts SET regency_id = 999 WHERE code = '32.73.02'",
+    );
+    let report = admin_get(&app, "/admin/quality").await.json();
+    assert_eq!(report["ok"], false);
+    assert_eq!(report["counts"]["orphan"], 1);
+    assert_eq!(report["foreign_key_check"][0]["table"], "districts");
+}
+
+#[actix_web::test]
+async fn reports_cache_stats() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    get(&app, "/provinces").await;
+    get(&app, "/provinces").await;
+    let reply = admin_get(&app, "/admin/cache").await;
+    assert_eq!(reply.status, StatusCode::OK);
+    let stats = reply.json();
+    assert_eq!(stats["hits"], 1);
+    assert_eq!(stats["misses"], 1);
+    assert_eq!(stats["capacity"], 100);
+    assert_eq!(stats["ttl_secs"], 300);
+}
+
+#[actix_web::test]
+async fn downloads_backup() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    for uri in ["/admin/backup", "/admin/backup?vacuum=true"] {
+        // The file holds the webhook secrets
+        let anonymous = get(&app, uri).await;
+        assert_eq!(anonymous.status, StatusCode::UNAUTHORIZED);
+        assert!(!anonymous.body.starts_with(b"SQLite format 3"));
+
+        let reply = admin_get(&app, uri).await;
+        assert_eq!(reply.status, StatusCode::OK);
+        assert_eq!(
+            reply.header("content-type"),
+            Some("application/vnd.sqlite3")
+        );
+        let disposition = reply.header("content-disposition").unwrap();
+        assert!(disposition.starts_with("attachment; filename=\"wilayah-"));
+        assert!(reply.body.starts_with(b"SQLite format 3\0"));
+    }
+}
+
+#[actix_web::test]
+async fn requires_the_admin_token() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    for uri in ["/admin/quality", "/admin/cache", "/admin/webhooks"] {
+        let anonymous = get(&app, uri).await;
+        assert_eq!(anonymous.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            anonymous.json(),
+            json!({"error": "Missing or wrong admin token"})
+        );
+        let wrong = send(
+            &app,
+            TestRequest::get()
+                .uri(uri)
+                .insert_header(("Authorization", "Bearer not-the-token")),
+        )
+        .await;
+        assert_eq!(wrong.status, StatusCode::UNAUTHORIZED);
+        assert!(!wrong.body.starts_with(b"SQLite format 3"));
+    }
+    let created = send(
+        &app,
+        TestRequest::post().uri("/admin/webhooks").set_json(json!({
+            "url": "https://cache.internal/hooks",
+            "secret": "0123456789abcdef"
+        })),
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(admin_get(&app, "/admin/webhooks").await.json(), json!([]));
+}
+
+#[actix_web::test]
+async fn manages_webhooks() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let invalid = send(
+        &app,
+        admin(TestRequest::post().uri("/admin/webhooks").set_json(json!({
+            "url": "ftp://cache.internal/hooks",
+            "secret": "short"
+        }))),
+    )
+    .await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        invalid.json()["fields"],
+        json!([
+            {"field": "url", "message": "must be an http:// or https:// URL"},
+            {"field": "secret", "message": "must be at least 16 characters"}
+        ])
+    );
+
+    let private = send(
+        &app,
+        admin(TestRequest::post().uri("/admin/webhooks").set_json(json!({
+            "url": "http://169.254.169.254/latest/meta-data/",
+            "secret": "0123456789abcdef"
+        }))),
+    )
+    .await;
+    assert_eq!(private.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        private.json()["fields"],
+        json!([{
+            "field": "url",
+            "message": "must not point to a loopback, private or link-local address"
+        }])
+    );
+
+    let no_secret = send(
+        &app,
+        admin(
+            TestRequest::post()
+                .uri("/admin/webhooks")
+                .set_json(json!({"url": "https://cache.internal/hooks"})),
+        ),
+    )
+    .await;
+    assert_eq!(no_secret.status, StatusCode::BAD_REQUEST);
+
+    let created = send(
+        &app,
+        admin(TestRequest::post().uri("/admin/webhooks").set_json(json!({
+            "url": "https://cache.internal/hooks",
+            "secret": "0123456789abcdef",
+            "levels": ["village"],
+            "code_prefix": "32."
+        }))),
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    let webhook = created.json();
+    assert!(webhook.get("secret").is_none());
+    assert_eq!(webhook["levels"], json!(["village"]));
+    let id = webhook["id"].as_i64().unwrap();
+
+    let listed = admin_get(&app, "/admin/webhooks").await;
+    assert_eq!(listed.status, StatusCode::OK);
+    assert_eq!(listed.json(), json!([webhook]));
+
+    let one = admin_get(&app, &format!("/admin/webhooks/{}", id)).await;
+    assert_eq!(one.status, StatusCode::OK);
+    assert_eq!(one.json(), webhook);
+
+    let deliveries = admin_get(&app, &format!("/admin/webhooks/{}/deliveries", id)).await;
+    assert_eq!(deliveries.status, StatusCode::OK);
+    assert_eq!(deliveries.json(), json!([]));
+
+    let deleted = send(
+        &app,
+        admin(TestRequest::delete().uri(&format!("/admin/webhooks/{}", id))),
+    )
+    .await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+
+    for uri in [
+        format!("/admin/webhooks/{}", id),
+        format!("/admin/webhooks/{}/deliveries", MISSING),
+    ] {
+        let missing = admin_get(&app, &uri).await;
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+        assert_eq!(missing.json(), json!({"error": "Webhook not found"}));
+    }
+    let again = send(
+        &app,
+        admin(TestRequest::delete().uri(&format!("/admin/webhooks/{}", id))),
+    )
+    .await;
+    assert_eq!(again.status, StatusCode::NOT_FOUND);
+}
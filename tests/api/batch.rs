@@ -0,0 +1,158 @@
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use serde_json::json;
+
+use crate::common::{send, Fixture, HEGARMANAH};
+
+#[actix_web::test]
+async fn resolves_codes_and_ids() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = send(
+        &app,
+        TestRequest::post().uri("/batch/resolve").set_json(json!({
+            "items": [
+                "32.04",
+                {"level": "village", "id": HEGARMANAH},
+                "32.77",
+                "x.y.z.w.v",
+                "JB.04"
+            ]
+        })),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(
+        reply.json()["results"],
+        json!({
+            "32.04": {
+                "status": "found",
+                "level": "regency",
+                "province_code": "32",
+                "province_name": "JAWA BARAT",
+                "city_code": "32.04",
+                "city_name": "KAB. BANDUNG"
+            },
+            "32.77": {"status": "not_found"},
+            "village:3": {
+                "status": "found",
+                "level": "village",
+                "province_code": "32",
+                "province_name": "JAWA BARAT",
+                "city_code": "32.73",
+                "city_name": "KOTA BANDUNG",
+                "region_code": "32.73.02",
+                "region_name": "Cidadap",
+                "village_code": "32.73.02.1001",
+                "village_name": "Hegarmanah"
+            },
+            "x.y.z.w.v": {"status": "invalid"},
+            "JB.04": {"status": "invalid"}
+        })
+    );
+}
+
+#[actix_web::test]
+async fn rejects_oversized_and_malformed_batches() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let items = vec!["32"; 50_001];
+    let oversized = send(
+        &app,
+        TestRequest::post()
+            .uri("/batch/resolve")
+            .set_json(json!({ "items": items })),
+    )
+    .await;
+    assert_eq!(oversized.status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        oversized.json(),
+        json!({"error": "At most 50000 items can be resolved per request"})
+    );
+
+    let unknown_level = send(
+        &app,
+        TestRequest::post()
+            .uri("/batch/resolve")
+            .set_json(json!({"items": [{"level": "kelurahan", "id": 1}]})),
+    )
+    .await;
+    assert_eq!(unknown_level.status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn rejects_too_many_upsert_lines() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = send(
+        &app,
+        TestRequest::post()
+            .uri("/batch/upsert")
+            .insert_header((header::CONTENT_TYPE, "application/x-ndjson"))
+            .set_payload("{}\n".repeat(200_001)),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        reply.json(),
+        json!({"error": "At most 200000 lines can be upserted per request"})
+    );
+}
+
+#[actix_web::test]
+async fn upserts_ndjson_lines() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let body = [
+        r#"{"code": "32.04.08.2003", "name": "Buahbatu"}"#,
+        r#"{"code": "32.04", "name": "KAB. BANDUNG"}"#,
+        "",
+        r#"{"code": "32.73.02", "name": "CIDADAP"}"#,
+        r#"{"code": "99.01", "name": "KAB. ENTAH"}"#,
+        "not json",
+    ]
+    .join("\n");
+    let reply = send(
+        &app,
+        TestRequest::post()
+            .uri("/batch/upsert")
+            .insert_header((header::CONTENT_TYPE, "application/x-ndjson"))
+            .set_payload(body),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+
+    let json = reply.json();
+    assert_eq!(
+        json["counts"],
+        json!({"created": 1, "invalid": 2, "unchanged": 1, "updated": 1})
+    );
+    let statuses: Vec<_> = json["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| {
+            (
+                result["line"].as_u64().unwrap(),
+                result["status"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            (1, "created"),
+            (2, "unchanged"),
+            (4, "updated"),
+            (5, "invalid"),
+            (6, "invalid")
+        ]
+    );
+    assert_eq!(json["results"][0]["id"], 4);
+    assert_eq!(json["results"][4]["errors"][0]["field"], "line");
+}
@@ -0,0 +1,161 @@
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use serde_json::json;
+
+use crate::common::{admin_get, get, send, Fixture, BALI, JAWA_BARAT};
+
+fn conditional(uri: &str, headers: &[(header::HeaderName, &str)]) -> TestRequest {
+    headers
+        .iter()
+        .fold(TestRequest::get().uri(uri), |req, (name, value)| {
+            req.insert_header((name.clone(), value.to_string()))
+        })
+}
+
+#[actix_web::test]
+async fn answers_conditional_reads() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let uri = format!("/provinces/{}", BALI);
+
+    let fresh = get(&app, &uri).await;
+    assert_eq!(fresh.header("etag"), Some("\"1\""));
+    assert_eq!(fresh.header("cache-control"), Some("public, max-age=60"));
+    let modified = fresh.header("last-modified").unwrap().to_string();
+
+    let cases: [(&[(header::HeaderName, &str)], StatusCode); 7] = [
+        (
+            &[(header::IF_NONE_MATCH, "\"1\"")],
+            StatusCode::NOT_MODIFIED,
+        ),
+        (
+            &[(header::IF_NONE_MATCH, "\"7\", W/\"1\"")],
+            StatusCode::NOT_MODIFIED,
+        ),
+        (&[(header::IF_NONE_MATCH, "*")], StatusCode::NOT_MODIFIED),
+        (&[(header::IF_NONE_MATCH, "\"2\"")], StatusCode::OK),
+        (
+            &[(header::IF_MODIFIED_SINCE, &modified)],
+            StatusCode::NOT_MODIFIED,
+        ),
+        (
+            &[(header::IF_MODIFIED_SINCE, "Mon, 01 Jan 2001 00:00:00 GMT")],
+            StatusCode::OK,
+        ),
+        // If-Modified-Since only counts without If-None-Match
+        (
+            &[
+                (header::IF_NONE_MATCH, "\"2\""),
+                (header::IF_MODIFIED_SINCE, &modified),
+            ],
+            StatusCode::OK,
+        ),
+    ];
+    for (headers, status) in cases {
+        let reply = send(&app, conditional(&uri, headers)).await;
+        assert_eq!(reply.status, status, "{:?}", headers);
+        assert_eq!(reply.header("etag"), Some("\"1\""));
+        assert_eq!(reply.body.is_empty(), status == StatusCode::NOT_MODIFIED);
+    }
+}
+
+#[actix_web::test]
+async fn writes_invalidate_cached_reads() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let bali = format!("/provinces/{}", BALI);
+    let regencies = format!("/provinces/{}/regencies", JAWA_BARAT);
+
+    let list_tag = get(&app, "/provinces")
+        .await
+        .header("etag")
+        .unwrap()
+        .to_string();
+    let regencies_tag = get(&app, &regencies)
+        .await
+        .header("etag")
+        .unwrap()
+        .to_string();
+    get(&app, &bali).await;
+    let hit = send(
+        &app,
+        conditional("/provinces", &[(header::IF_NONE_MATCH, &list_tag)]),
+    )
+    .await;
+    assert_eq!(hit.status, StatusCode::NOT_MODIFIED);
+    let stats = admin_get(&app, "/admin/cache").await.json();
+    assert_eq!(
+        (stats["hits"].as_u64(), stats["misses"].as_u64()),
+        (Some(1), Some(3))
+    );
+
+    let updated = send(
+        &app,
+        TestRequest::put()
+            .uri(&bali)
+            .set_json(json!({"code": "51", "name": "PROVINSI BALI"})),
+    )
+    .await;
+    assert_eq!(updated.status, StatusCode::NO_CONTENT);
+
+    // Every read is rebuilt, the row itself and the lists alike
+    let row = send(
+        &app,
+        conditional(&bali, &[(header::IF_NONE_MATCH, "\"1\"")]),
+    )
+    .await;
+    assert_eq!(row.status, StatusCode::OK);
+    assert_eq!(row.header("etag"), Some("\"2\""));
+    assert_eq!(row.json()["name"], "PROVINSI BALI");
+    let list = send(
+        &app,
+        conditional("/provinces", &[(header::IF_NONE_MATCH, &list_tag)]),
+    )
+    .await;
+    assert_eq!(list.status, StatusCode::OK);
+    assert_ne!(list.header("etag"), Some(list_tag.as_str()));
+    assert_eq!(list.json()[1]["name"], "PROVINSI BALI");
+    // Unrelated lists move to the new revision too
+    let other = send(
+        &app,
+        conditional(&regencies, &[(header::IF_NONE_MATCH, &regencies_tag)]),
+    )
+    .await;
+    assert_eq!(other.status, StatusCode::OK);
+
+    let stats = admin_get(&app, "/admin/cache").await.json();
+    assert_eq!(
+        (stats["hits"].as_u64(), stats["misses"].as_u64()),
+        (Some(1), Some(6))
+    );
+}
+
+#[actix_web::test]
+async fn caches_each_representation() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let read = |accept: &str| {
+        TestRequest::get()
+            .uri("/provinces")
+            .insert_header((header::ACCEPT, accept.to_string()))
+    };
+
+    let msgpack = send(&app, read("application/msgpack")).await;
+    let json = send(&app, read("application/json")).await;
+    let again = send(&app, read("application/msgpack")).await;
+    assert_eq!(msgpack.header("content-type"), Some("application/msgpack"));
+    assert_eq!(json.header("content-type"), Some("application/json"));
+    assert_eq!(again.header("content-type"), Some("application/msgpack"));
+    assert_eq!(again.body, msgpack.body);
+
+    let stats = admin_get(&app, "/admin/cache").await.json();
+    assert_eq!(
+        (
+            stats["hits"].as_u64(),
+            stats["misses"].as_u64(),
+            stats["entries"].as_u64()
+        ),
+        (Some(1), Some(2), Some(2))
+    );
+}
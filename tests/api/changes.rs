@@ -0,0 +1,153 @@
+use actix_web::body::MessageBody;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use serde_json::json;
+use std::future::poll_fn;
+use std::pin::pin;
+
+use crate::common::{get, send, Fixture, BOJONGSOANG};
+
+#[actix_web::test]
+async fn pages_through_changes() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    // 2 provinces, 3 regencies, a delete, 3 districts and 3 villages
+    let all = get(&app, "/changes?since=0").await;
+    assert_eq!(all.status, StatusCode::OK);
+    let all = all.json();
+    assert_eq!(all["changes"].as_array().unwrap().len(), 12);
+    assert_eq!(all["has_more"], false);
+    assert_eq!(all["next"], all["head"]);
+
+    let first = get(&app, "/changes?since=0&limit=5").await.json();
+    assert_eq!(first["has_more"], true);
+    assert_eq!(first["changes"][0]["level"], "province");
+    assert_eq!(first["changes"][0]["op"], "insert");
+    assert_eq!(first["changes"][0]["data"]["name"], "JAWA BARAT");
+
+    let rest = get(
+        &app,
+        &format!("/changes?since={}", first["next"].as_str().unwrap()),
+    )
+    .await;
+    let rest = rest.json();
+    assert_eq!(rest["changes"].as_array().unwrap().len(), 7);
+    assert_eq!(rest["changes"][0]["op"], "delete");
+    assert_eq!(rest["changes"][0]["code"], "32.77");
+}
+
+#[actix_web::test]
+async fn pages_through_tombstones() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let head = get(&app, "/changes?since=0").await.json()["head"].clone();
+
+    let purged = send(
+        &app,
+        TestRequest::delete().uri(&format!(
+            "/districts/{}?cascade=true&hard=true",
+            BOJONGSOANG
+        )),
+    )
+    .await;
+    assert_eq!(purged.status, StatusCode::NO_CONTENT);
+
+    // One tombstone per page, each page starting where the last one ended
+    let mut since = head.as_str().unwrap().to_string();
+    let mut tombstones = Vec::new();
+    loop {
+        let page = get(&app, &format!("/changes?since={}&limit=0", since))
+            .await
+            .json();
+        let changes = page["changes"].as_array().unwrap();
+        assert_eq!(changes.len(), 1);
+        let change = &changes[0];
+        assert_eq!(change["op"], "delete");
+        assert!(change.get("data").is_none());
+        tombstones.push(change["code"].as_str().unwrap().to_string());
+        since = page["next"].as_str().unwrap().to_string();
+        if page["has_more"] == false {
+            assert_eq!(page["next"], page["head"]);
+            break;
+        }
+    }
+    tombstones.sort();
+    assert_eq!(tombstones, ["32.04.08", "32.04.08.2001", "32.04.08.2002"]);
+
+    // At the head: nothing new, the same token back
+    let idle = get(&app, &format!("/changes?since={}", since)).await.json();
+    assert_eq!(idle["changes"], json!([]));
+    assert_eq!(idle["has_more"], false);
+    assert_eq!(idle["next"], since);
+
+    // Inserts of purged rows no longer carry data, soft-deleted ones still do
+    let all = get(&app, "/changes?since=0").await.json();
+    let inserted = |code: &str| {
+        all["changes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|change| change["op"] == "insert" && change["code"] == code)
+            .unwrap()
+            .clone()
+    };
+    assert!(inserted("32.04.08").get("data").is_none());
+    assert_eq!(inserted("32.77")["data"]["name"], "KOTA CIMAHI");
+    assert_eq!(inserted("32.73.02")["data"]["name"], "Cidadap");
+}
+
+#[actix_web::test]
+async fn rejects_bad_since_token() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    for uri in ["/changes?since=abc", "/changes?since=-1"] {
+        let reply = get(&app, uri).await;
+        assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            reply.json(),
+            json!({"error": "since must be a token returned as next or head"})
+        );
+    }
+}
+
+#[actix_web::test]
+async fn opens_event_stream() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let unknown = get(&app, "/events?level=regency,kelurahan").await;
+    assert_eq!(unknown.status, StatusCode::BAD_REQUEST);
+    assert_eq!(unknown.json(), json!({"error": "Unknown level: kelurahan"}));
+
+    let bad_since = get(&app, "/events?since=later").await;
+    assert_eq!(bad_since.status, StatusCode::BAD_REQUEST);
+
+    // The stream never ends, so only its first chunks are read
+    let res = test::call_service(
+        &app,
+        TestRequest::get()
+            .uri("/events?level=regency&since=0")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut body = pin!(res.into_body());
+    let mut received = String::new();
+    while !received.contains("32.77") {
+        let chunk = poll_fn(|cx| body.as_mut().poll_next(cx))
+            .await
+            .unwrap()
+            .unwrap_or_else(|_| panic!("failed to read the stream"));
+        received.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+    assert!(received.starts_with("retry: 3000\n\n"));
+    assert!(received.contains("event: regency.created\n"));
+    assert!(received.contains("event: regency.deleted\n"));
+    assert!(!received.contains("province."));
+}
@@ -0,0 +1,264 @@
+// Fixture database and an App wired like the server's, on SQLite or on
+// MemoryRepository
+use actix_http::Request;
+use actix_web::body::{self, MessageBody};
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::header::HeaderMap;
+use actix_web::http::StatusCode;
+use actix_web::middleware::{from_fn, Compress};
+use actix_web::test::{self, TestRequest};
+use actix_web::{web, App, Error};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+
+use wilayah::auth::AdminToken;
+use wilayah::cache::ResponseCache;
+use wilayah::models::{DeleteOptions, District, Province, Regency, Village};
+use wilayah::{negotiate, Database, MemoryRepository, WilayahRepository};
+
+// Ids of the fixture rows, in insert order
+pub const JAWA_BARAT: i64 = 1;
+// Province without regencies
+pub const BALI: i64 = 2;
+pub const KAB_BANDUNG: i64 = 1;
+pub const KOTA_BANDUNG: i64 = 2;
+// Soft-deleted, hidden from every read
+pub const KOTA_CIMAHI: i64 = 3;
+pub const BOJONGSOANG: i64 = 1;
+// District without villages
+pub const DAYEUHKOLOT: i64 = 2;
+pub const CIDADAP: i64 = 3;
+// Village named like its district
+pub const DESA_BOJONGSOANG: i64 = 1;
+pub const LENGKONG: i64 = 2;
+pub const HEGARMANAH: i64 = 3;
+
+// Id no level uses
+pub const MISSING: i64 = 999;
+
+// Bearer token of the /admin routes
+pub const ADMIN_TOKEN: &str = "fixture-admin-token";
+
+pub struct Fixture {
+    // Removed with the database file when the fixture is dropped
+    dir: Option<TempDir>,
+    // None for MemoryRepository, which only serves the per-level routes
+    db: Option<web::Data<Database>>,
+    repository: web::Data<dyn WilayahRepository>,
+    pub cache: web::Data<ResponseCache>,
+}
+
+impl Fixture {
+    // The fixture rows in an SQLite file, with every route
+    pub fn new() -> Fixture {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("wilayah.db");
+        let db = web::Data::new(Database::new(path.to_str().unwrap()).unwrap());
+        let repository: Arc<dyn WilayahRepository> = db.clone().into_inner();
+        Fixture::with(Some(dir), Some(db), repository)
+    }
+
+    // The same rows in a MemoryRepository
+    pub fn memory() -> Fixture {
+        Fixture::with(None, None, Arc::new(MemoryRepository::new()))
+    }
+
+    fn with(
+        dir: Option<TempDir>,
+        db: Option<web::Data<Database>>,
+        repository: Arc<dyn WilayahRepository>,
+    ) -> Fixture {
+        for (code, name) in [("32", "JAWA BARAT"), ("51", "BALI")] {
+            repository
+                .create_province(&Province {
+                    id: None,
+                    version: None,
+                    code: code.to_string(),
+                    name: name.to_string(),
+                })
+                .unwrap();
+        }
+        for (code, name) in [
+            ("32.04", "KAB. BANDUNG"),
+            ("32.73", "KOTA BANDUNG"),
+            ("32.77", "KOTA CIMAHI"),
+        ] {
+            repository
+                .create_regency(&Regency {
+                    id: None,
+                    version: None,
+                    code: code.to_string(),
+                    name: name.to_string(),
+                    province_id: Some(JAWA_BARAT),
+                })
+                .unwrap();
+        }
+        repository
+            .delete_regency(KOTA_CIMAHI, &DeleteOptions::default(), None)
+            .unwrap();
+        for (code, name, regency_id) in [
+            ("32.04.08", "Bojongsoang", KAB_BANDUNG),
+            ("32.04.11", "Dayeuhkolot", KAB_BANDUNG),
+            ("32.73.02", "Cidadap", KOTA_BANDUNG),
+        ] {
+            repository
+                .create_district(&District {
+                    id: None,
+                    version: None,
+                    code: code.to_string(),
+                    name: name.to_string(),
+                    regency_id: Some(regency_id),
+                })
+                .unwrap();
+        }
+        for (code, name, district_id) in [
+            ("32.04.08.2002", "Bojongsoang", BOJONGSOANG),
+            ("32.04.08.2001", "Lengkong", BOJONGSOANG),
+            ("32.73.02.1001", "Hegarmanah", CIDADAP),
+        ] {
+            repository
+                .create_village(&Village {
+                    id: None,
+                    version: None,
+                    code: code.to_string(),
+                    name: name.to_string(),
+                    district_id: Some(district_id),
+                })
+                .unwrap();
+        }
+
+        Fixture {
+            dir,
+            db,
+            repository: web::Data::from(repository),
+            cache: web::Data::new(ResponseCache::new(
+                100,
+                Duration::from_secs(300),
+                Duration::from_secs(60),
+            )),
+        }
+    }
+
+    // The SQLite file of the fixture
+    pub fn path(&self) -> PathBuf {
+        let dir = self
+            .dir
+            .as_ref()
+            .expect("only the SQLite fixture has a file");
+        dir.path().join("wilayah.db")
+    }
+
+    // Run SQL on the fixture file directly, for states the API refuses to
+    // create
+    pub fn execute(&self, sql: &str) {
+        rusqlite::Connection::open(self.path())
+            .unwrap()
+            .execute_batch(sql)
+            .unwrap();
+    }
+
+    // The server's app without webhook delivery; only the per-level routes
+    // without SQLite
+    pub async fn app(
+        &self,
+    ) -> impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = Error> {
+        test::init_service(
+            App::new()
+                .app_data(self.repository.clone())
+                .app_data(self.cache.clone())
+                .app_data(web::Data::new(AdminToken::new(Some(
+                    ADMIN_TOKEN.to_string(),
+                ))))
+                .wrap(from_fn(negotiate::negotiate))
+                .wrap(Compress::default())
+                .configure(|cfg| match &self.db {
+                    Some(db) => {
+                        cfg.app_data(db.clone());
+                        wilayah::configure_all(cfg);
+                    }
+                    None => wilayah::configure_region_routes(cfg),
+                }),
+        )
+        .await
+    }
+}
+
+// Fixture::memory under the name the per-level suites import, so main.rs
+// can run them again against MemoryRepository
+pub struct MemoryFixture(Fixture);
+
+impl MemoryFixture {
+    pub fn new() -> MemoryFixture {
+        MemoryFixture(Fixture::memory())
+    }
+
+    pub async fn app(
+        &self,
+    ) -> impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = Error> {
+        self.0.app().await
+    }
+}
+
+pub struct Reply {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: web::Bytes,
+}
+
+impl Reply {
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or_else(|e| {
+            panic!("{} is not JSON: {}", String::from_utf8_lossy(&self.body), e)
+        })
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+}
+
+pub async fn send<S, B>(app: &S, req: TestRequest) -> Reply
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let res = test::call_service(app, req.to_request()).await;
+    let status = res.status();
+    let headers = res.headers().clone();
+    let body = body::to_bytes(res.into_body())
+        .await
+        .unwrap_or_else(|_| panic!("failed to read the body"));
+    Reply {
+        status,
+        headers,
+        body,
+    }
+}
+
+pub async fn get<S, B>(app: &S, uri: &str) -> Reply
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    send(app, TestRequest::get().uri(uri)).await
+}
+
+// `req` with the fixture's admin token
+pub fn admin(req: TestRequest) -> TestRequest {
+    req.insert_header(("Authorization", format!("Bearer {}", ADMIN_TOKEN)))
+}
+
+pub async fn admin_get<S, B>(app: &S, uri: &str) -> Reply
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    send(app, admin(TestRequest::get().uri(uri))).await
+}
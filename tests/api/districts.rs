@@ -0,0 +1,195 @@
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use serde_json::json;
+
+use super::common::{get, send, Fixture, BOJONGSOANG, CIDADAP, DAYEUHKOLOT, KAB_BANDUNG, MISSING};
+
+#[actix_web::test]
+async fn gets_district() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, &format!("/districts/{}", CIDADAP)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(
+        reply.json(),
+        json!({"id": 3, "version": 1, "code": "32.73.02", "name": "Cidadap", "regency_id": 2})
+    );
+
+    let missing = get(&app, &format!("/districts/{}", MISSING)).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.json(), json!({"error": "District not found"}));
+}
+
+#[actix_web::test]
+async fn gets_district_by_code() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, "/districts/code/32.04.11").await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.json()["id"], DAYEUHKOLOT);
+
+    let missing = get(&app, "/districts/code/32.04.99").await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.json(), json!({"error": "District not found"}));
+}
+
+#[actix_web::test]
+async fn upserts_district_by_code() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let upsert = |code: &str, name: &str| {
+        TestRequest::put()
+            .uri(&format!("/districts/code/{}", code))
+            .set_json(json!({ "name": name }))
+    };
+
+    let created = send(&app, upsert("32.04.13", "Baleendah")).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.json()["level"], "district");
+    assert_eq!(created.header("location"), Some("/districts/4"));
+
+    let unchanged = send(&app, upsert("32.04.08", "Bojongsoang")).await;
+    assert_eq!(unchanged.status, StatusCode::OK);
+    assert_eq!(unchanged.json()["status"], "unchanged");
+
+    // Parent 32.77 is soft-deleted
+    let orphan = send(&app, upsert("32.77.01", "Cimahi Selatan")).await;
+    assert_eq!(orphan.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn creates_district() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let create = |body| TestRequest::post().uri("/districts").set_json(body);
+
+    let created = send(
+        &app,
+        create(json!({"code": "32.04.13", "name": "Baleendah", "regency_id": KAB_BANDUNG})),
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.json(), json!({"id": 4}));
+
+    let no_parent = send(
+        &app,
+        create(json!({"code": "32.04.13", "name": "Baleendah"})),
+    )
+    .await;
+    assert_eq!(no_parent.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        no_parent.json()["fields"],
+        json!([{"field": "regency_id", "message": "is required"}])
+    );
+
+    let wrong_type = send(
+        &app,
+        create(json!({"code": 320413, "name": "Baleendah", "regency_id": KAB_BANDUNG})),
+    )
+    .await;
+    assert_eq!(wrong_type.status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn updates_and_patches_district() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let updated = send(
+        &app,
+        TestRequest::put()
+            .uri(&format!("/districts/{}", DAYEUHKOLOT))
+            .set_json(
+                json!({"code": "32.04.12", "name": "Dayeuhkolot", "regency_id": KAB_BANDUNG}),
+            ),
+    )
+    .await;
+    assert_eq!(updated.status, StatusCode::NO_CONTENT);
+    let district = get(&app, &format!("/districts/{}", DAYEUHKOLOT)).await;
+    assert_eq!(district.json()["code"], "32.04.12");
+
+    // Its villages start with 32.04.08.
+    let recode = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!("/districts/{}", BOJONGSOANG))
+            .set_json(json!({"code": "32.04.09"})),
+    )
+    .await;
+    assert_eq!(recode.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let missing = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!("/districts/{}", MISSING))
+            .set_json(json!({"name": "X"})),
+    )
+    .await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.json(), json!({"error": "District not found"}));
+}
+
+#[actix_web::test]
+async fn deletes_district() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let childless = send(
+        &app,
+        TestRequest::delete().uri(&format!("/districts/{}?hard=true", DAYEUHKOLOT)),
+    )
+    .await;
+    assert_eq!(childless.status, StatusCode::NO_CONTENT);
+    assert_eq!(
+        get(&app, &format!("/districts/{}", DAYEUHKOLOT))
+            .await
+            .status,
+        StatusCode::NOT_FOUND
+    );
+
+    let plan = send(
+        &app,
+        TestRequest::delete().uri(&format!(
+            "/districts/{}?cascade=true&dry_run=true",
+            BOJONGSOANG
+        )),
+    )
+    .await;
+    assert_eq!(plan.status, StatusCode::OK);
+    assert_eq!(
+        plan.json()["counts"],
+        json!({"districts": 1, "villages": 2})
+    );
+
+    let stale = send(
+        &app,
+        TestRequest::delete()
+            .uri(&format!("/districts/{}?cascade=true", BOJONGSOANG))
+            .insert_header(("If-Match", "\"5\"")),
+    )
+    .await;
+    assert_eq!(stale.status, StatusCode::PRECONDITION_FAILED);
+}
+
+#[actix_web::test]
+async fn lists_villages_of_district() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, &format!("/districts/{}/villages", BOJONGSOANG)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    let names: Vec<_> = reply
+        .json()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|village| village["name"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["Bojongsoang", "Lengkong"]);
+
+    let empty = get(&app, &format!("/districts/{}/villages", DAYEUHKOLOT)).await;
+    assert_eq!(empty.status, StatusCode::OK);
+    assert_eq!(empty.json(), json!([]));
+}
@@ -0,0 +1,58 @@
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use serde_json::json;
+
+use crate::common::{get, send, Fixture};
+
+#[actix_web::test]
+async fn serves_openapi_and_docs() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let spec = get(&app, "/openapi.json").await;
+    assert_eq!(spec.status, StatusCode::OK);
+    let spec = spec.json();
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3.1"));
+    assert!(spec["paths"]["/villages/{village_id}/details"].is_object());
+
+    let docs = get(&app, "/docs").await;
+    assert_eq!(docs.status, StatusCode::OK);
+    assert!(docs
+        .header("content-type")
+        .unwrap()
+        .starts_with("text/html"));
+    assert!(docs.text().contains("/openapi.json"));
+}
+
+#[actix_web::test]
+async fn answers_graphql() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = send(
+        &app,
+        TestRequest::post().uri("/graphql").set_json(json!({
+            "query": r#"{ village(code: "32.04.08.2001") { name district { name } } }"#
+        })),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(
+        reply.json(),
+        json!({"data": {"village": {"name": "Lengkong", "district": {"name": "Bojongsoang"}}}})
+    );
+
+    let invalid = send(
+        &app,
+        TestRequest::post()
+            .uri("/graphql")
+            .set_json(json!({"query": "{ kelurahan { name } }"})),
+    )
+    .await;
+    assert_eq!(invalid.status, StatusCode::OK);
+    assert!(invalid.json()["errors"][0]["message"].is_string());
+
+    let graphiql = get(&app, "/graphql").await;
+    assert_eq!(graphiql.status, StatusCode::OK);
+    assert!(graphiql.text().contains("graphiql"));
+}
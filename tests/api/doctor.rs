@@ -0,0 +1,48 @@
+use serde_json::Value;
+use std::process::Command;
+
+use crate::common::Fixture;
+
+// `wilayah-service doctor --json` on the fixture file: exit code and report
+fn doctor(fixture: &Fixture, strict: bool) -> (Option<i32>, Value) {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_wilayah-service"));
+    command
+        .arg("--database")
+        .arg(fixture.path())
+        .args(["doctor", "--json"]);
+    if strict {
+        command.arg("--strict");
+    }
+    let output = command.output().unwrap();
+    let report = serde_json::from_slice(&output.stdout).unwrap();
+    (output.status.code(), report)
+}
+
+#[test]
+fn fails_only_on_errors_unless_strict() {
+    let fixture = Fixture::new();
+
+    // BALI has no regencies and DAYEUHKOLOT no villages
+    let (code, report) = doctor(&fixture, false);
+    assert_eq!(code, Some(0));
+    assert_eq!(report["ok"], true);
+    assert_eq!(report["warnings"], 2);
+    assert_eq!(report["counts"]["empty_level"], 2);
+
+    let (code, _) = doctor(&fixture, true);
+    assert_eq!(code, Some(1));
+}
+
+#[test]
+fn fails_on_errors() {
+    let fixture = Fixture::new();
+    fixture.execute(
+        "PRAGMA foreign_keys = OFF;
+        UPDATE villages SET district_id = 999 WHERE code = '32.73.02.1001'",
+    );
+
+    let (code, report) = doctor(&fixture, false);
+    assert_eq!(code, Some(1));
+    assert_eq!(report["ok"], false);
+    assert_eq!(report["counts"]["orphan"], 1);
+}
@@ -0,0 +1,20 @@
+// Every route of routes.rs against a fixture database, one module per group
+mod admin;
+mod batch;
+mod cache;
+mod changes;
+mod common;
+mod districts;
+mod docs;
+#[cfg(feature = "cli")]
+mod doctor;
+mod negotiation;
+mod provinces;
+mod regencies;
+#[cfg(feature = "search")]
+mod search;
+mod villages;
+
+// Loaded through #[path] so the suites it includes resolve from this directory
+#[path = "memory.rs"]
+mod memory;
@@ -0,0 +1,16 @@
+// The per-level suites again, with MemoryRepository behind the app. Loading
+// the same files twice is the point here.
+#![allow(clippy::duplicate_mod)]
+
+mod common {
+    pub use crate::common::MemoryFixture as Fixture;
+    pub use crate::common::*;
+}
+#[path = "districts.rs"]
+mod districts;
+#[path = "provinces.rs"]
+mod provinces;
+#[path = "regencies.rs"]
+mod regencies;
+#[path = "villages.rs"]
+mod villages;
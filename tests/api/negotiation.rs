@@ -0,0 +1,236 @@
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use serde_json::{json, Value};
+
+use crate::common::{get, send, Fixture, BALI, MISSING};
+
+fn accept(req: TestRequest, accept: &str) -> TestRequest {
+    req.insert_header((header::ACCEPT, accept.to_string()))
+}
+
+#[actix_web::test]
+async fn encodes_on_accept() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let bali = json!({"id": 2, "version": 1, "code": "51", "name": "BALI"});
+    let uri = format!("/provinces/{}", BALI);
+
+    let msgpack = send(
+        &app,
+        accept(TestRequest::get().uri(&uri), "application/msgpack"),
+    )
+    .await;
+    assert_eq!(msgpack.header("content-type"), Some("application/msgpack"));
+    assert_eq!(rmp_serde::from_slice::<Value>(&msgpack.body).unwrap(), bali);
+
+    let cbor = send(
+        &app,
+        accept(TestRequest::get().uri(&uri), "application/cbor"),
+    )
+    .await;
+    assert_eq!(cbor.header("content-type"), Some("application/cbor"));
+    assert_eq!(
+        ciborium::from_reader::<Value, _>(&cbor.body[..]).unwrap(),
+        bali
+    );
+
+    #[cfg(feature = "grpc")]
+    {
+        let protobuf = send(
+            &app,
+            accept(TestRequest::get().uri(&uri), "application/x-protobuf"),
+        )
+        .await;
+        assert_eq!(
+            protobuf.header("content-type"),
+            Some("application/x-protobuf")
+        );
+        // Errors have no message, so they stay JSON
+        let missing = format!("/provinces/{}", MISSING);
+        let missing = send(
+            &app,
+            accept(TestRequest::get().uri(&missing), "application/x-protobuf"),
+        )
+        .await;
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+        assert_eq!(missing.json(), json!({"error": "Province not found"}));
+    }
+}
+
+#[actix_web::test]
+async fn tags_each_encoding_apart() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let uri = format!("/provinces/{}", BALI);
+    let read = |format: &str, if_none_match: Option<&str>| {
+        let req = accept(TestRequest::get().uri(&uri), format);
+        match if_none_match {
+            Some(tag) => req.insert_header((header::IF_NONE_MATCH, tag.to_string())),
+            None => req,
+        }
+    };
+
+    let json = get(&app, &uri).await;
+    assert_eq!(json.header("etag"), Some("\"1\""));
+    let msgpack = send(&app, read("application/msgpack", None)).await;
+    assert_eq!(msgpack.header("etag"), Some("\"1-msgpack\""));
+    let list = send(
+        &app,
+        accept(TestRequest::get().uri("/provinces"), "application/cbor"),
+    )
+    .await;
+    let list_tag = list.header("etag").unwrap();
+    assert!(
+        list_tag.starts_with("\"r") && list_tag.ends_with("-cbor\""),
+        "{}",
+        list_tag
+    );
+
+    // A JSON copy is no proof of a current MessagePack copy, or the reverse
+    let other = send(&app, read("application/msgpack", Some("\"1\""))).await;
+    assert_eq!(other.status, StatusCode::OK);
+    assert_eq!(other.header("content-type"), Some("application/msgpack"));
+    let other = send(&app, read("application/json", Some("\"1-msgpack\""))).await;
+    assert_eq!(other.status, StatusCode::OK);
+
+    let same = send(&app, read("application/msgpack", Some("\"1-msgpack\""))).await;
+    assert_eq!(same.status, StatusCode::NOT_MODIFIED);
+    assert_eq!(same.header("etag"), Some("\"1-msgpack\""));
+    assert_eq!(same.header("vary"), Some("accept"));
+
+    // If-Match takes the version from a tag of any encoding
+    let update = |tag: &str| {
+        accept(
+            TestRequest::put()
+                .uri(&uri)
+                .insert_header((header::IF_MATCH, tag.to_string()))
+                .set_json(json!({"code": "51", "name": "PROVINSI BALI"})),
+            "application/msgpack",
+        )
+    };
+    let updated = send(&app, update("\"1-msgpack\"")).await;
+    assert_eq!(updated.status, StatusCode::NO_CONTENT);
+    assert_eq!(updated.header("etag"), Some("\"2-msgpack\""));
+    let stale = send(&app, update("\"1-cbor\"")).await;
+    assert_eq!(stale.status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(stale.header("etag"), Some("\"2-msgpack\""));
+}
+
+#[actix_web::test]
+async fn varies_every_response_on_accept() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let json = get(&app, "/provinces").await;
+    let tag = json.header("etag").unwrap().to_string();
+    let replies = [
+        json,
+        send(
+            &app,
+            TestRequest::get()
+                .uri("/provinces")
+                .insert_header((header::IF_NONE_MATCH, tag)),
+        )
+        .await,
+        get(&app, &format!("/provinces/{}", MISSING)).await,
+        send(
+            &app,
+            TestRequest::post().uri("/provinces").set_json(json!({})),
+        )
+        .await,
+        get(&app, "/docs").await,
+    ];
+    let statuses: Vec<_> = replies.iter().map(|reply| reply.status.as_u16()).collect();
+    assert_eq!(statuses, [200, 304, 404, 400, 200]);
+    for reply in &replies {
+        assert_eq!(reply.header("vary"), Some("accept"), "{}", reply.status);
+    }
+}
+
+#[actix_web::test]
+async fn falls_back_per_response() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let ranked = "application/x-protobuf, application/msgpack;q=0.9";
+
+    // /changes has no protobuf message, so it takes the next type
+    let changes = send(
+        &app,
+        accept(TestRequest::get().uri("/changes?since=0"), ranked),
+    )
+    .await;
+    assert_eq!(changes.status, StatusCode::OK);
+    assert_eq!(changes.header("content-type"), Some("application/msgpack"));
+    assert!(rmp_serde::from_slice::<Value>(&changes.body).is_ok());
+
+    // Writes are tagged in the format the middleware answers with
+    let updated = send(
+        &app,
+        accept(
+            TestRequest::put()
+                .uri(&format!("/provinces/{}", BALI))
+                .set_json(json!({"code": "51", "name": "PROVINSI BALI"})),
+            ranked,
+        ),
+    )
+    .await;
+    assert_eq!(updated.status, StatusCode::NO_CONTENT);
+    assert_eq!(updated.header("etag"), Some("\"2-msgpack\""));
+
+    #[cfg(feature = "grpc")]
+    {
+        let uri = format!("/provinces/{}", BALI);
+        let province = send(&app, accept(TestRequest::get().uri(&uri), ranked)).await;
+        assert_eq!(
+            province.header("content-type"),
+            Some("application/x-protobuf")
+        );
+        assert_eq!(province.header("etag"), Some("\"2-protobuf\""));
+    }
+}
+
+#[actix_web::test]
+async fn refuses_what_nobody_accepts() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let html = send(
+        &app,
+        accept(TestRequest::get().uri("/provinces"), "text/html"),
+    )
+    .await;
+    assert_eq!(html.status, StatusCode::NOT_ACCEPTABLE);
+    assert_eq!(html.header("vary"), Some("accept"));
+    assert!(html.json()["error"].is_string());
+
+    #[cfg(feature = "grpc")]
+    {
+        let changes = send(
+            &app,
+            accept(
+                TestRequest::get().uri("/changes?since=0"),
+                "application/x-protobuf",
+            ),
+        )
+        .await;
+        assert_eq!(changes.status, StatusCode::NOT_ACCEPTABLE);
+
+        // A write is refused before it changes anything
+        let created = send(
+            &app,
+            accept(
+                TestRequest::post()
+                    .uri("/provinces")
+                    .set_json(json!({"code": "99", "name": "PAPUA SELATAN"})),
+                "application/x-protobuf",
+            ),
+        )
+        .await;
+        assert_eq!(created.status, StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(
+            get(&app, "/provinces/code/99").await.status,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
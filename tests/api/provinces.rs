@@ -0,0 +1,281 @@
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use serde_json::json;
+
+use super::common::{get, send, Fixture, BALI, JAWA_BARAT, MISSING};
+
+#[actix_web::test]
+async fn lists_provinces() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, "/provinces").await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(
+        reply.json(),
+        json!([
+            {"id": 1, "version": 1, "code": "32", "name": "JAWA BARAT"},
+            {"id": 2, "version": 1, "code": "51", "name": "BALI"}
+        ])
+    );
+    let etag = reply.header("etag").unwrap().to_string();
+    assert!(reply.header("last-modified").is_some());
+
+    let cached = send(
+        &app,
+        TestRequest::get()
+            .uri("/provinces")
+            .insert_header((header::IF_NONE_MATCH, etag)),
+    )
+    .await;
+    assert_eq!(cached.status, StatusCode::NOT_MODIFIED);
+    assert!(cached.body.is_empty());
+}
+
+#[actix_web::test]
+async fn gets_one_province() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, &format!("/provinces/{}", JAWA_BARAT)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(
+        reply.json(),
+        json!({"id": 1, "version": 1, "code": "32", "name": "JAWA BARAT"})
+    );
+    assert_eq!(reply.header("etag"), Some("\"1\""));
+
+    let missing = get(&app, &format!("/provinces/{}", MISSING)).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.json(), json!({"error": "Province not found"}));
+
+    let not_an_id = get(&app, "/provinces/jabar").await;
+    assert_eq!(not_an_id.status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn gets_province_by_code() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, "/provinces/code/51").await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.json()["id"], BALI);
+
+    let missing = get(&app, "/provinces/code/99").await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.json(), json!({"error": "Province not found"}));
+}
+
+#[actix_web::test]
+async fn upserts_province_by_code() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let upsert = |code: &str, body| {
+        TestRequest::put()
+            .uri(&format!("/provinces/code/{}", code))
+            .set_json(body)
+    };
+
+    let created = send(&app, upsert("36", json!({"name": "BANTEN"}))).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.header("location"), Some("/provinces/3"));
+    assert_eq!(created.json()["status"], "created");
+
+    let unchanged = send(&app, upsert("36", json!({"name": "BANTEN"}))).await;
+    assert_eq!(unchanged.status, StatusCode::OK);
+    assert_eq!(unchanged.json()["status"], "unchanged");
+
+    let updated = send(&app, upsert("32", json!({"name": "JABAR"}))).await;
+    assert_eq!(updated.status, StatusCode::OK);
+    assert_eq!(updated.json()["status"], "updated");
+    assert_eq!(updated.header("etag"), Some("\"2\""));
+
+    let invalid = send(&app, upsert("3", json!({"name": ""}))).await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let fields: Vec<_> = invalid.json()["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field["field"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(fields, ["code", "name"]);
+
+    let no_name = send(&app, upsert("36", json!({}))).await;
+    assert_eq!(no_name.status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn creates_province() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let created = send(
+        &app,
+        TestRequest::post()
+            .uri("/provinces")
+            .set_json(json!({"code": "36", "name": "BANTEN"})),
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.json(), json!({"id": 3}));
+    assert_eq!(get(&app, "/provinces/3").await.json()["name"], "BANTEN");
+
+    let invalid = send(
+        &app,
+        TestRequest::post()
+            .uri("/provinces")
+            .set_json(json!({"code": "3.6", "name": "BANTEN"})),
+    )
+    .await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(invalid.json()["error"], "Validation failed");
+    assert_eq!(invalid.json()["fields"][0]["field"], "code");
+
+    let malformed = send(
+        &app,
+        TestRequest::post()
+            .uri("/provinces")
+            .insert_header(header::ContentType::json())
+            .set_payload("{\"code\": \"36\","),
+    )
+    .await;
+    assert_eq!(malformed.status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn updates_province() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let update = |id: i64| {
+        TestRequest::put()
+            .uri(&format!("/provinces/{}", id))
+            .set_json(json!({"code": "51", "name": "PROVINSI BALI"}))
+    };
+
+    let stale = send(
+        &app,
+        update(BALI).insert_header((header::IF_MATCH, "\"7\"")),
+    )
+    .await;
+    assert_eq!(stale.status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(stale.header("etag"), Some("\"1\""));
+
+    let updated = send(
+        &app,
+        update(BALI).insert_header((header::IF_MATCH, "\"1\"")),
+    )
+    .await;
+    assert_eq!(updated.status, StatusCode::NO_CONTENT);
+    assert_eq!(updated.header("etag"), Some("\"2\""));
+    let bali = get(&app, &format!("/provinces/{}", BALI)).await.json();
+    assert_eq!(bali["name"], "PROVINSI BALI");
+    assert_eq!(bali["version"], 2);
+
+    let missing = send(&app, update(MISSING)).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.json(), json!({"error": "Province not found"}));
+
+    // Its regencies start with 32.
+    let recode = send(
+        &app,
+        TestRequest::put()
+            .uri(&format!("/provinces/{}", JAWA_BARAT))
+            .set_json(json!({"code": "33", "name": "JAWA BARAT"})),
+    )
+    .await;
+    assert_eq!(recode.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn patches_province() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let patch = |id: i64, body| {
+        TestRequest::patch()
+            .uri(&format!("/provinces/{}", id))
+            .set_json(body)
+    };
+
+    let patched = send(&app, patch(BALI, json!({"name": "BALI DWIPA"}))).await;
+    assert_eq!(patched.status, StatusCode::NO_CONTENT);
+    let bali = get(&app, &format!("/provinces/{}", BALI)).await.json();
+    assert_eq!(bali["name"], "BALI DWIPA");
+    assert_eq!(bali["code"], "51");
+
+    let removed_code = send(&app, patch(BALI, json!({"code": null}))).await;
+    assert_eq!(removed_code.status, StatusCode::BAD_REQUEST);
+
+    let missing = send(&app, patch(MISSING, json!({"name": "X"}))).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn deletes_province() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let delete = |uri: String| TestRequest::delete().uri(&uri);
+
+    let refused = send(&app, delete(format!("/provinces/{}", JAWA_BARAT))).await;
+    assert_eq!(refused.status, StatusCode::CONFLICT);
+    assert_eq!(
+        refused.json()["children"],
+        json!({"regencies": 2, "districts": 3, "villages": 3})
+    );
+
+    let plan = send(
+        &app,
+        delete(format!(
+            "/provinces/{}?cascade=true&dry_run=true",
+            JAWA_BARAT
+        )),
+    )
+    .await;
+    assert_eq!(plan.status, StatusCode::OK);
+    assert_eq!(plan.json()["dry_run"], true);
+    assert_eq!(plan.json()["counts"]["villages"], 3);
+    assert_eq!(get(&app, "/provinces/1").await.status, StatusCode::OK);
+
+    let deleted = send(
+        &app,
+        delete(format!("/provinces/{}?cascade=true", JAWA_BARAT)),
+    )
+    .await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+    assert_eq!(
+        get(&app, "/provinces/1").await.status,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(get(&app, "/villages/1").await.status, StatusCode::NOT_FOUND);
+
+    let again = send(&app, delete(format!("/provinces/{}", JAWA_BARAT))).await;
+    assert_eq!(again.status, StatusCode::NOT_FOUND);
+    assert_eq!(again.json(), json!({"error": "Province not found"}));
+}
+
+#[actix_web::test]
+async fn lists_regencies_of_province() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    // The soft-deleted KOTA CIMAHI is left out
+    let reply = get(&app, &format!("/provinces/{}/regencies", JAWA_BARAT)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    let codes: Vec<_> = reply
+        .json()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|regency| regency["code"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(codes, ["32.04", "32.73"]);
+
+    let empty = get(&app, &format!("/provinces/{}/regencies", BALI)).await;
+    assert_eq!(empty.status, StatusCode::OK);
+    assert_eq!(empty.json(), json!([]));
+
+    let missing = get(&app, &format!("/provinces/{}/regencies", MISSING)).await;
+    assert_eq!(missing.status, StatusCode::OK);
+    assert_eq!(missing.json(), json!([]));
+}
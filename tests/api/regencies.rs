@@ -0,0 +1,292 @@
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use serde_json::json;
+
+use super::common::{
+    get, send, Fixture, JAWA_BARAT, KAB_BANDUNG, KOTA_BANDUNG, KOTA_CIMAHI, MISSING,
+};
+
+#[actix_web::test]
+async fn gets_regency() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, &format!("/regencies/{}", KOTA_BANDUNG)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(
+        reply.json(),
+        json!({
+            "id": 2,
+            "version": 1,
+            "code": "32.73",
+            "name": "KOTA BANDUNG",
+            "province_id": 1
+        })
+    );
+
+    for id in [KOTA_CIMAHI, MISSING] {
+        let missing = get(&app, &format!("/regencies/{}", id)).await;
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+        assert_eq!(missing.json(), json!({"error": "Regency not found"}));
+    }
+}
+
+#[actix_web::test]
+async fn gets_regency_by_code() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, "/regencies/code/32.04").await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.json()["id"], KAB_BANDUNG);
+
+    let deleted = get(&app, "/regencies/code/32.77").await;
+    assert_eq!(deleted.status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn upserts_regency_by_code() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let upsert = |code: &str, name: &str| {
+        TestRequest::put()
+            .uri(&format!("/regencies/code/{}", code))
+            .set_json(json!({ "name": name }))
+    };
+
+    // The soft-deleted 32.77 does not count, a new row is made
+    let created = send(&app, upsert("32.77", "KOTA CIMAHI")).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.json()["id"], 4);
+
+    let updated = send(&app, upsert("32.04", "KABUPATEN BANDUNG")).await;
+    assert_eq!(updated.status, StatusCode::OK);
+    assert_eq!(updated.json()["status"], "updated");
+
+    let orphan = send(&app, upsert("33.01", "KAB. CILACAP")).await;
+    assert_eq!(orphan.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        orphan.json()["fields"][0]["message"],
+        "no province with code 33 exists"
+    );
+}
+
+#[actix_web::test]
+async fn creates_regency() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let create = |body| TestRequest::post().uri("/regencies").set_json(body);
+
+    let created = send(
+        &app,
+        create(json!({"code": "32.01", "name": "KAB. BOGOR", "province_id": JAWA_BARAT})),
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.json(), json!({"id": 4}));
+
+    let wrong_parent = send(
+        &app,
+        create(json!({"code": "51.01", "name": "KAB. JEMBRANA", "province_id": JAWA_BARAT})),
+    )
+    .await;
+    assert_eq!(wrong_parent.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        wrong_parent.json()["fields"][0]["message"],
+        "must start with the parent code 32."
+    );
+
+    let no_parent = send(
+        &app,
+        create(json!({"code": "32.01", "name": "KAB. BOGOR", "province_id": MISSING})),
+    )
+    .await;
+    assert_eq!(no_parent.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(no_parent.json()["fields"][0]["field"], "province_id");
+
+    let not_json = send(
+        &app,
+        TestRequest::post()
+            .uri("/regencies")
+            .insert_header(header::ContentType::plaintext())
+            .set_payload("32.01,KAB. BOGOR"),
+    )
+    .await;
+    assert_eq!(not_json.status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn updates_and_patches_regency() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let updated = send(
+        &app,
+        TestRequest::put()
+            .uri(&format!("/regencies/{}", KAB_BANDUNG))
+            .set_json(
+                json!({"code": "32.04", "name": "KABUPATEN BANDUNG", "province_id": JAWA_BARAT}),
+            ),
+    )
+    .await;
+    assert_eq!(updated.status, StatusCode::NO_CONTENT);
+    assert_eq!(updated.header("etag"), Some("\"2\""));
+
+    let patched = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!("/regencies/{}", KAB_BANDUNG))
+            .insert_header((header::IF_MATCH, "\"2\""))
+            .set_json(json!({"name": "KAB. BANDUNG"})),
+    )
+    .await;
+    assert_eq!(patched.status, StatusCode::NO_CONTENT);
+    assert_eq!(patched.header("etag"), Some("\"3\""));
+
+    let stale = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!("/regencies/{}", KAB_BANDUNG))
+            .insert_header((header::IF_MATCH, "\"2\""))
+            .set_json(json!({"name": "KAB BANDUNG"})),
+    )
+    .await;
+    assert_eq!(stale.status, StatusCode::PRECONDITION_FAILED);
+
+    let deleted = send(
+        &app,
+        TestRequest::put()
+            .uri(&format!("/regencies/{}", KOTA_CIMAHI))
+            .set_json(json!({"code": "32.77", "name": "KOTA CIMAHI", "province_id": JAWA_BARAT})),
+    )
+    .await;
+    assert_eq!(deleted.status, StatusCode::NOT_FOUND);
+    assert_eq!(deleted.json(), json!({"error": "Regency not found"}));
+}
+
+#[actix_web::test]
+async fn patches_nulls_away() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let patch = |body| {
+        TestRequest::patch()
+            .uri(&format!("/regencies/{}", KAB_BANDUNG))
+            .set_json(body)
+    };
+
+    // A null member removes the field, so a required one fails validation
+    let orphaned = send(&app, patch(json!({"province_id": null}))).await;
+    assert_eq!(orphaned.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        orphaned.json()["fields"],
+        json!([{"field": "province_id", "message": "is required"}])
+    );
+    let unnamed = send(&app, patch(json!({"name": null}))).await;
+    assert_eq!(unnamed.status, StatusCode::BAD_REQUEST);
+
+    // Fields the server owns are not patched
+    let patched = send(
+        &app,
+        patch(json!({"name": "KABUPATEN BANDUNG", "id": null, "version": null})),
+    )
+    .await;
+    assert_eq!(patched.status, StatusCode::NO_CONTENT);
+    let regency = get(&app, &format!("/regencies/{}", KAB_BANDUNG))
+        .await
+        .json();
+    assert_eq!(regency["id"], KAB_BANDUNG);
+    assert_eq!(regency["version"], 2);
+    assert_eq!(regency["name"], "KABUPATEN BANDUNG");
+    assert_eq!(regency["province_id"], JAWA_BARAT);
+}
+
+#[actix_web::test]
+async fn patches_only_the_matching_version() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let patch = |if_match: &str, name: &str| {
+        TestRequest::patch()
+            .uri(&format!("/regencies/{}", KAB_BANDUNG))
+            .insert_header((header::IF_MATCH, if_match.to_string()))
+            .set_json(json!({ "name": name }))
+    };
+
+    let stale = send(&app, patch("\"7\"", "KAB BANDUNG")).await;
+    assert_eq!(stale.status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(stale.header("etag"), Some("\"1\""));
+    assert_eq!(
+        stale.json(),
+        json!({"error": "If-Match does not match the current version 1"})
+    );
+    // Weak tags never match
+    let weak = send(&app, patch("W/\"1\"", "KAB BANDUNG")).await;
+    assert_eq!(weak.status, StatusCode::PRECONDITION_FAILED);
+    let regency = get(&app, &format!("/regencies/{}", KAB_BANDUNG))
+        .await
+        .json();
+    assert_eq!(regency["name"], "KAB. BANDUNG");
+
+    let listed = send(&app, patch("\"5\", \"1\"", "KAB BANDUNG")).await;
+    assert_eq!(listed.status, StatusCode::NO_CONTENT);
+    assert_eq!(listed.header("etag"), Some("\"2\""));
+    let any = send(&app, patch("*", "KABUPATEN BANDUNG")).await;
+    assert_eq!(any.status, StatusCode::NO_CONTENT);
+    assert_eq!(any.header("etag"), Some("\"3\""));
+}
+
+#[actix_web::test]
+async fn deletes_regency() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let refused = send(
+        &app,
+        TestRequest::delete().uri(&format!("/regencies/{}", KOTA_BANDUNG)),
+    )
+    .await;
+    assert_eq!(refused.status, StatusCode::CONFLICT);
+    assert_eq!(
+        refused.json()["error"],
+        "Regency still has children, retry with cascade=true"
+    );
+
+    let deleted = send(
+        &app,
+        TestRequest::delete()
+            .uri(&format!("/regencies/{}?cascade=true", KOTA_BANDUNG))
+            .insert_header((header::IF_MATCH, "\"1\"")),
+    )
+    .await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+    let regencies = get(&app, &format!("/provinces/{}/regencies", JAWA_BARAT)).await;
+    assert_eq!(regencies.json().as_array().unwrap().len(), 1);
+
+    let missing = send(
+        &app,
+        TestRequest::delete().uri(&format!("/regencies/{}", MISSING)),
+    )
+    .await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn lists_districts_of_regency() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, &format!("/regencies/{}/districts", KAB_BANDUNG)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(
+        reply.json(),
+        json!([
+            {"id": 1, "version": 1, "code": "32.04.08", "name": "Bojongsoang", "regency_id": 1},
+            {"id": 2, "version": 1, "code": "32.04.11", "name": "Dayeuhkolot", "regency_id": 1}
+        ])
+    );
+    assert!(reply.header("etag").unwrap().starts_with("\"r"));
+
+    let deleted = get(&app, &format!("/regencies/{}/districts", KOTA_CIMAHI)).await;
+    assert_eq!(deleted.status, StatusCode::OK);
+    assert_eq!(deleted.json(), json!([]));
+}
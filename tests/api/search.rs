@@ -0,0 +1,319 @@
+use actix_web::body;
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use serde_json::json;
+
+use crate::common::{send, Fixture, BOJONGSOANG};
+
+fn csv(uri: &str, body: &'static str) -> TestRequest {
+    TestRequest::post()
+        .uri(uri)
+        .insert_header((header::CONTENT_TYPE, "text/csv"))
+        .set_payload(body)
+}
+
+#[actix_web::test]
+async fn enriches_csv() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = send(
+        &app,
+        csv("/enrich?column=kode", "nama,kode\nToko A,32.04.08.2002\n"),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert!(reply
+        .header("content-type")
+        .unwrap()
+        .starts_with("text/csv"));
+    let text = reply.text();
+    let mut lines = text.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "nama,kode,province_code,province_name,regency_code,regency_name,\
+         district_code,district_name,village_code,village_name,match_status"
+    );
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("Toko A,32.04.08.2002,32,JAWA BARAT,32.04,KAB. BANDUNG,"));
+
+    let no_column = send(&app, csv("/enrich", "nama,kode\n")).await;
+    assert_eq!(no_column.status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        no_column.json(),
+        json!({"error": "Missing key column, pass it as ?column=<header>"})
+    );
+
+    let unknown_column = send(&app, csv("/enrich?column=kecamatan", "nama,kode\n")).await;
+    assert_eq!(unknown_column.status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn streams_large_uploads() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    // Several worker chunks, so rows are resolved while the upload streams
+    let mut body = String::from("kode\n");
+    for i in 0..2500 {
+        body.push_str(if i % 2 == 0 {
+            "32.04.08.2002\n"
+        } else {
+            "Hegarmanah\n"
+        });
+    }
+    let reply = send(
+        &app,
+        TestRequest::post()
+            .uri("/enrich?column=kode")
+            .insert_header((header::CONTENT_TYPE, "text/csv"))
+            .set_payload(body),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+    let text = reply.text();
+    let rows: Vec<_> = text.lines().skip(1).collect();
+    assert_eq!(rows.len(), 2500);
+    for (i, row) in rows.iter().enumerate() {
+        let status = if i % 2 == 0 {
+            "matched_code"
+        } else {
+            "matched_name"
+        };
+        assert!(row.ends_with(status), "row {}: {}", i, row);
+    }
+}
+
+#[actix_web::test]
+async fn enriches_multipart_uploads() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let form = |parts: &[(&str, &str)]| {
+        let mut body = String::new();
+        for (name, value) in parts {
+            body.push_str(&format!(
+                "--b\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}.csv\"\r\n\r\n{}\r\n",
+                name, name, value
+            ));
+        }
+        body.push_str("--b--\r\n");
+        TestRequest::post()
+            .uri("/enrich")
+            .insert_header((header::CONTENT_TYPE, "multipart/form-data; boundary=b"))
+            .set_payload(body)
+    };
+
+    let reply = send(
+        &app,
+        form(&[("column", "desa"), ("file", "desa\nLengkong\n")]),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert!(reply.text().lines().nth(1).unwrap().starts_with(
+        "Lengkong,32,JAWA BARAT,32.04,KAB. BANDUNG,32.04.08,Bojongsoang,32.04.08.2001,"
+    ));
+
+    // The file is enriched as it arrives, so a later column field is too late
+    let late_column = send(
+        &app,
+        form(&[("file", "desa\nLengkong\n"), ("column", "desa")]),
+    )
+    .await;
+    assert_eq!(late_column.status, StatusCode::BAD_REQUEST);
+
+    let no_file = send(&app, form(&[("column", "desa")])).await;
+    assert_eq!(
+        no_file.json(),
+        json!({"error": "Missing `file` field in multipart body"})
+    );
+}
+
+#[actix_web::test]
+async fn aborts_on_a_malformed_record() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let upload = |body: &'static [u8]| {
+        TestRequest::post()
+            .uri("/enrich?column=kode")
+            .insert_header((header::CONTENT_TYPE, "text/csv"))
+            .set_payload(body)
+            .to_request()
+    };
+
+    // Before the header is read the error is still a 400
+    let header = test::call_service(&app, upload(b"\xff,kode\n")).await;
+    assert_eq!(header.status(), StatusCode::BAD_REQUEST);
+
+    // After it the response has started, so the body is cut off
+    let res = test::call_service(&app, upload(b"kode\n32.04.08.2002\n\xff\n")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(body::to_bytes(res.into_body()).await.is_err());
+}
+
+#[actix_web::test]
+async fn parses_address() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = send(
+        &app,
+        TestRequest::post().uri("/address/parse").set_json(json!({
+            "address": "Jl. Raya Bojongsoang No. 12, Ds. Lengkong, Kec. Bojongsoang, Kab. Bandung, Jawa Barat 40288"
+        })),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+    let parsed = reply.json();
+    assert_eq!(parsed["postal_code"], "40288");
+    assert_eq!(parsed["village"]["code"], "32.04.08.2001");
+    assert_eq!(parsed["regency"]["code"], "32.04");
+
+    let no_address = send(
+        &app,
+        TestRequest::post()
+            .uri("/address/parse")
+            .set_json(json!({})),
+    )
+    .await;
+    assert_eq!(no_address.status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn formats_address_in_every_style() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let format =
+        |body: serde_json::Value| TestRequest::post().uri("/address/format").set_json(body);
+
+    let full = json!({
+        "village_code": "32.73.02.1001",
+        "street": "Jl. Setiabudi No. 9",
+        "rt": "3",
+        "rw": "4",
+        "postal_code": "40141"
+    });
+    let expected = [
+        (
+            "single_line",
+            "Jl. Setiabudi No. 9, RT 003/RW 004, Kel. Hegarmanah, Kec. Cidadap, Kota Bandung, Jawa Barat 40141",
+        ),
+        (
+            "multi_line",
+            "Jl. Setiabudi No. 9 RT 003/RW 004\nKel. Hegarmanah, Kec. Cidadap\nKota Bandung\nJawa Barat 40141",
+        ),
+        (
+            "pos_indonesia",
+            "JL. SETIABUDI NO. 9 RT 003/RW 004\nKEL. HEGARMANAH\nKEC. CIDADAP\nKOTA BANDUNG\nJAWA BARAT 40141",
+        ),
+    ];
+    for (style, formatted) in expected {
+        let mut body = full.clone();
+        body["style"] = json!(style);
+        let reply = send(&app, format(body)).await;
+        assert_eq!(reply.status, StatusCode::OK);
+        let reply = reply.json();
+        assert_eq!(reply["style"], style);
+        assert_eq!(reply["formatted"], formatted);
+    }
+
+    // Only the village code, in the default style
+    let bare = send(&app, format(json!({"village_code": " 32.04.08.2001 "}))).await;
+    assert_eq!(
+        bare.json()["formatted"],
+        "Desa Lengkong, Kec. Bojongsoang, Kab. Bandung, Jawa Barat"
+    );
+}
+
+#[actix_web::test]
+async fn refuses_to_format_an_incomplete_hierarchy() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let format = |code: &str| {
+        TestRequest::post()
+            .uri("/address/format")
+            .set_json(json!({ "village_code": code }))
+    };
+
+    // Village still live, its district deleted underneath it
+    fixture.execute(&format!(
+        "UPDATE districts SET deleted_at = CURRENT_TIMESTAMP WHERE id = {}",
+        BOJONGSOANG
+    ));
+    let orphan = send(&app, format("32.04.08.2001")).await;
+    assert_eq!(orphan.status, StatusCode::NOT_FOUND);
+    assert_eq!(orphan.json(), json!({"error": "Village not found"}));
+
+    // Codes of other levels name no village
+    let district = send(&app, format("32.73.02")).await;
+    assert_eq!(district.status, StatusCode::NOT_FOUND);
+    // Villages outside the deleted district still format
+    let other = send(&app, format("32.73.02.1001")).await;
+    assert_eq!(other.status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn keeps_kota_in_street_names() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let parse = |address: &str| {
+        TestRequest::post()
+            .uri("/address/parse")
+            .set_json(json!({ "address": address }))
+    };
+
+    let reply = send(
+        &app,
+        parse("Jl. Kota Baru No. 3, Hegarmanah Kec. Cidadap Kota Bandung 40141"),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+    let parsed = reply.json();
+    assert_eq!(parsed["street"], "Jl. Kota Baru No. 3");
+    assert_eq!(parsed["postal_code"], "40141");
+    assert_eq!(parsed["village"]["code"], "32.73.02.1001");
+    assert_eq!(parsed["district"]["input"], "Cidadap");
+    assert_eq!(parsed["regency"]["code"], "32.73");
+    assert_eq!(parsed["regency"]["input"], "Bandung");
+    assert_eq!(parsed["unmatched"], json!([]));
+    assert_eq!(parsed["confidence"], 1.0);
+
+    // Not a known kota, so still part of the street
+    let street = send(&app, parse("Jl. Kota Baru Kota Lama, Kota Bandung")).await;
+    assert_eq!(street.json()["street"], "Jl. Kota Baru Kota Lama");
+}
+
+#[actix_web::test]
+async fn formats_address() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = send(
+        &app,
+        TestRequest::post().uri("/address/format").set_json(json!({
+            "village_code": "32.04.08.2002",
+            "street": "Jl. Raya Bojongsoang No. 12",
+            "postal_code": "40288"
+        })),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+    let formatted = reply.json();
+    assert_eq!(formatted["lines"][0], "Jl. Raya Bojongsoang No. 12");
+    assert!(formatted["formatted"]
+        .as_str()
+        .unwrap()
+        .contains("Bojongsoang"));
+
+    let missing = send(
+        &app,
+        TestRequest::post()
+            .uri("/address/format")
+            .set_json(json!({"village_code": "32.04.08.9999"})),
+    )
+    .await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.json(), json!({"error": "Village not found"}));
+}
@@ -0,0 +1,195 @@
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use serde_json::json;
+
+use super::common::{
+    get, send, Fixture, BOJONGSOANG, DESA_BOJONGSOANG, HEGARMANAH, LENGKONG, MISSING,
+};
+
+#[actix_web::test]
+async fn gets_village() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, &format!("/villages/{}", LENGKONG)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(
+        reply.json(),
+        json!({
+            "id": 2,
+            "version": 1,
+            "code": "32.04.08.2001",
+            "name": "Lengkong",
+            "district_id": 1
+        })
+    );
+
+    let missing = get(&app, &format!("/villages/{}", MISSING)).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.json(), json!({"error": "Village not found"}));
+}
+
+#[actix_web::test]
+async fn gets_village_by_code() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, "/villages/code/32.73.02.1001").await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.json()["id"], HEGARMANAH);
+
+    let missing = get(&app, "/villages/code/32.73.02.9999").await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn gets_full_admin_data() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let reply = get(&app, &format!("/villages/{}/details", DESA_BOJONGSOANG)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(
+        reply.json(),
+        json!({
+            "province_code": "32",
+            "province_name": "JAWA BARAT",
+            "city_code": "32.04",
+            "city_name": "KAB. BANDUNG",
+            "region_code": "32.04.08",
+            "region_name": "Bojongsoang",
+            "village_code": "32.04.08.2002",
+            "village_name": "Bojongsoang"
+        })
+    );
+
+    let missing = get(&app, &format!("/villages/{}/details", MISSING)).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(missing.json(), json!({"error": "Village not found"}));
+}
+
+#[actix_web::test]
+async fn upserts_village_by_code() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+    let upsert = |code: &str, name: &str| {
+        TestRequest::put()
+            .uri(&format!("/villages/code/{}", code))
+            .set_json(json!({ "name": name }))
+    };
+
+    let created = send(&app, upsert("32.04.08.2003", "Buahbatu")).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.json()["version"], 1);
+
+    let updated = send(&app, upsert("32.04.08.2001", "Lengkong Kulon")).await;
+    assert_eq!(updated.status, StatusCode::OK);
+    assert_eq!(updated.json()["version"], 2);
+
+    let bad_code = send(&app, upsert("32.04.08.20", "Buahbatu")).await;
+    assert_eq!(bad_code.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        bad_code.json()["fields"][0]["message"],
+        "must be a village code formatted as 99.99.99.9999"
+    );
+}
+
+#[actix_web::test]
+async fn creates_village() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let created = send(
+        &app,
+        TestRequest::post().uri("/villages").set_json(json!({
+            "code": "32.04.08.2003",
+            "name": "Buahbatu",
+            "district_id": BOJONGSOANG
+        })),
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.json(), json!({"id": 4}));
+
+    let empty_body = send(
+        &app,
+        TestRequest::post()
+            .uri("/villages")
+            .insert_header(header::ContentType::json()),
+    )
+    .await;
+    assert_eq!(empty_body.status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn updates_and_patches_village() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let moved = send(
+        &app,
+        TestRequest::put()
+            .uri(&format!("/villages/{}", LENGKONG))
+            .set_json(json!({
+                "code": "32.04.08.2001",
+                "name": "Lengkong",
+                "district_id": 3
+            })),
+    )
+    .await;
+    assert_eq!(moved.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        moved.json()["fields"][0]["message"],
+        "must start with the parent code 32.73.02."
+    );
+
+    let patched = send(
+        &app,
+        TestRequest::patch()
+            .uri(&format!("/villages/{}", LENGKONG))
+            .set_json(json!({"name": "Lengkong Wetan"})),
+    )
+    .await;
+    assert_eq!(patched.status, StatusCode::NO_CONTENT);
+    let village = get(&app, &format!("/villages/{}", LENGKONG)).await.json();
+    assert_eq!(village["name"], "Lengkong Wetan");
+    assert_eq!(village["district_id"], BOJONGSOANG);
+
+    let missing = send(
+        &app,
+        TestRequest::put()
+            .uri(&format!("/villages/{}", MISSING))
+            .set_json(json!({
+                "code": "32.04.08.2009",
+                "name": "X",
+                "district_id": BOJONGSOANG
+            })),
+    )
+    .await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn deletes_village() {
+    let fixture = Fixture::new();
+    let app = fixture.app().await;
+
+    let deleted = send(
+        &app,
+        TestRequest::delete().uri(&format!("/villages/{}", HEGARMANAH)),
+    )
+    .await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+    assert!(deleted.body.is_empty());
+    let details = get(&app, &format!("/villages/{}/details", HEGARMANAH)).await;
+    assert_eq!(details.status, StatusCode::NOT_FOUND);
+
+    let again = send(
+        &app,
+        TestRequest::delete().uri(&format!("/villages/{}", HEGARMANAH)),
+    )
+    .await;
+    assert_eq!(again.status, StatusCode::NOT_FOUND);
+    assert_eq!(again.json(), json!({"error": "Village not found"}));
+}